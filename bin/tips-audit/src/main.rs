@@ -5,11 +5,15 @@ use aws_sdk_s3::{Client as S3Client, config::Builder as S3ConfigBuilder};
 use clap::{Parser, ValueEnum};
 use rdkafka::consumer::Consumer;
 use std::net::SocketAddr;
+use std::time::Duration;
 use tips_audit_lib::{
-    KafkaAuditArchiver, KafkaAuditLogReader, S3EventReaderWriter, create_kafka_consumer,
+    KafkaAuditArchiver, KafkaAuditLogReader, KafkaConsumerLagCheck, S3BucketCheck,
+    S3EventReaderWriter, create_kafka_consumer,
 };
+use tips_core::health::{Readiness, bind_health_server};
 use tips_core::logger::init_logger_with_format;
 use tips_core::metrics::init_prometheus_exporter;
+use tips_core::shutdown::shutdown_signal;
 use tracing::info;
 
 #[derive(Debug, Clone, ValueEnum)]
@@ -54,6 +58,28 @@ struct Args {
     #[arg(long, env = "TIPS_AUDIT_METRICS_ADDR", default_value = "0.0.0.0:9002")]
     metrics_addr: SocketAddr,
 
+    #[arg(
+        long,
+        env = "TIPS_AUDIT_HEALTH_CHECK_ADDR",
+        default_value = "0.0.0.0:8081"
+    )]
+    health_check_addr: SocketAddr,
+
+    #[arg(
+        long,
+        env = "TIPS_AUDIT_READY_CHECK_TIMEOUT_MS",
+        default_value = "2000"
+    )]
+    ready_check_timeout_ms: u64,
+
+    /// Number of unarchived messages on the audit topic above which the archiver isn't ready
+    #[arg(
+        long,
+        env = "TIPS_AUDIT_READY_MAX_CONSUMER_LAG",
+        default_value = "10000"
+    )]
+    ready_max_consumer_lag: u64,
+
     #[arg(long, env = "TIPS_AUDIT_WORKER_POOL_SIZE", default_value = "80")]
     worker_pool_size: usize,
 
@@ -62,6 +88,10 @@ struct Args {
 
     #[arg(long, env = "TIPS_AUDIT_NOOP_ARCHIVE", default_value = "false")]
     noop_archive: bool,
+
+    /// Time in seconds to wait for in-flight S3 writes on shutdown
+    #[arg(long, env = "TIPS_AUDIT_SHUTDOWN_TIMEOUT_SECS", default_value = "30")]
+    shutdown_timeout_secs: u64,
 }
 
 #[tokio::main]
@@ -89,9 +119,27 @@ async fn main() -> Result<()> {
 
     let s3_client = create_s3_client(&args).await?;
     let s3_bucket = args.s3_bucket.clone();
-    let writer = S3EventReaderWriter::new(s3_client, s3_bucket);
+    let writer = S3EventReaderWriter::new(s3_client.clone(), s3_bucket.clone());
+
+    // a separate consumer in the archiver's group, used to look up its committed offsets
+    let lag_consumer = create_kafka_consumer(&args.kafka_properties_file)?;
+    let ready_check_timeout = Duration::from_millis(args.ready_check_timeout_ms);
+    let readiness = Readiness::new(ready_check_timeout)
+        .with_check(KafkaConsumerLagCheck::new(
+            lag_consumer,
+            args.kafka_topic.clone(),
+            args.ready_max_consumer_lag,
+            ready_check_timeout,
+        ))
+        .with_check(S3BucketCheck::new(s3_client, s3_bucket));
+    let (bound_health_addr, _health_handle) =
+        bind_health_server(args.health_check_addr, readiness).await?;
+    info!(
+        message = "Health check server started",
+        address = %bound_health_addr
+    );
 
-    let mut archiver = KafkaAuditArchiver::new(
+    let archiver = KafkaAuditArchiver::new(
         reader,
         writer,
         args.worker_pool_size,
@@ -101,7 +149,12 @@ async fn main() -> Result<()> {
 
     info!("Audit archiver initialized, starting main loop");
 
-    archiver.run().await
+    archiver
+        .run_until(
+            shutdown_signal(),
+            Duration::from_secs(args.shutdown_timeout_secs),
+        )
+        .await
 }
 
 async fn create_s3_client(args: &Args) -> Result<S3Client> {
@@ -1,22 +1,48 @@
 use account_abstraction_core::create_mempool_engine;
 use alloy_provider::ProviderBuilder;
+use aws_config::BehaviorVersion;
+use aws_sdk_s3::{Client as S3Client, config::Builder as S3ConfigBuilder};
 use clap::Parser;
-use jsonrpsee::server::Server;
 use op_alloy_network::Optimism;
 use rdkafka::ClientConfig;
-use rdkafka::producer::FutureProducer;
-use tips_audit_lib::{BundleEvent, KafkaBundleEventPublisher, connect_audit_to_publisher};
+use rdkafka::producer::{FutureProducer, Producer};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tips_audit_lib::{
+    AuditChannelConfig, BundleEvent, KafkaAuditLogReader, KafkaBundleEventPublisher,
+    S3EventReaderWriter, audit_channel, connect_audit_to_publisher,
+};
+use tips_core::health::{Readiness, bind_health_server};
 use tips_core::kafka::load_kafka_config_from_file;
 use tips_core::logger::init_logger_with_format;
 use tips_core::metrics::init_prometheus_exporter;
+use tips_core::shutdown::shutdown_signal;
 use tips_core::{AcceptedBundle, MeterBundleResponse};
 use tips_ingress_rpc_lib::Config;
-use tips_ingress_rpc_lib::connect_ingress_to_builder;
-use tips_ingress_rpc_lib::health::bind_health_server;
+use tips_ingress_rpc_lib::admin::{
+    AdminApiServer, AdminService, RuntimeControls, start_admin_server,
+};
+use tips_ingress_rpc_lib::auth::Authenticator;
+use tips_ingress_rpc_lib::builder::{BuilderDeliveryConfig, BuilderEndpoint};
+use tips_ingress_rpc_lib::builder_registry::{
+    BuilderAdminApiServer, BuilderAdminService, BuilderRegistry, watch_builders_file,
+};
+use tips_ingress_rpc_lib::health::{
+    BuilderCheck, DrainingCheck, KafkaProducerCheck, MempoolEngineCheck, SimulationHeadCheck,
+};
+use tips_ingress_rpc_lib::metering_cache::watch_state_changes;
 use tips_ingress_rpc_lib::queue::KafkaMessageQueue;
+use tips_ingress_rpc_lib::rate_limit::{RateLimiter, start_ingress_server};
 use tips_ingress_rpc_lib::service::{IngressApiServer, IngressService, Providers};
-use tokio::sync::{broadcast, mpsc};
-use tracing::info;
+use tips_ingress_rpc_lib::status::{BundleStatusApiServer, BundleStatusService};
+use tips_ingress_rpc_lib::subscriptions::{
+    BundleSubscriptionApiServer, BundleSubscriptionService, connect_audit_log_to_subscriptions,
+    create_audit_subscription_consumer,
+};
+use tokio::sync::{broadcast, oneshot};
+use tokio::time::{Instant, sleep, timeout_at};
+use tracing::{info, warn};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -39,6 +65,12 @@ async fn main() -> anyhow::Result<()> {
         health_check_address = %config.health_check_addr,
     );
 
+    if config.client_ip_hash_key.is_none() {
+        warn!(
+            message = "TIPS_INGRESS_CLIENT_IP_HASH_KEY is not set, client IPs are not recorded on bundle events"
+        );
+    }
+
     let providers = Providers {
         mempool: ProviderBuilder::new()
             .disable_recommended_fillers()
@@ -48,6 +80,16 @@ async fn main() -> anyhow::Result<()> {
             .disable_recommended_fillers()
             .network::<Optimism>()
             .connect_http(config.simulation_rpc),
+        simulation_fallbacks: config
+            .simulation_fallback_rpcs
+            .iter()
+            .map(|url| {
+                ProviderBuilder::new()
+                    .disable_recommended_fillers()
+                    .network::<Optimism>()
+                    .connect_http(url.clone())
+            })
+            .collect(),
         raw_tx_forward: config.raw_tx_forward_rpc.clone().map(|url| {
             ProviderBuilder::new()
                 .disable_recommended_fillers()
@@ -56,22 +98,71 @@ async fn main() -> anyhow::Result<()> {
         }),
     };
 
+    let simulation_provider = providers.simulation.clone();
+    let ready_check_timeout = Duration::from_millis(config.ready_check_timeout_ms);
+    let max_head_age = Duration::from_secs(config.ready_max_head_age_secs);
+    let mut readiness = Readiness::new(ready_check_timeout).with_check(SimulationHeadCheck::new(
+        "simulation:primary",
+        providers.simulation.clone(),
+        max_head_age,
+    ));
+    for (i, provider) in providers.simulation_fallbacks.iter().enumerate() {
+        readiness = readiness.with_check(SimulationHeadCheck::new(
+            format!("simulation:fallback-{}", i + 1),
+            provider.clone(),
+            max_head_age,
+        ));
+    }
+
     let ingress_client_config = ClientConfig::from_iter(load_kafka_config_from_file(
         &config.ingress_kafka_properties,
     )?);
 
     let queue_producer: FutureProducer = ingress_client_config.create()?;
 
-    let queue = KafkaMessageQueue::new(queue_producer);
+    readiness = readiness.with_check(KafkaProducerCheck::new(
+        "kafka:ingress",
+        queue_producer.clone(),
+        config.ingress_topic.clone(),
+        ready_check_timeout,
+    ));
+
+    let queue = KafkaMessageQueue::new(queue_producer.clone());
 
     let audit_client_config =
         ClientConfig::from_iter(load_kafka_config_from_file(&config.audit_kafka_properties)?);
 
     let audit_producer: FutureProducer = audit_client_config.create()?;
 
-    let audit_publisher = KafkaBundleEventPublisher::new(audit_producer, config.audit_topic);
-    let (audit_tx, audit_rx) = mpsc::unbounded_channel::<BundleEvent>();
-    connect_audit_to_publisher(audit_rx, audit_publisher);
+    readiness = readiness.with_check(KafkaProducerCheck::new(
+        "kafka:audit",
+        audit_producer.clone(),
+        config.audit_topic.clone(),
+        ready_check_timeout,
+    ));
+
+    let audit_publisher =
+        KafkaBundleEventPublisher::new(audit_producer.clone(), config.audit_topic.clone());
+    let (bundle_events_tx, _) =
+        broadcast::channel::<BundleEvent>(config.max_buffered_bundle_events);
+    let (audit_tx, audit_rx) = audit_channel(AuditChannelConfig {
+        capacity: config.audit_channel_capacity,
+        overflow_policy: config.audit_overflow_policy,
+        spill_file: config.audit_spill_file.clone(),
+        send_timeout: Duration::from_millis(config.audit_send_timeout_ms),
+    })?;
+    let audit_tx = audit_tx.with_subscribers(bundle_events_tx.clone());
+    let (stop_audit_publisher, audit_publisher_stopped) = oneshot::channel::<()>();
+    let mut audit_publisher_handle =
+        connect_audit_to_publisher(audit_rx, audit_publisher, async move {
+            let _ = audit_publisher_stopped.await;
+        });
+
+    if let Some(properties_file) = &config.audit_subscription_kafka_properties {
+        let consumer = create_audit_subscription_consumer(properties_file)?;
+        let reader = KafkaAuditLogReader::new(consumer, config.audit_topic.clone())?;
+        connect_audit_log_to_subscriptions(reader, bundle_events_tx.clone());
+    }
 
     let (mempool_engine, mempool_engine_handle) = if let Some(user_op_properties_file) =
         &config.user_operation_consumer_properties
@@ -95,23 +186,34 @@ async fn main() -> anyhow::Result<()> {
         );
         (None, None)
     };
+    if let Some(engine_handle) = &mempool_engine_handle {
+        readiness = readiness.with_check(MempoolEngineCheck::new(engine_handle.abort_handle()));
+    }
 
     let (builder_tx, _) =
         broadcast::channel::<MeterBundleResponse>(config.max_buffered_meter_bundle_responses);
     let (builder_backrun_tx, _) =
         broadcast::channel::<AcceptedBundle>(config.max_buffered_backrun_bundles);
+    let builder_registry = Arc::new(BuilderRegistry::new(
+        builder_tx.clone(),
+        builder_backrun_tx.clone(),
+        BuilderDeliveryConfig::from(&cfg),
+    ));
     config.builder_rpcs.iter().for_each(|builder_rpc| {
-        let metering_rx = builder_tx.subscribe();
-        let backrun_rx = builder_backrun_tx.subscribe();
-        connect_ingress_to_builder(metering_rx, backrun_rx, builder_rpc.clone());
+        builder_registry.add(BuilderEndpoint {
+            url: builder_rpc.clone(),
+            auth: None,
+        });
     });
+    if let Some(builders_file) = config.builders_file.clone() {
+        watch_builders_file(
+            builders_file,
+            builder_registry.clone(),
+            Duration::from_secs(config.builders_file_poll_interval_secs),
+        );
+    }
 
-    let health_check_addr = config.health_check_addr;
-    let (bound_health_addr, health_handle) = bind_health_server(health_check_addr).await?;
-    info!(
-        message = "Health check server started",
-        address = %bound_health_addr
-    );
+    readiness = readiness.with_check(BuilderCheck::new(builder_registry.clone()));
 
     let service = IngressService::new(
         providers,
@@ -120,24 +222,167 @@ async fn main() -> anyhow::Result<()> {
         builder_tx,
         builder_backrun_tx,
         mempool_engine.clone(),
-        cfg,
+        cfg.clone(),
     );
-    let bind_addr = format!("{}:{}", config.address, config.port);
 
-    let server = Server::builder().build(&bind_addr).await?;
-    let addr = server.local_addr()?;
-    let handle = server.start(service.into_rpc());
+    if config.metering_cache_size > 0 {
+        watch_state_changes(
+            service.metering_cache(),
+            simulation_provider,
+            Duration::from_millis(config.metering_cache_poll_interval_ms),
+        );
+    }
+
+    let readiness = readiness.with_check(DrainingCheck::new(service.runtime_controls()));
+    let (bound_health_addr, health_handle) =
+        bind_health_server(config.health_check_addr, readiness).await?;
+    info!(
+        message = "Health check server started",
+        address = %bound_health_addr
+    );
+
+    let admin_handle = match config.admin_addr {
+        Some(admin_addr) => {
+            let mut admin_module =
+                AdminService::new(service.runtime_controls(), service.bundle_cache(), cfg)
+                    .into_rpc();
+            admin_module.merge(BuilderAdminService::new(builder_registry.clone()).into_rpc())?;
+            let (bound_admin_addr, admin_handle) =
+                start_admin_server(admin_addr, config.admin_auth_tokens.clone(), admin_module)
+                    .await?;
+            info!(
+                message = "Admin RPC server started",
+                address = %bound_admin_addr
+            );
+            Some(admin_handle)
+        }
+        None => None,
+    };
+
+    let controls = service.runtime_controls();
+    let mut module = service.into_rpc();
+    if let Some(bucket) = config.audit_s3_bucket.clone() {
+        let s3_client = create_s3_client(config.audit_s3_endpoint.as_deref()).await;
+        let reader = S3EventReaderWriter::new(s3_client, bucket);
+        module.merge(BundleStatusService::new(Arc::new(reader)).into_rpc())?;
+        info!(message = "Bundle status methods enabled");
+    }
+    module.merge(
+        BundleSubscriptionService::new(bundle_events_tx, config.firehose_auth_tokens.clone())
+            .into_rpc(),
+    )?;
+
+    // Serves both HTTP and WebSocket, subscriptions are only available over WebSocket
+    let rate_limiter = RateLimiter::new(config.rate_limits.clone());
+    if !rate_limiter.is_empty() {
+        info!(message = "Rate limits enabled", limits = ?config.rate_limits);
+    }
+    let authenticator = match &config.auth_keys_file {
+        Some(path) => {
+            let authenticator = Authenticator::load(path, config.auth_required).await?;
+            info!(message = "Searcher authentication enabled", authenticator = ?authenticator);
+            Some(authenticator)
+        }
+        None if config.auth_required => {
+            anyhow::bail!("TIPS_INGRESS_AUTH_REQUIRED requires TIPS_INGRESS_AUTH_KEYS_FILE")
+        }
+        None => None,
+    };
+    let (addr, handle) = start_ingress_server(
+        SocketAddr::new(config.address, config.port),
+        rate_limiter,
+        authenticator,
+        config.trust_forwarded_for,
+        module,
+    )
+    .await?;
 
     info!(
         message = "Ingress RPC server started",
         address = %addr
     );
 
-    handle.stopped().await;
+    tokio::select! {
+        _ = shutdown_signal() => {}
+        _ = handle.clone().stopped() => {}
+    }
+
+    // Stop accepting submissions first, `/ready` reports the instance as draining meanwhile
+    let deadline = Instant::now() + Duration::from_secs(config.shutdown_timeout_secs);
+    controls.set_draining(true);
+    let _ = handle.stop();
+    if timeout_at(deadline, handle.stopped()).await.is_err() {
+        warn!(message = "RPC requests still in flight at the shutdown deadline");
+    }
+    if timeout_at(deadline, wait_for_submissions(&controls))
+        .await
+        .is_err()
+    {
+        warn!(
+            message = "Submissions still in flight at the shutdown deadline",
+            in_flight = controls.in_flight()
+        );
+    }
+
+    // The RPC server dropped its audit senders, the publisher stops once the channel is drained
+    if timeout_at(deadline, &mut audit_publisher_handle)
+        .await
+        .is_err()
+    {
+        warn!(message = "Audit events still buffered at the shutdown deadline are spilled");
+        let _ = stop_audit_publisher.send(());
+        let _ = audit_publisher_handle.await;
+    }
+    flush_producer("ingress", queue_producer, deadline).await;
+    flush_producer("audit", audit_producer, deadline).await;
+    if timeout_at(deadline, builder_registry.shutdown())
+        .await
+        .is_err()
+    {
+        warn!(message = "Builder delivery still running at the shutdown deadline");
+    }
+
     health_handle.abort();
+    if let Some(admin_handle) = admin_handle {
+        let _ = admin_handle.stop();
+    }
     if let Some(engine_handle) = mempool_engine_handle {
         engine_handle.abort();
     }
 
     Ok(())
 }
+
+async fn wait_for_submissions(controls: &RuntimeControls) {
+    while controls.in_flight() > 0 {
+        sleep(Duration::from_millis(50)).await;
+    }
+}
+
+/// Waits for the messages queued in a producer to be delivered, until the deadline
+async fn flush_producer(name: &'static str, producer: FutureProducer, deadline: Instant) {
+    let timeout = deadline.saturating_duration_since(Instant::now());
+    match tokio::task::spawn_blocking(move || producer.flush(timeout)).await {
+        Ok(Ok(())) => info!(message = "Flushed Kafka producer", producer = name),
+        Ok(Err(e)) => {
+            warn!(message = "Failed to flush Kafka producer", producer = name, error = %e)
+        }
+        Err(e) => {
+            warn!(message = "Failed to flush Kafka producer", producer = name, error = %e)
+        }
+    }
+}
+
+async fn create_s3_client(endpoint: Option<&str>) -> S3Client {
+    let config = aws_config::load_defaults(BehaviorVersion::latest()).await;
+    match endpoint {
+        Some(endpoint) => {
+            let s3_config = S3ConfigBuilder::from(&config)
+                .endpoint_url(endpoint)
+                .force_path_style(true)
+                .build();
+            S3Client::from_conf(s3_config)
+        }
+        None => S3Client::new(&config),
+    }
+}
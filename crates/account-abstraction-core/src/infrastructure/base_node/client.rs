@@ -0,0 +1,18 @@
+use alloy_provider::{Provider, RootProvider};
+use async_trait::async_trait;
+use op_alloy_network::Optimism;
+use serde_json::Value;
+use std::fmt::Debug;
+
+/// Sends JSON-RPC requests to a base node, either a single node or a pool of them
+#[async_trait]
+pub trait BaseNodeClient: Debug + Send + Sync {
+    async fn request(&self, method: &'static str, params: Value) -> anyhow::Result<Value>;
+}
+
+#[async_trait]
+impl BaseNodeClient for RootProvider<Optimism> {
+    async fn request(&self, method: &'static str, params: Value) -> anyhow::Result<Value> {
+        Ok(self.client().request(method, params).await?)
+    }
+}
@@ -1,25 +1,24 @@
 use crate::domain::types::{ValidationResult, VersionedUserOperation};
+use crate::infrastructure::base_node::client::BaseNodeClient;
 use crate::services::interfaces::user_op_validator::UserOperationValidator;
 use alloy_primitives::Address;
-use alloy_provider::{Provider, RootProvider};
 use async_trait::async_trait;
-use op_alloy_network::Optimism;
 use std::sync::Arc;
 use tokio::time::{Duration, timeout};
 
 #[derive(Debug, Clone)]
 pub struct BaseNodeValidator {
-    simulation_provider: Arc<RootProvider<Optimism>>,
+    simulation_client: Arc<dyn BaseNodeClient>,
     validate_user_operation_timeout: u64,
 }
 
 impl BaseNodeValidator {
     pub fn new(
-        simulation_provider: Arc<RootProvider<Optimism>>,
+        simulation_client: Arc<dyn BaseNodeClient>,
         validate_user_operation_timeout: u64,
     ) -> Self {
         Self {
-            simulation_provider,
+            simulation_client,
             validate_user_operation_timeout,
         }
     }
@@ -32,15 +31,15 @@ impl UserOperationValidator for BaseNodeValidator {
         user_operation: &VersionedUserOperation,
         entry_point: &Address,
     ) -> anyhow::Result<ValidationResult> {
+        let params = serde_json::to_value((user_operation, entry_point))?;
         let result = timeout(
             Duration::from_secs(self.validate_user_operation_timeout),
-            self.simulation_provider
-                .client()
-                .request("base_validateUserOperation", (user_operation, entry_point)),
+            self.simulation_client
+                .request("base_validateUserOperation", params),
         )
         .await;
 
-        let validation_result: ValidationResult = match result {
+        let validation_result = match result {
             Err(_) => {
                 return Err(anyhow::anyhow!("Timeout on requesting validation"));
             }
@@ -50,7 +49,7 @@ impl UserOperationValidator for BaseNodeValidator {
             Ok(Ok(v)) => v,
         };
 
-        Ok(validation_result)
+        Ok(serde_json::from_value(validation_result)?)
     }
 }
 
@@ -59,7 +58,9 @@ mod tests {
     use super::*;
 
     use alloy_primitives::{Address, Bytes, U256};
+    use alloy_provider::RootProvider;
     use alloy_rpc_types::erc4337::UserOperation;
+    use op_alloy_network::Optimism;
     use tokio::time::Duration;
     use wiremock::{Mock, MockServer, ResponseTemplate, matchers::method};
 
@@ -2,15 +2,79 @@ use crate::metrics::Metrics;
 use crate::reader::{Event, EventReader};
 use crate::storage::EventWriter;
 use anyhow::Result;
+use rdkafka::consumer::CommitMode;
+use std::collections::{BTreeSet, HashMap};
 use std::fmt;
+use std::future::Future;
 use std::marker::PhantomData;
 use std::sync::Arc;
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
-use tokio::sync::{Mutex, mpsc};
-use tokio::time::sleep;
+use tokio::sync::{Mutex, mpsc, watch};
+use tokio::task::{JoinError, JoinSet};
+use tokio::time::{sleep, timeout};
 use tracing::{error, info};
 
-/// Archives audit events from Kafka to S3 storage.
+const INITIAL_RETRY_BACKOFF: Duration = Duration::from_millis(100);
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(5);
+/// Attempts at writing an event before its worker fails, about five minutes of retries
+const MAX_WRITE_ATTEMPTS: usize = 64;
+
+/// Offsets read from a partition whose events are not written yet
+#[derive(Debug)]
+struct PartitionOffsets {
+    pending: BTreeSet<i64>,
+    /// Offset after the last one read
+    next: i64,
+    /// Offset reading resumes from after a restart
+    committed: i64,
+}
+
+/// Tracks the events read from each partition until they are written to storage. An offset is
+/// only committed once the events before it are written, so an event whose write fails is read
+/// again after a restart.
+#[derive(Debug, Default)]
+struct OffsetTracker {
+    partitions: HashMap<i32, PartitionOffsets>,
+}
+
+impl OffsetTracker {
+    fn read(&mut self, partition: i32, offset: i64) {
+        let offsets = self
+            .partitions
+            .entry(partition)
+            .or_insert_with(|| PartitionOffsets {
+                pending: BTreeSet::new(),
+                next: offset,
+                committed: offset,
+            });
+        offsets.pending.insert(offset);
+        offsets.next = offsets.next.max(offset + 1);
+    }
+
+    /// Marks an event written, returning the new offset to commit once every event before it is
+    /// written
+    fn written(&mut self, partition: i32, offset: i64) -> Option<i64> {
+        let offsets = self.partitions.get_mut(&partition)?;
+        offsets.pending.remove(&offset);
+        let commit = offsets.pending.first().copied().unwrap_or(offsets.next);
+        if commit <= offsets.committed {
+            return None;
+        }
+        offsets.committed = commit;
+        Some(commit)
+    }
+
+    /// The offset each partition resumes from after a restart
+    fn committed(&self) -> impl Iterator<Item = (i32, i64)> + '_ {
+        self.partitions
+            .iter()
+            .map(|(partition, offsets)| (*partition, offsets.committed))
+    }
+}
+
+/// Archives audit events from Kafka to S3 storage. Offsets are committed as events are written,
+/// never past an event that isn't written yet. Failed writes are retried with backoff, and the
+/// archiver fails if an event can't be written after `MAX_WRITE_ATTEMPTS` attempts.
 pub struct KafkaAuditArchiver<R, W>
 where
     R: EventReader,
@@ -18,6 +82,12 @@ where
 {
     reader: R,
     event_tx: mpsc::Sender<Event>,
+    /// Partition and offset of every event the workers wrote
+    written_rx: mpsc::UnboundedReceiver<(i32, i64)>,
+    offsets: OffsetTracker,
+    workers: JoinSet<Result<()>>,
+    /// Set once shutdown starts, failed writes are no longer retried
+    stopping_tx: watch::Sender<bool>,
     metrics: Metrics,
     _phantom: PhantomData<W>,
 }
@@ -46,11 +116,15 @@ where
         noop_archive: bool,
     ) -> Self {
         let (event_tx, event_rx) = mpsc::channel(channel_buffer_size);
+        let (written_tx, written_rx) = mpsc::unbounded_channel();
+        let (stopping_tx, stopping_rx) = watch::channel(false);
         let metrics = Metrics::default();
 
-        Self::spawn_workers(
+        let workers = Self::spawn_workers(
             writer,
             event_rx,
+            written_tx,
+            stopping_rx,
             metrics.clone(),
             worker_pool_size,
             noop_archive,
@@ -59,6 +133,10 @@ where
         Self {
             reader,
             event_tx,
+            written_rx,
+            offsets: OffsetTracker::default(),
+            workers,
+            stopping_tx,
             metrics,
             _phantom: PhantomData,
         }
@@ -67,18 +145,23 @@ where
     fn spawn_workers(
         writer: W,
         event_rx: mpsc::Receiver<Event>,
+        written_tx: mpsc::UnboundedSender<(i32, i64)>,
+        stopping_rx: watch::Receiver<bool>,
         metrics: Metrics,
         worker_pool_size: usize,
         noop_archive: bool,
-    ) {
+    ) -> JoinSet<Result<()>> {
         let event_rx = Arc::new(Mutex::new(event_rx));
 
+        let mut workers = JoinSet::new();
         for worker_id in 0..worker_pool_size {
             let writer = writer.clone();
             let metrics = metrics.clone();
             let event_rx = event_rx.clone();
+            let written_tx = written_tx.clone();
+            let mut stopping_rx = stopping_rx.clone();
 
-            tokio::spawn(async move {
+            workers.spawn(async move {
                 loop {
                     let event = {
                         let mut rx = event_rx.lock().await;
@@ -87,7 +170,7 @@ where
 
                     match event {
                         Some(event) => {
-                            let archive_start = Instant::now();
+                            let position = (event.partition, event.offset);
                             // tmp: only use this to clear kafka consumer offset
                             // TODO: use debug! later
                             if noop_archive {
@@ -100,64 +183,403 @@ where
                                 );
                                 metrics.events_processed.increment(1);
                                 metrics.in_flight_archive_tasks.decrement(1.0);
+                                let _ = written_tx.send(position);
                                 continue;
                             }
-                            if let Err(e) = writer.archive_event(event).await {
-                                error!(worker_id, error = %e, "Failed to write event");
-                            } else {
-                                metrics
-                                    .archive_event_duration
-                                    .record(archive_start.elapsed().as_secs_f64());
-                                metrics.events_processed.increment(1);
-                            }
+                            let written =
+                                write_event(&writer, event, &metrics, &mut stopping_rx, worker_id)
+                                    .await;
                             metrics.in_flight_archive_tasks.decrement(1.0);
+                            match written {
+                                Ok(true) => {
+                                    let _ = written_tx.send(position);
+                                }
+                                // an event given up on at shutdown is never committed, so it's
+                                // archived again after a restart
+                                Ok(false) => {}
+                                Err(e) => return Err(e),
+                            }
                         }
                         None => {
                             info!(worker_id, "Worker stopped - channel closed");
-                            break;
+                            return Ok(());
                         }
                     }
                 }
             });
         }
+        workers
     }
 
-    /// Runs the archiver loop, reading events and writing them to storage.
+    /// Runs the archiver loop, reading events and writing them to storage. Fails once a worker
+    /// fails to write an event.
     pub async fn run(&mut self) -> Result<()> {
         loop {
             let read_start = Instant::now();
-            match self.reader.read_event().await {
-                Ok(event) => {
-                    self.metrics
-                        .kafka_read_duration
-                        .record(read_start.elapsed().as_secs_f64());
-
-                    let now_ms = SystemTime::now()
-                        .duration_since(UNIX_EPOCH)
-                        .unwrap_or_default()
-                        .as_millis() as i64;
-                    let event_age_ms = now_ms.saturating_sub(event.timestamp);
-                    self.metrics.event_age.record(event_age_ms as f64);
-
-                    self.metrics.in_flight_archive_tasks.increment(1.0);
-                    if let Err(e) = self.event_tx.send(event).await {
-                        error!(error = %e, "Failed to send event to worker pool");
-                        self.metrics.in_flight_archive_tasks.decrement(1.0);
-                    }
+            tokio::select! {
+                Some(result) = self.workers.join_next() => return Err(worker_stopped(result)),
+                Some((partition, offset)) = self.written_rx.recv() => {
+                    self.handle_written(partition, offset).await;
+                }
+                result = self.reader.read_event() => self.handle_read(result, read_start).await,
+            }
+        }
+    }
 
-                    let commit_start = Instant::now();
-                    if let Err(e) = self.reader.commit().await {
-                        error!(error = %e, "Failed to commit message");
-                    }
-                    self.metrics
-                        .kafka_commit_duration
-                        .record(commit_start.elapsed().as_secs_f64());
+    /// Runs the archiver loop until `shutdown` completes. Reading then stops, and the events
+    /// already read are written to storage before their offsets are committed. Failed writes
+    /// are no longer retried. Fails if the writes don't complete within `drain_timeout`, or once
+    /// a worker fails to write an event before `shutdown` completes.
+    pub async fn run_until<F>(mut self, shutdown: F, drain_timeout: Duration) -> Result<()>
+    where
+        F: Future<Output = ()>,
+    {
+        tokio::pin!(shutdown);
+        loop {
+            let read_start = Instant::now();
+            tokio::select! {
+                biased;
+                _ = &mut shutdown => break,
+                Some(result) = self.workers.join_next() => return Err(worker_stopped(result)),
+                Some((partition, offset)) = self.written_rx.recv() => {
+                    self.handle_written(partition, offset).await;
                 }
-                Err(e) => {
-                    error!(error = %e, "Error reading events");
-                    sleep(Duration::from_secs(1)).await;
+                result = self.reader.read_event() => self.handle_read(result, read_start).await,
+            }
+        }
+
+        info!("Stopped reading events, waiting for in-flight writes");
+        let Self {
+            mut reader,
+            event_tx,
+            mut written_rx,
+            mut offsets,
+            mut workers,
+            stopping_tx,
+            ..
+        } = self;
+        stopping_tx.send_replace(true);
+        // workers stop once the channel is closed and the events in it are written
+        drop(event_tx);
+        let drained = timeout(drain_timeout, async {
+            while let Some(result) = workers.join_next().await {
+                match result {
+                    Ok(Ok(())) => {}
+                    Ok(Err(e)) => error!(error = %e, "Archive worker failed"),
+                    Err(e) => error!(error = %e, "Archive worker failed"),
+                }
+            }
+        })
+        .await;
+        if drained.is_err() {
+            anyhow::bail!("Timed out waiting for in-flight writes to complete");
+        }
+
+        // the workers are stopped, so this ends with the last event they wrote
+        while let Some((partition, offset)) = written_rx.recv().await {
+            offsets.written(partition, offset);
+        }
+        // the commits made while running are asynchronous, so every partition is committed again
+        // and only considered done once the broker has it
+        for (partition, offset) in offsets.committed() {
+            reader.commit(partition, offset, CommitMode::Sync).await?;
+        }
+        info!("In-flight writes complete, offsets committed");
+        Ok(())
+    }
+
+    async fn handle_read(&mut self, result: Result<Event>, read_start: Instant) {
+        match result {
+            Ok(event) => {
+                self.metrics
+                    .kafka_read_duration
+                    .record(read_start.elapsed().as_secs_f64());
+
+                let now_ms = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_millis() as i64;
+                let event_age_ms = now_ms.saturating_sub(event.timestamp);
+                self.metrics.event_age.record(event_age_ms as f64);
+
+                self.offsets.read(event.partition, event.offset);
+                self.metrics.in_flight_archive_tasks.increment(1.0);
+                if let Err(e) = self.event_tx.send(event).await {
+                    error!(error = %e, "Failed to send event to worker pool");
+                    self.metrics.in_flight_archive_tasks.decrement(1.0);
+                }
+            }
+            Err(e) => {
+                error!(error = %e, "Error reading events");
+                sleep(Duration::from_secs(1)).await;
+            }
+        }
+    }
+
+    async fn handle_written(&mut self, partition: i32, offset: i64) {
+        let Some(commit_offset) = self.offsets.written(partition, offset) else {
+            return;
+        };
+        let commit_start = Instant::now();
+        if let Err(e) = self
+            .reader
+            .commit(partition, commit_offset, CommitMode::Async)
+            .await
+        {
+            error!(partition, offset = commit_offset, error = %e, "Failed to commit offset");
+        }
+        self.metrics
+            .kafka_commit_duration
+            .record(commit_start.elapsed().as_secs_f64());
+    }
+}
+
+/// Writes `event`, retrying with backoff until the write succeeds or `stopping_rx` is set.
+/// Returns whether the event was written, and fails after `MAX_WRITE_ATTEMPTS` failed attempts.
+async fn write_event<W: EventWriter>(
+    writer: &W,
+    event: Event,
+    metrics: &Metrics,
+    stopping_rx: &mut watch::Receiver<bool>,
+    worker_id: usize,
+) -> Result<bool> {
+    let (partition, offset) = (event.partition, event.offset);
+    let mut backoff = INITIAL_RETRY_BACKOFF;
+    let mut attempts = 0;
+    loop {
+        let archive_start = Instant::now();
+        match writer.archive_event(event.clone()).await {
+            Ok(()) => {
+                metrics
+                    .archive_event_duration
+                    .record(archive_start.elapsed().as_secs_f64());
+                metrics.events_processed.increment(1);
+                return Ok(true);
+            }
+            Err(e) => {
+                attempts += 1;
+                metrics.failed_archive_tasks.increment(1);
+                error!(worker_id, partition, offset, attempts, error = %e, "Failed to write event");
+                if attempts >= MAX_WRITE_ATTEMPTS {
+                    return Err(e.context(format!(
+                        "Failed to write event at offset {offset} of partition {partition} after \
+                         {attempts} attempts"
+                    )));
                 }
             }
         }
+
+        tokio::select! {
+            _ = stopping_rx.wait_for(|stopping| *stopping) => return Ok(false),
+            _ = sleep(backoff) => {}
+        }
+        backoff = (backoff * 2).min(MAX_RETRY_BACKOFF);
+    }
+}
+
+fn worker_stopped(result: Result<Result<()>, JoinError>) -> anyhow::Error {
+    match result {
+        Ok(Ok(())) => anyhow::anyhow!("Archive worker stopped"),
+        Ok(Err(e)) => e,
+        Err(e) => anyhow::anyhow!("Archive worker failed: {e}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{BundleEvent, DropReason};
+    use async_trait::async_trait;
+    use std::collections::VecDeque;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use uuid::Uuid;
+
+    /// A commit made by the archiver, and whether it was synchronous
+    type Commit = (i32, i64, bool);
+
+    struct QueueReader {
+        events: VecDeque<Event>,
+        commits: Arc<std::sync::Mutex<Vec<Commit>>>,
+    }
+
+    #[async_trait]
+    impl EventReader for QueueReader {
+        async fn read_event(&mut self) -> Result<Event> {
+            match self.events.pop_front() {
+                Some(event) => Ok(event),
+                None => std::future::pending().await,
+            }
+        }
+
+        async fn commit(&mut self, partition: i32, offset: i64, mode: CommitMode) -> Result<()> {
+            let sync = matches!(mode, CommitMode::Sync);
+            self.commits.lock().unwrap().push((partition, offset, sync));
+            Ok(())
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct SlowWriter {
+        written: Arc<std::sync::Mutex<Vec<String>>>,
+        /// Key of the event whose writes fail, and how many times they fail
+        failing: Option<(&'static str, usize)>,
+        /// Writes attempted for the failing event
+        attempts: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl EventWriter for SlowWriter {
+        async fn archive_event(&self, event: Event) -> Result<()> {
+            sleep(Duration::from_millis(50)).await;
+            if let Some((key, times)) = self.failing {
+                if key == event.key && self.attempts.fetch_add(1, Ordering::SeqCst) < times {
+                    anyhow::bail!("write failed");
+                }
+            }
+            self.written.lock().unwrap().push(event.key);
+            Ok(())
+        }
+    }
+
+    fn events(keys: &[&str]) -> VecDeque<Event> {
+        keys.iter()
+            .enumerate()
+            .map(|(offset, key)| Event {
+                key: key.to_string(),
+                timestamp: 0,
+                partition: 0,
+                offset: offset as i64,
+                event: BundleEvent::Dropped {
+                    bundle_id: Uuid::new_v5(&Uuid::NAMESPACE_OID, key.as_bytes()),
+                    reason: DropReason::TimedOut,
+                },
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_offset_tracker_commits_contiguous_writes() {
+        let mut offsets = OffsetTracker::default();
+        for offset in 10..13 {
+            offsets.read(0, offset);
+        }
+        offsets.read(1, 5);
+
+        // 10 isn't written yet
+        assert_eq!(offsets.written(0, 11), None);
+        assert_eq!(offsets.written(0, 10), Some(12));
+        assert_eq!(offsets.written(1, 5), Some(6));
+        assert_eq!(offsets.written(0, 12), Some(13));
+        assert_eq!(offsets.written(2, 0), None);
+    }
+
+    #[tokio::test]
+    async fn test_run_until_drains_in_flight_writes() {
+        let commits = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let reader = QueueReader {
+            events: events(&["a", "b", "c"]),
+            commits: commits.clone(),
+        };
+        let writer = SlowWriter::default();
+        let archiver = KafkaAuditArchiver::new(reader, writer.clone(), 2, 10, false);
+
+        archiver
+            .run_until(sleep(Duration::from_millis(10)), Duration::from_secs(5))
+            .await
+            .unwrap();
+
+        let mut written = writer.written.lock().unwrap().clone();
+        written.sort();
+        assert_eq!(written, vec!["a", "b", "c"]);
+        // nothing is committed before it's written, and everything is once the writes completed
+        assert_eq!(*commits.lock().unwrap(), vec![(0, 3, true)]);
+    }
+
+    #[tokio::test]
+    async fn test_commits_only_written_events() {
+        let commits = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let reader = QueueReader {
+            events: events(&["a", "b", "c"]),
+            commits: commits.clone(),
+        };
+        let writer = SlowWriter {
+            failing: Some(("b", usize::MAX)),
+            ..Default::default()
+        };
+        let archiver = KafkaAuditArchiver::new(reader, writer.clone(), 1, 10, false);
+
+        archiver
+            .run_until(sleep(Duration::from_millis(200)), Duration::from_secs(5))
+            .await
+            .unwrap();
+
+        // "b" is retried until shutdown starts
+        assert!(writer.attempts.load(Ordering::SeqCst) >= 2);
+        assert_eq!(*writer.written.lock().unwrap(), vec!["a", "c"]);
+        // "b" is read again after a restart, along with "c" after it
+        assert_eq!(*commits.lock().unwrap(), vec![(0, 1, false), (0, 1, true)]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_retries_failed_writes() {
+        let commits = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let reader = QueueReader {
+            events: events(&["a", "b", "c"]),
+            commits: commits.clone(),
+        };
+        let writer = SlowWriter {
+            failing: Some(("b", 3)),
+            ..Default::default()
+        };
+        let archiver = KafkaAuditArchiver::new(reader, writer.clone(), 1, 10, false);
+
+        archiver
+            .run_until(sleep(Duration::from_secs(60)), Duration::from_secs(5))
+            .await
+            .unwrap();
+
+        assert_eq!(writer.attempts.load(Ordering::SeqCst), 4);
+        assert_eq!(*writer.written.lock().unwrap(), vec!["a", "b", "c"]);
+        assert_eq!(
+            *commits.lock().unwrap(),
+            vec![(0, 1, false), (0, 2, false), (0, 3, false), (0, 3, true)]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_fails_when_write_never_succeeds() {
+        let commits = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let reader = QueueReader {
+            events: events(&["a", "b", "c"]),
+            commits: commits.clone(),
+        };
+        let writer = SlowWriter {
+            failing: Some(("b", usize::MAX)),
+            ..Default::default()
+        };
+        let archiver = KafkaAuditArchiver::new(reader, writer.clone(), 1, 10, false);
+
+        let result = archiver
+            .run_until(sleep(Duration::from_secs(3600)), Duration::from_secs(5))
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!(writer.attempts.load(Ordering::SeqCst), MAX_WRITE_ATTEMPTS);
+        // the offset of "b" is never committed, so it's read again after the restart
+        assert_eq!(*commits.lock().unwrap(), vec![(0, 1, false)]);
+    }
+
+    #[tokio::test]
+    async fn test_run_until_drain_timeout() {
+        let reader = QueueReader {
+            events: events(&["a"]),
+            commits: Arc::default(),
+        };
+        let archiver = KafkaAuditArchiver::new(reader, SlowWriter::default(), 1, 10, false);
+
+        let result = archiver
+            .run_until(sleep(Duration::from_millis(10)), Duration::from_millis(1))
+            .await;
+        assert!(result.is_err());
     }
 }
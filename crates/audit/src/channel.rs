@@ -0,0 +1,787 @@
+use crate::metrics::ChannelMetrics;
+use crate::publisher::BundleEventPublisher;
+use crate::types::BundleEvent;
+use anyhow::{Result, bail};
+use serde::{Serialize, Serializer};
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::OpenOptions;
+use std::future::Future;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{Notify, broadcast, mpsc::error::TryRecvError};
+use tokio::time::{Duration, Instant, sleep, sleep_until, timeout_at};
+use tracing::{error, info, warn};
+
+/// Failed publish attempts after which an event is spilled instead of retried.
+const SPILL_AFTER_ATTEMPTS: u32 = 3;
+const INITIAL_RETRY_BACKOFF: Duration = Duration::from_millis(100);
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(5);
+/// How often the spill file is checked for events to replay.
+const SPILL_REPLAY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// What happens to a bundle event sent while the audit channel is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverflowPolicy {
+    /// Wait for room in the channel, slowing down the sender, up to the send timeout.
+    #[default]
+    Block,
+    /// Drop the oldest buffered event to make room.
+    DropOldest,
+    /// Append the event to the spill file, it is published once Kafka recovers.
+    Spill,
+}
+
+impl FromStr for OverflowPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "block" => Ok(Self::Block),
+            "drop-oldest" => Ok(Self::DropOldest),
+            "spill" => Ok(Self::Spill),
+            _ => Err(format!(
+                "Invalid overflow policy: '{s}'. Valid options: block, drop-oldest, spill"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for OverflowPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Block => f.write_str("block"),
+            Self::DropOldest => f.write_str("drop-oldest"),
+            Self::Spill => f.write_str("spill"),
+        }
+    }
+}
+
+impl Serialize for OverflowPolicy {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Configuration of the audit channel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditChannelConfig {
+    /// Maximum number of events buffered in memory.
+    pub capacity: usize,
+    /// What happens to events sent while the channel is full.
+    pub overflow_policy: OverflowPolicy,
+    /// Append-only file events are spilled to, required by the spill policy. When set, events
+    /// that keep failing to publish are spilled too instead of being retried indefinitely.
+    pub spill_file: Option<PathBuf>,
+    /// Longest a sender waits for room in the channel. The event is then spilled if a spill file
+    /// is configured, and dropped otherwise.
+    pub send_timeout: Duration,
+}
+
+/// Append-only file of JSON encoded events, one per line. A replay moves the file aside first, so
+/// events spilled in the meantime go to a new file.
+#[derive(Debug)]
+struct SpillFile {
+    path: PathBuf,
+    replay_path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl SpillFile {
+    fn new(path: PathBuf) -> Self {
+        let mut replay_path = path.clone().into_os_string();
+        replay_path.push(".replay");
+        Self {
+            path,
+            replay_path: replay_path.into(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    fn encode(events: &[BundleEvent]) -> Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        for event in events {
+            serde_json::to_writer(&mut buf, event)?;
+            buf.push(b'\n');
+        }
+        Ok(buf)
+    }
+
+    /// Appends the events and syncs the file, blocking the current thread
+    fn append_blocking(&self, events: &[BundleEvent]) -> Result<()> {
+        let buf = Self::encode(events)?;
+        let _guard = self.lock.lock().unwrap();
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(&buf)?;
+        file.sync_data()?;
+        Ok(())
+    }
+
+    async fn append(self: &Arc<Self>, events: &[BundleEvent]) -> Result<()> {
+        let spill = self.clone();
+        let events = events.to_vec();
+        tokio::task::spawn_blocking(move || spill.append_blocking(&events)).await?
+    }
+
+    /// Moves the spilled events aside and returns the file to replay them from, if any. A file
+    /// left over from an interrupted replay is replayed first.
+    fn take_for_replay(&self) -> Result<Option<PathBuf>> {
+        let _guard = self.lock.lock().unwrap();
+        if self.replay_path.exists() {
+            return Ok(Some(self.replay_path.clone()));
+        }
+        match std::fs::metadata(&self.path) {
+            Ok(metadata) if metadata.len() > 0 => {
+                std::fs::rename(&self.path, &self.replay_path)?;
+                Ok(Some(self.replay_path.clone()))
+            }
+            Ok(_) => Ok(None),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct State {
+    events: VecDeque<BundleEvent>,
+    senders: usize,
+    closed: bool,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    capacity: usize,
+    overflow_policy: OverflowPolicy,
+    spill: Option<Arc<SpillFile>>,
+    send_timeout: Duration,
+    /// Wakes the receiver when an event is buffered or the last sender is dropped
+    item_ready: Notify,
+    /// Wakes blocked senders when an event is received or the receiver is dropped
+    space_ready: Notify,
+    metrics: ChannelMetrics,
+}
+
+impl Shared {
+    fn spill_path(&self) -> Option<&Path> {
+        self.spill.as_ref().map(|spill| spill.path.as_path())
+    }
+
+    async fn spill(&self, events: &[BundleEvent]) -> Result<()> {
+        let Some(spill) = &self.spill else {
+            bail!("No spill file configured");
+        };
+        match spill.append(events).await {
+            Ok(()) => {
+                self.metrics.events_spilled.increment(events.len() as u64);
+                Ok(())
+            }
+            Err(e) => {
+                self.metrics.spill_failures.increment(1);
+                error!(error = %e, "failed to spill audit events");
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Creates a bounded channel buffering bundle events until they are published to the audit log.
+/// Events sent while it is full are handled according to the configured [`OverflowPolicy`].
+pub fn audit_channel(config: AuditChannelConfig) -> Result<(AuditSender, AuditReceiver)> {
+    if config.capacity == 0 {
+        bail!("The audit channel capacity must be at least 1");
+    }
+    if config.overflow_policy == OverflowPolicy::Spill && config.spill_file.is_none() {
+        bail!("The spill overflow policy requires a spill file");
+    }
+
+    let shared = Arc::new(Shared {
+        state: Mutex::new(State {
+            events: VecDeque::with_capacity(config.capacity),
+            senders: 1,
+            closed: false,
+        }),
+        capacity: config.capacity,
+        overflow_policy: config.overflow_policy,
+        spill: config.spill_file.map(|path| Arc::new(SpillFile::new(path))),
+        send_timeout: config.send_timeout,
+        item_ready: Notify::new(),
+        space_ready: Notify::new(),
+        metrics: ChannelMetrics::default(),
+    });
+
+    let sender = AuditSender {
+        shared: shared.clone(),
+        subscribers: None,
+    };
+    Ok((sender, AuditReceiver { shared }))
+}
+
+/// Sending half of the audit channel, see [`audit_channel`].
+pub struct AuditSender {
+    shared: Arc<Shared>,
+    subscribers: Option<broadcast::Sender<BundleEvent>>,
+}
+
+impl fmt::Debug for AuditSender {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AuditSender")
+            .field("capacity", &self.shared.capacity)
+            .field("overflow_policy", &self.shared.overflow_policy)
+            .field("spill_file", &self.shared.spill_path())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Clone for AuditSender {
+    fn clone(&self) -> Self {
+        self.shared.state.lock().unwrap().senders += 1;
+        Self {
+            shared: self.shared.clone(),
+            subscribers: self.subscribers.clone(),
+        }
+    }
+}
+
+impl Drop for AuditSender {
+    fn drop(&mut self) {
+        let mut state = self.shared.state.lock().unwrap();
+        state.senders -= 1;
+        if state.senders == 0 {
+            self.shared.item_ready.notify_one();
+        }
+    }
+}
+
+impl AuditSender {
+    /// Also broadcasts every event sent through this sender, e.g. to stream them to subscribers.
+    pub fn with_subscribers(mut self, subscribers: broadcast::Sender<BundleEvent>) -> Self {
+        self.subscribers = Some(subscribers);
+        self
+    }
+
+    /// Buffers an event, applying the overflow policy when the channel is full. Fails when the
+    /// receiver was dropped, or the channel stayed full for the send timeout, and the event
+    /// couldn't be spilled.
+    pub async fn send(&self, event: BundleEvent) -> Result<()> {
+        if let Some(subscribers) = &self.subscribers {
+            // Sending only fails when nobody is subscribed
+            let _ = subscribers.send(event.clone());
+        }
+
+        let shared = &self.shared;
+        let mut blocked_since = None;
+        loop {
+            // Registered before checking for room so a receive in between isn't missed
+            let space_ready = shared.space_ready.notified();
+            let closed = {
+                let mut state = shared.state.lock().unwrap();
+                let has_room = state.events.len() < shared.capacity
+                    || shared.overflow_policy == OverflowPolicy::DropOldest;
+                if !state.closed && has_room {
+                    if state.events.len() >= shared.capacity {
+                        state.events.pop_front();
+                        shared.metrics.events_dropped_overflow.increment(1);
+                    }
+                    state.events.push_back(event);
+                    shared.metrics.queue_depth.set(state.events.len() as f64);
+                    drop(state);
+
+                    shared.item_ready.notify_one();
+                    if let Some(blocked_since) = blocked_since {
+                        shared
+                            .metrics
+                            .send_blocked_duration
+                            .record(blocked_since.elapsed().as_secs_f64());
+                    }
+                    return Ok(());
+                }
+                state.closed
+            };
+
+            let spill = closed || shared.overflow_policy == OverflowPolicy::Spill;
+            if spill
+                && shared.spill.is_some()
+                && shared.spill(std::slice::from_ref(&event)).await.is_ok()
+            {
+                return Ok(());
+            }
+            if closed {
+                shared.metrics.events_dropped_closed.increment(1);
+                bail!("Audit channel closed, event dropped");
+            }
+
+            // Full, or the spill file couldn't be written, wait for room
+            let deadline = *blocked_since.get_or_insert_with(Instant::now) + shared.send_timeout;
+            if timeout_at(deadline, space_ready).await.is_err() {
+                if shared.spill.is_some()
+                    && shared.spill(std::slice::from_ref(&event)).await.is_ok()
+                {
+                    return Ok(());
+                }
+                shared.metrics.events_dropped_timeout.increment(1);
+                bail!("Timed out waiting for room in the audit channel, event dropped");
+            }
+        }
+    }
+}
+
+/// Receiving half of the audit channel, see [`audit_channel`]. Events still buffered when it is
+/// dropped are lost, [`AuditReceiver::publish_to`] spills them when it is stopped instead.
+pub struct AuditReceiver {
+    shared: Arc<Shared>,
+}
+
+impl fmt::Debug for AuditReceiver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AuditReceiver")
+            .field("capacity", &self.shared.capacity)
+            .field("overflow_policy", &self.shared.overflow_policy)
+            .field("spill_file", &self.shared.spill_path())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for AuditReceiver {
+    fn drop(&mut self) {
+        let remaining = self.close();
+        if !remaining.is_empty() {
+            error!(events = remaining.len(), "dropped buffered audit events");
+            self.shared
+                .metrics
+                .events_dropped_closed
+                .increment(remaining.len() as u64);
+        }
+    }
+}
+
+impl AuditReceiver {
+    /// Receives the next event, returns `None` once every sender is dropped and the channel is
+    /// empty.
+    pub async fn recv(&mut self) -> Option<BundleEvent> {
+        loop {
+            let item_ready = self.shared.item_ready.notified();
+            match self.try_recv() {
+                Ok(event) => return Some(event),
+                Err(TryRecvError::Disconnected) => return None,
+                Err(TryRecvError::Empty) => item_ready.await,
+            }
+        }
+    }
+
+    /// Receives the next event if one is buffered.
+    pub fn try_recv(&mut self) -> Result<BundleEvent, TryRecvError> {
+        let mut state = self.shared.state.lock().unwrap();
+        match state.events.pop_front() {
+            Some(event) => {
+                self.shared
+                    .metrics
+                    .queue_depth
+                    .set(state.events.len() as f64);
+                drop(state);
+                self.shared.space_ready.notify_one();
+                Ok(event)
+            }
+            None if state.senders == 0 => Err(TryRecvError::Disconnected),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    /// Closes the channel and takes the events still buffered. Senders spill or drop the events
+    /// sent afterwards.
+    fn close(&mut self) -> Vec<BundleEvent> {
+        let remaining = {
+            let mut state = self.shared.state.lock().unwrap();
+            state.closed = true;
+            state.events.drain(..).collect()
+        };
+        self.shared.space_ready.notify_waiters();
+        self.shared.metrics.queue_depth.set(0.0);
+        remaining
+    }
+
+    /// Publishes the events sent through the channel until every sender is dropped, or `stop`
+    /// completes. Failed publishes are retried with backoff, or spilled after a few attempts when
+    /// a spill file is configured. Spilled events are replayed periodically. Once stopped, the
+    /// events not published yet are spilled if a spill file is configured, and dropped otherwise.
+    pub async fn publish_to<P, F>(mut self, publisher: P, stop: F)
+    where
+        P: BundleEventPublisher,
+        F: Future<Output = ()>,
+    {
+        let mut publishing = None;
+        tokio::select! {
+            biased;
+            _ = stop => {}
+            _ = self.forward(&publisher, &mut publishing) => return,
+        }
+
+        let mut remaining: Vec<_> = publishing.into_iter().collect();
+        remaining.extend(self.close());
+        if remaining.is_empty() {
+            return;
+        }
+        if self.shared.spill.is_some() && self.shared.spill(&remaining).await.is_ok() {
+            info!(events = remaining.len(), "spilled buffered audit events");
+            return;
+        }
+        error!(events = remaining.len(), "dropped buffered audit events");
+        self.shared
+            .metrics
+            .events_dropped_closed
+            .increment(remaining.len() as u64);
+    }
+
+    /// Publishes events until every sender is dropped, keeping the event being published in
+    /// `publishing` so it isn't lost when stopped
+    async fn forward<P: BundleEventPublisher>(
+        &mut self,
+        publisher: &P,
+        publishing: &mut Option<BundleEvent>,
+    ) {
+        let mut next_replay = Instant::now();
+        loop {
+            if self.shared.spill.is_some() && Instant::now() >= next_replay {
+                if let Err(e) = self.replay(publisher).await {
+                    error!(error = %e, "failed to replay spilled audit events");
+                }
+                next_replay = Instant::now() + SPILL_REPLAY_INTERVAL;
+            }
+
+            let event = if self.shared.spill.is_some() {
+                tokio::select! {
+                    event = self.recv() => event,
+                    _ = sleep_until(next_replay) => continue,
+                }
+            } else {
+                self.recv().await
+            };
+            let Some(event) = event else {
+                break;
+            };
+            self.publish(publisher, publishing.insert(event)).await;
+            *publishing = None;
+        }
+
+        // Last chance to publish what was spilled, anything left is replayed on the next start
+        if let Err(e) = self.replay(publisher).await {
+            error!(error = %e, "failed to replay spilled audit events");
+        }
+    }
+
+    async fn publish<P: BundleEventPublisher>(&self, publisher: &P, event: &BundleEvent) {
+        let mut backoff = INITIAL_RETRY_BACKOFF;
+        let mut attempts = 0;
+        loop {
+            match publisher.publish(event.clone()).await {
+                Ok(()) => {
+                    self.shared.metrics.events_published.increment(1);
+                    return;
+                }
+                Err(e) => {
+                    attempts += 1;
+                    self.shared.metrics.publish_failures.increment(1);
+                    warn!(attempts, error = %e, "failed to publish bundle event");
+                }
+            }
+
+            if attempts >= SPILL_AFTER_ATTEMPTS
+                && self.shared.spill.is_some()
+                && self.shared.spill(std::slice::from_ref(event)).await.is_ok()
+            {
+                return;
+            }
+            sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_RETRY_BACKOFF);
+        }
+    }
+
+    /// Publishes the spilled events. Stops at the first failure and spills the events not
+    /// published yet again, to be retried on the next replay.
+    async fn replay<P: BundleEventPublisher>(&self, publisher: &P) -> Result<()> {
+        let Some(spill) = self.shared.spill.clone() else {
+            return Ok(());
+        };
+        let replay_path = {
+            let spill = spill.clone();
+            tokio::task::spawn_blocking(move || spill.take_for_replay()).await??
+        };
+        let Some(replay_path) = replay_path else {
+            return Ok(());
+        };
+
+        let contents = tokio::fs::read_to_string(&replay_path).await?;
+        let mut events = Vec::new();
+        for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+            match serde_json::from_str::<BundleEvent>(line) {
+                Ok(event) => events.push(event),
+                Err(e) => {
+                    error!(error = %e, "skipping unreadable spilled audit event");
+                    self.shared.metrics.events_dropped_corrupt.increment(1);
+                }
+            }
+        }
+        info!(events = events.len(), "replaying spilled audit events");
+
+        for (i, event) in events.iter().enumerate() {
+            if let Err(e) = publisher.publish(event.clone()).await {
+                self.shared.metrics.publish_failures.increment(1);
+                warn!(error = %e, remaining = events.len() - i, "failed to replay spilled audit events");
+                // Written before the replay file is removed, so no event is lost if this fails
+                spill.append(&events[i..]).await?;
+                break;
+            }
+            self.shared.metrics.events_replayed.increment(1);
+        }
+        tokio::fs::remove_file(&replay_path).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::DropReason;
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use uuid::Uuid;
+
+    #[derive(Clone, Default)]
+    struct RecordingPublisher {
+        published: Arc<Mutex<Vec<Uuid>>>,
+        failures: Arc<AtomicUsize>,
+    }
+
+    impl RecordingPublisher {
+        fn failing(failures: usize) -> Self {
+            Self {
+                published: Arc::default(),
+                failures: Arc::new(AtomicUsize::new(failures)),
+            }
+        }
+
+        fn published(&self) -> Vec<Uuid> {
+            self.published.lock().unwrap().clone()
+        }
+    }
+
+    #[async_trait]
+    impl BundleEventPublisher for RecordingPublisher {
+        async fn publish(&self, event: BundleEvent) -> Result<()> {
+            let failing = self
+                .failures
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                .is_ok();
+            if failing {
+                bail!("Kafka unavailable");
+            }
+            self.published.lock().unwrap().push(event.bundle_id());
+            Ok(())
+        }
+
+        async fn publish_all(&self, events: Vec<BundleEvent>) -> Result<()> {
+            for event in events {
+                self.publish(event).await?;
+            }
+            Ok(())
+        }
+    }
+
+    fn bundle_id(i: u8) -> Uuid {
+        Uuid::new_v5(&Uuid::NAMESPACE_OID, &[i])
+    }
+
+    fn event(i: u8) -> BundleEvent {
+        BundleEvent::Dropped {
+            bundle_id: bundle_id(i),
+            reason: DropReason::TimedOut,
+        }
+    }
+
+    fn spill_file(name: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("tips-audit-{}-{name}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    fn channel(
+        capacity: usize,
+        overflow_policy: OverflowPolicy,
+        spill_file: Option<PathBuf>,
+    ) -> (AuditSender, AuditReceiver) {
+        audit_channel(AuditChannelConfig {
+            capacity,
+            overflow_policy,
+            spill_file,
+            send_timeout: Duration::from_secs(5),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_spill_policy_requires_spill_file() {
+        assert!(
+            audit_channel(AuditChannelConfig {
+                capacity: 10,
+                overflow_policy: OverflowPolicy::Spill,
+                spill_file: None,
+                send_timeout: Duration::from_secs(5),
+            })
+            .is_err()
+        );
+        assert_eq!(
+            "drop-oldest".parse::<OverflowPolicy>().unwrap(),
+            OverflowPolicy::DropOldest
+        );
+    }
+
+    #[tokio::test]
+    async fn test_block_waits_for_room() {
+        let (tx, mut rx) = channel(1, OverflowPolicy::Block, None);
+        tx.send(event(1)).await.unwrap();
+
+        let blocked = tokio::time::timeout(Duration::from_millis(50), tx.send(event(2))).await;
+        assert!(blocked.is_err());
+
+        let sender = tokio::spawn({
+            let tx = tx.clone();
+            async move { tx.send(event(3)).await }
+        });
+        assert_eq!(rx.recv().await.unwrap().bundle_id(), bundle_id(1));
+        sender.await.unwrap().unwrap();
+        assert_eq!(rx.recv().await.unwrap().bundle_id(), bundle_id(3));
+
+        drop(tx);
+        assert!(rx.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_block_times_out() {
+        let path = spill_file("send-timeout");
+        for spill_file in [None, Some(path.clone())] {
+            let (tx, _rx) = audit_channel(AuditChannelConfig {
+                capacity: 1,
+                overflow_policy: OverflowPolicy::Block,
+                spill_file: spill_file.clone(),
+                send_timeout: Duration::from_millis(20),
+            })
+            .unwrap();
+            tx.send(event(1)).await.unwrap();
+
+            // spilled if possible, dropped otherwise
+            let sent = tokio::time::timeout(Duration::from_secs(5), tx.send(event(2)))
+                .await
+                .expect("send didn't time out");
+            assert_eq!(sent.is_ok(), spill_file.is_some());
+        }
+        assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 1);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_drop_oldest() {
+        let (tx, mut rx) = channel(2, OverflowPolicy::DropOldest, None);
+        for i in 1..=3 {
+            tx.send(event(i)).await.unwrap();
+        }
+
+        assert_eq!(rx.try_recv().unwrap().bundle_id(), bundle_id(2));
+        assert_eq!(rx.try_recv().unwrap().bundle_id(), bundle_id(3));
+        assert!(matches!(rx.try_recv(), Err(TryRecvError::Empty)));
+    }
+
+    #[tokio::test]
+    async fn test_spill_on_overflow_and_replay() {
+        let path = spill_file("overflow");
+        let (tx, rx) = channel(1, OverflowPolicy::Spill, Some(path.clone()));
+        for i in 1..=3 {
+            tx.send(event(i)).await.unwrap();
+        }
+        let spilled = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(spilled.lines().count(), 2);
+
+        let publisher = RecordingPublisher::default();
+        drop(tx);
+        rx.publish_to(publisher.clone(), std::future::pending())
+            .await;
+
+        let mut published = publisher.published();
+        published.sort();
+        let mut expected: Vec<_> = (1..=3).map(bundle_id).collect();
+        expected.sort();
+        assert_eq!(published, expected);
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_failed_publish_is_spilled_and_replayed() {
+        let path = spill_file("publish-failure");
+        let (tx, rx) = channel(10, OverflowPolicy::Block, Some(path.clone()));
+
+        let publisher = RecordingPublisher::failing(SPILL_AFTER_ATTEMPTS as usize);
+        rx.publish(&publisher, &event(1)).await;
+        assert!(publisher.published().is_empty());
+        assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 1);
+
+        rx.replay(&publisher).await.unwrap();
+        assert_eq!(publisher.published(), vec![bundle_id(1)]);
+        assert!(!path.exists());
+        drop(tx);
+    }
+
+    #[tokio::test]
+    async fn test_failed_replay_keeps_events() {
+        let path = spill_file("interrupted-replay");
+        let (_tx, rx) = channel(10, OverflowPolicy::Spill, Some(path.clone()));
+        rx.shared.spill(&[event(1), event(2)]).await.unwrap();
+
+        let failing = RecordingPublisher::failing(usize::MAX);
+        rx.replay(&failing).await.unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 2);
+
+        let publisher = RecordingPublisher::default();
+        rx.replay(&publisher).await.unwrap();
+        assert_eq!(publisher.published(), vec![bundle_id(1), bundle_id(2)]);
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_buffered_events_spilled_when_stopped() {
+        let path = spill_file("publisher-stopped");
+        let (tx, rx) = channel(10, OverflowPolicy::Block, Some(path.clone()));
+        tx.send(event(1)).await.unwrap();
+        tx.send(event(2)).await.unwrap();
+
+        // the event being published is spilled along with the buffered ones
+        let failing = RecordingPublisher::failing(usize::MAX);
+        rx.publish_to(failing, sleep(Duration::from_millis(50)))
+            .await;
+        assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 2);
+        // later events go straight to the spill file
+        tx.send(event(3)).await.unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 3);
+        let _ = std::fs::remove_file(&path);
+
+        let (tx, rx) = channel(10, OverflowPolicy::Block, None);
+        tx.send(event(1)).await.unwrap();
+        drop(rx);
+        assert!(tx.send(event(2)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_events_broadcast_to_subscribers() {
+        let (events, mut subscriber) = broadcast::channel(10);
+        let (tx, mut rx) = channel(10, OverflowPolicy::Block, None);
+        let tx = tx.with_subscribers(events);
+
+        tx.send(event(1)).await.unwrap();
+        assert_eq!(subscriber.recv().await.unwrap().bundle_id(), bundle_id(1));
+        assert_eq!(rx.recv().await.unwrap().bundle_id(), bundle_id(1));
+    }
+}
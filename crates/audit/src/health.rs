@@ -0,0 +1,126 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use aws_sdk_s3::{Client as S3Client, error::DisplayErrorContext};
+use rdkafka::{
+    Offset, TopicPartitionList,
+    consumer::{Consumer, StreamConsumer},
+};
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+use tips_core::health::{CheckStatus, HealthCheck};
+
+/// Checks how far the archiver's consumer group is behind the end of the audit topic.
+///
+/// The consumer passed in must share the archiver's `group.id` but must not subscribe to the
+/// topic, it is only used to look up the group's committed offsets.
+pub struct KafkaConsumerLagCheck {
+    consumer: Arc<StreamConsumer>,
+    topic: String,
+    max_lag: u64,
+    timeout: Duration,
+}
+
+impl fmt::Debug for KafkaConsumerLagCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KafkaConsumerLagCheck")
+            .field("topic", &self.topic)
+            .field("max_lag", &self.max_lag)
+            .finish_non_exhaustive()
+    }
+}
+
+impl KafkaConsumerLagCheck {
+    /// Creates a check reporting unhealthy once the lag exceeds `max_lag` messages.
+    pub fn new(consumer: StreamConsumer, topic: String, max_lag: u64, timeout: Duration) -> Self {
+        Self {
+            consumer: Arc::new(consumer),
+            topic,
+            max_lag,
+            timeout,
+        }
+    }
+}
+
+/// Sums the number of messages after the committed offset across the topic's partitions. A
+/// partition without a committed offset counts from its low watermark.
+fn consumer_lag(consumer: &StreamConsumer, topic: &str, timeout: Duration) -> Result<u64> {
+    let metadata = consumer.fetch_metadata(Some(topic), timeout)?;
+    let mut partitions = TopicPartitionList::new();
+    for partition in metadata
+        .topics()
+        .iter()
+        .flat_map(|topic| topic.partitions())
+    {
+        partitions.add_partition(topic, partition.id());
+    }
+
+    let committed = consumer.committed_offsets(partitions, timeout)?;
+    let mut lag = 0;
+    for element in committed.elements() {
+        let (low, high) = consumer.fetch_watermarks(topic, element.partition(), timeout)?;
+        let position = match element.offset() {
+            Offset::Offset(offset) => offset,
+            _ => low,
+        };
+        lag += high.saturating_sub(position).max(0) as u64;
+    }
+    Ok(lag)
+}
+
+#[async_trait]
+impl HealthCheck for KafkaConsumerLagCheck {
+    fn name(&self) -> &str {
+        "kafka:consumer_lag"
+    }
+
+    async fn check(&self) -> CheckStatus {
+        let consumer = self.consumer.clone();
+        let topic = self.topic.clone();
+        let timeout = self.timeout;
+        // the offset and watermark lookups block until the brokers respond
+        let lag =
+            tokio::task::spawn_blocking(move || consumer_lag(&consumer, &topic, timeout)).await;
+
+        match lag {
+            Ok(Ok(lag)) if lag > self.max_lag => {
+                CheckStatus::unhealthy(format!("lag of {lag} messages exceeds {}", self.max_lag))
+            }
+            Ok(Ok(lag)) => CheckStatus::healthy_with(format!("lag of {lag} messages")),
+            Ok(Err(e)) => CheckStatus::unhealthy(e.to_string()),
+            Err(e) => CheckStatus::unhealthy(e.to_string()),
+        }
+    }
+}
+
+/// Checks that the archive bucket exists and is accessible.
+#[derive(Debug)]
+pub struct S3BucketCheck {
+    client: S3Client,
+    bucket: String,
+}
+
+impl S3BucketCheck {
+    /// Creates a check for the given bucket.
+    pub const fn new(client: S3Client, bucket: String) -> Self {
+        Self { client, bucket }
+    }
+}
+
+#[async_trait]
+impl HealthCheck for S3BucketCheck {
+    fn name(&self) -> &str {
+        "s3"
+    }
+
+    async fn check(&self) -> CheckStatus {
+        match self.client.head_bucket().bucket(&self.bucket).send().await {
+            Ok(_) => CheckStatus::healthy(),
+            Err(e) => CheckStatus::unhealthy(format!(
+                "bucket {}: {}",
+                self.bucket,
+                DisplayErrorContext(&e)
+            )),
+        }
+    }
+}
@@ -10,6 +10,12 @@
 mod archiver;
 pub use archiver::KafkaAuditArchiver;
 
+mod channel;
+pub use channel::{AuditChannelConfig, AuditReceiver, AuditSender, OverflowPolicy, audit_channel};
+
+mod health;
+pub use health::{KafkaConsumerLagCheck, S3BucketCheck};
+
 mod metrics;
 pub use metrics::Metrics;
 
@@ -34,33 +40,40 @@ pub use storage::{
 
 mod types;
 pub use types::{
-    BundleEvent, BundleId, DropReason, Transaction, TransactionId, UserOpDropReason, UserOpEvent,
-    UserOpHash,
+    BundleEvent, BundleId, DropReason, IngressMetadata, Transaction, TransactionId,
+    UserOpDropReason, UserOpEvent, UserOpHash,
 };
 
+use std::future::Future;
 use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
 use tracing::error;
 
-/// Connects a bundle event receiver to a publisher, spawning a task to forward events.
-pub fn connect_audit_to_publisher<P>(event_rx: mpsc::UnboundedReceiver<BundleEvent>, publisher: P)
+/// Connects the audit channel to a publisher, spawning a task to forward events.
+///
+/// The task completes once every sender is dropped and the buffered events are published or
+/// spilled, or once `stop` completes and the events not published yet are spilled, see
+/// [`AuditReceiver::publish_to`].
+pub fn connect_audit_to_publisher<P, F>(
+    event_rx: AuditReceiver,
+    publisher: P,
+    stop: F,
+) -> JoinHandle<()>
 where
     P: BundleEventPublisher + 'static,
+    F: Future<Output = ()> + Send + 'static,
 {
-    tokio::spawn(async move {
-        let mut event_rx = event_rx;
-        while let Some(event) = event_rx.recv().await {
-            if let Err(e) = publisher.publish(event).await {
-                error!(error = %e, "failed to publish bundle event");
-            }
-        }
-    });
+    tokio::spawn(event_rx.publish_to(publisher, stop))
 }
 
 /// Connects a user operation event receiver to a publisher, spawning a task to forward events.
+///
+/// The task completes once every sender is dropped and the buffered events are published.
 pub fn connect_userop_audit_to_publisher<P>(
     event_rx: mpsc::UnboundedReceiver<UserOpEvent>,
     publisher: P,
-) where
+) -> JoinHandle<()>
+where
     P: UserOpEventPublisher + 'static,
 {
     tokio::spawn(async move {
@@ -70,5 +83,5 @@ pub fn connect_userop_audit_to_publisher<P>(
                 error!(error = %e, "Failed to publish user op event");
             }
         }
-    });
+    })
 }
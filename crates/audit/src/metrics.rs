@@ -53,3 +53,55 @@ pub struct Metrics {
     #[metric(describe = "Number of failed archive tasks")]
     pub failed_archive_tasks: Counter,
 }
+
+/// Metrics for the channel buffering bundle events until they are published, covering every path
+/// an event can take through it.
+#[derive(Metrics, Clone)]
+#[metrics(scope = "tips_audit_channel")]
+pub(crate) struct ChannelMetrics {
+    /// Number of events buffered in memory.
+    #[metric(describe = "Number of events buffered in memory")]
+    pub(crate) queue_depth: Gauge,
+
+    /// Time senders waited for room in a full channel.
+    #[metric(describe = "Time senders waited for room in a full channel")]
+    pub(crate) send_blocked_duration: Histogram,
+
+    /// Total events published.
+    #[metric(describe = "Total events published")]
+    pub(crate) events_published: Counter,
+
+    /// Total failed publish attempts, each event is retried until it is published or spilled.
+    #[metric(describe = "Total failed publish attempts")]
+    pub(crate) publish_failures: Counter,
+
+    /// Total events written to the spill file.
+    #[metric(describe = "Total events written to the spill file")]
+    pub(crate) events_spilled: Counter,
+
+    /// Total failed writes to the spill file.
+    #[metric(describe = "Total failed writes to the spill file")]
+    pub(crate) spill_failures: Counter,
+
+    /// Total events read back from the spill file and published.
+    #[metric(describe = "Total events replayed from the spill file")]
+    pub(crate) events_replayed: Counter,
+
+    /// Total events dropped to make room for newer ones with the drop-oldest policy.
+    #[metric(describe = "Total events dropped to make room for newer ones")]
+    pub(crate) events_dropped_overflow: Counter,
+
+    /// Total events dropped because the channel stayed full for the send timeout.
+    #[metric(
+        describe = "Total events dropped because the channel stayed full for the send timeout"
+    )]
+    pub(crate) events_dropped_timeout: Counter,
+
+    /// Total events dropped because the publisher stopped before they were published.
+    #[metric(describe = "Total events dropped because the publisher stopped")]
+    pub(crate) events_dropped_closed: Counter,
+
+    /// Total unreadable lines skipped in the spill file.
+    #[metric(describe = "Total unreadable lines skipped in the spill file")]
+    pub(crate) events_dropped_corrupt: Counter,
+}
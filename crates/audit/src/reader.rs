@@ -4,7 +4,7 @@ use async_trait::async_trait;
 use rdkafka::{
     Timestamp, TopicPartitionList,
     config::ClientConfig,
-    consumer::{Consumer, StreamConsumer},
+    consumer::{CommitMode, Consumer, StreamConsumer},
     message::Message,
 };
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
@@ -37,6 +37,10 @@ pub struct Event {
     pub event: BundleEvent,
     /// The event timestamp in milliseconds.
     pub timestamp: i64,
+    /// The partition the event was read from.
+    pub partition: i32,
+    /// The offset of the event in its partition.
+    pub offset: i64,
 }
 
 /// Trait for reading bundle events.
@@ -44,24 +48,21 @@ pub struct Event {
 pub trait EventReader {
     /// Reads the next event.
     async fn read_event(&mut self) -> Result<Event>;
-    /// Commits the last read message.
-    async fn commit(&mut self) -> Result<()>;
+    /// Commits the messages of `partition` before `offset`, reading resumes from `offset` after
+    /// a restart. With `CommitMode::Sync` this only returns once the commit reached the broker.
+    async fn commit(&mut self, partition: i32, offset: i64, mode: CommitMode) -> Result<()>;
 }
 
 /// Reads bundle audit events from Kafka.
 pub struct KafkaAuditLogReader {
     consumer: StreamConsumer,
     topic: String,
-    last_message_offset: Option<i64>,
-    last_message_partition: Option<i32>,
 }
 
 impl std::fmt::Debug for KafkaAuditLogReader {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("KafkaAuditLogReader")
             .field("topic", &self.topic)
-            .field("last_message_offset", &self.last_message_offset)
-            .field("last_message_partition", &self.last_message_partition)
             .finish_non_exhaustive()
     }
 }
@@ -70,12 +71,7 @@ impl KafkaAuditLogReader {
     /// Creates a new Kafka audit log reader.
     pub fn new(consumer: StreamConsumer, topic: String) -> Result<Self> {
         consumer.subscribe(&[&topic])?;
-        Ok(Self {
-            consumer,
-            topic,
-            last_message_offset: None,
-            last_message_partition: None,
-        })
+        Ok(Self { consumer, topic })
     }
 }
 
@@ -109,9 +105,6 @@ impl EventReader for KafkaAuditLogReader {
                     "Received event with timestamp"
                 );
 
-                self.last_message_offset = Some(message.offset());
-                self.last_message_partition = Some(message.partition());
-
                 let key = message
                     .key()
                     .map(|k| String::from_utf8_lossy(k).to_string())
@@ -121,6 +114,8 @@ impl EventReader for KafkaAuditLogReader {
                     key,
                     event,
                     timestamp,
+                    partition: message.partition(),
+                    offset: message.offset(),
                 };
 
                 Ok(event_result)
@@ -133,15 +128,10 @@ impl EventReader for KafkaAuditLogReader {
         }
     }
 
-    async fn commit(&mut self) -> Result<()> {
-        if let (Some(offset), Some(partition)) =
-            (self.last_message_offset, self.last_message_partition)
-        {
-            let mut tpl = TopicPartitionList::new();
-            tpl.add_partition_offset(&self.topic, partition, rdkafka::Offset::Offset(offset + 1))?;
-            self.consumer
-                .commit(&tpl, rdkafka::consumer::CommitMode::Async)?;
-        }
+    async fn commit(&mut self, partition: i32, offset: i64, mode: CommitMode) -> Result<()> {
+        let mut tpl = TopicPartitionList::new();
+        tpl.add_partition_offset(&self.topic, partition, rdkafka::Offset::Offset(offset))?;
+        self.consumer.commit(&tpl, mode)?;
         Ok(())
     }
 }
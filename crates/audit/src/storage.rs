@@ -1,7 +1,8 @@
 use crate::metrics::Metrics;
 use crate::reader::Event;
 use crate::types::{
-    BundleEvent, BundleId, DropReason, TransactionId, UserOpDropReason, UserOpEvent, UserOpHash,
+    BundleEvent, BundleId, DropReason, IngressMetadata, TransactionId, UserOpDropReason,
+    UserOpEvent, UserOpHash,
 };
 use alloy_primitives::{Address, TxHash, U256};
 use anyhow::Result;
@@ -58,6 +59,21 @@ pub enum BundleHistoryEvent {
         timestamp: i64,
         /// The accepted bundle.
         bundle: Box<AcceptedBundle>,
+        /// How the bundle arrived at ingress, absent from events archived before it was added.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        metadata: Option<IngressMetadata>,
+    },
+    /// Bundle was replaced by a newer version.
+    Updated {
+        /// Event key.
+        key: String,
+        /// Event timestamp.
+        timestamp: i64,
+        /// The replacement bundle.
+        bundle: Box<AcceptedBundle>,
+        /// How the replacement arrived at ingress, absent from events archived before it was added.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        metadata: Option<IngressMetadata>,
     },
     /// Bundle was cancelled.
     Cancelled {
@@ -106,6 +122,7 @@ impl BundleHistoryEvent {
     pub fn key(&self) -> &str {
         match self {
             Self::Received { key, .. } => key,
+            Self::Updated { key, .. } => key,
             Self::Cancelled { key, .. } => key,
             Self::BuilderIncluded { key, .. } => key,
             Self::BlockIncluded { key, .. } => key,
@@ -198,10 +215,21 @@ fn update_bundle_history_transform(
     }
 
     let history_event = match &event.event {
-        BundleEvent::Received { bundle, .. } => BundleHistoryEvent::Received {
+        BundleEvent::Received {
+            bundle, metadata, ..
+        } => BundleHistoryEvent::Received {
+            key: event.key.clone(),
+            timestamp: event.timestamp,
+            bundle: bundle.clone(),
+            metadata: metadata.clone(),
+        },
+        BundleEvent::Updated {
+            bundle, metadata, ..
+        } => BundleHistoryEvent::Updated {
             key: event.key.clone(),
             timestamp: event.timestamp,
             bundle: bundle.clone(),
+            metadata: metadata.clone(),
         },
         BundleEvent::Cancelled { .. } => BundleHistoryEvent::Cancelled {
             key: event.key.clone(),
@@ -609,6 +637,8 @@ mod tests {
             key: key.to_string(),
             timestamp,
             event: bundle_event,
+            partition: 0,
+            offset: 0,
         }
     }
 
@@ -617,9 +647,18 @@ mod tests {
         let bundle_history = BundleHistory { history: vec![] };
         let bundle = create_bundle_from_txn_data();
         let bundle_id = Uuid::new_v5(&Uuid::NAMESPACE_OID, bundle.bundle_hash().as_slice());
+        let ingress_metadata = IngressMetadata {
+            method: "eth_sendBundle".to_string(),
+            received_at: 1234567890000,
+            instance_id: "ingress-0".to_string(),
+            client_ip_hash: Some("ab".repeat(32)),
+            request_id: "request-1".to_string(),
+        };
         let bundle_event = BundleEvent::Received {
             bundle_id,
             bundle: Box::new(bundle.clone()),
+            identity: None,
+            metadata: Some(ingress_metadata.clone()),
         };
         let event = create_test_event("test-key", 1234567890, bundle_event);
 
@@ -634,10 +673,12 @@ mod tests {
                 key,
                 timestamp: ts,
                 bundle: b,
+                metadata,
             } => {
                 assert_eq!(key, "test-key");
                 assert_eq!(*ts, 1234567890);
                 assert_eq!(b.block_number, bundle.block_number);
+                assert_eq!(metadata.as_ref(), Some(&ingress_metadata));
             }
             _ => panic!("Expected Created event"),
         }
@@ -649,6 +690,7 @@ mod tests {
             key: "duplicate-key".to_string(),
             timestamp: 1111111111,
             bundle: Box::new(create_bundle_from_txn_data()),
+            metadata: None,
         };
         let bundle_history = BundleHistory {
             history: vec![existing_event],
@@ -659,6 +701,8 @@ mod tests {
         let bundle_event = BundleEvent::Received {
             bundle_id,
             bundle: Box::new(bundle),
+            identity: None,
+            metadata: None,
         };
         let event = create_test_event("duplicate-key", 1234567890, bundle_event);
 
@@ -667,6 +711,67 @@ mod tests {
         assert!(result.is_none());
     }
 
+    #[test]
+    fn test_update_bundle_history_transform_records_update() {
+        let bundle = create_bundle_from_txn_data();
+        let bundle_id = *bundle.uuid();
+        let bundle_history = BundleHistory {
+            history: vec![BundleHistoryEvent::Received {
+                key: "received-key".to_string(),
+                timestamp: 1111111111,
+                bundle: Box::new(bundle.clone()),
+                metadata: None,
+            }],
+        };
+
+        let bundle_event = BundleEvent::Updated {
+            bundle_id,
+            bundle: Box::new(bundle.with_version(1)),
+            metadata: None,
+        };
+        let event = create_test_event("updated-key", 1234567890, bundle_event);
+
+        let bundle_history = update_bundle_history_transform(bundle_history, &event).unwrap();
+        assert_eq!(bundle_history.history.len(), 2);
+
+        match &bundle_history.history[1] {
+            BundleHistoryEvent::Updated {
+                key,
+                timestamp,
+                bundle,
+                metadata,
+            } => {
+                assert_eq!(key, "updated-key");
+                assert_eq!(*timestamp, 1234567890);
+                assert_eq!(bundle.version, 1);
+                assert!(metadata.is_none());
+            }
+            _ => panic!("Expected Updated event"),
+        }
+    }
+
+    #[test]
+    fn test_received_event_without_metadata_deserializes() {
+        let history = BundleHistory {
+            history: vec![BundleHistoryEvent::Received {
+                key: "received-key".to_string(),
+                timestamp: 1111111111,
+                bundle: Box::new(create_bundle_from_txn_data()),
+                metadata: None,
+            }],
+        };
+
+        // archived before the metadata was recorded
+        let json = serde_json::to_value(&history).unwrap();
+        assert!(json["history"][0]["data"].get("metadata").is_none());
+
+        let history: BundleHistory = serde_json::from_value(json).unwrap();
+        assert!(matches!(
+            &history.history[0],
+            BundleHistoryEvent::Received { metadata: None, .. }
+        ));
+    }
+
     #[test]
     fn test_update_bundle_history_transform_handles_all_event_types() {
         let bundle_history = BundleHistory { history: vec![] };
@@ -676,6 +781,8 @@ mod tests {
         let bundle_event = BundleEvent::Received {
             bundle_id,
             bundle: Box::new(bundle),
+            identity: None,
+            metadata: None,
         };
         let event = create_test_event("test-key", 1234567890, bundle_event);
         let result = update_bundle_history_transform(bundle_history.clone(), &event);
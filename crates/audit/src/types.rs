@@ -2,7 +2,7 @@ use alloy_consensus::transaction::{SignerRecoverable, Transaction as ConsensusTr
 use alloy_primitives::{Address, B256, TxHash, U256};
 use bytes::Bytes;
 use serde::{Deserialize, Serialize};
-use tips_core::AcceptedBundle;
+use tips_core::{AcceptedBundle, SearcherIdentity};
 use uuid::Uuid;
 
 /// Unique identifier for a transaction.
@@ -20,7 +20,7 @@ pub struct TransactionId {
 pub type BundleId = Uuid;
 
 /// Reason a bundle was dropped.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum DropReason {
     /// Bundle timed out.
     TimedOut,
@@ -51,6 +51,24 @@ pub enum UserOpDropReason {
     ReplacedByHigherFee,
 }
 
+/// How a bundle arrived at ingress. The authenticated client, if any, is the `identity` of the
+/// `Received` event.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IngressMetadata {
+    /// RPC method the bundle was submitted with, e.g. `eth_sendBundle`.
+    pub method: String,
+    /// When ingress received the request, in milliseconds since the Unix epoch.
+    pub received_at: i64,
+    /// Ingress instance that received the request.
+    pub instance_id: String,
+    /// Hex encoded HMAC-SHA256 of the client IP, when it is known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_ip_hash: Option<String>,
+    /// The `X-Request-Id` set by the load balancer, or one generated by ingress.
+    pub request_id: String,
+}
+
 /// Bundle lifecycle event.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "event", content = "data")]
@@ -61,6 +79,22 @@ pub enum BundleEvent {
         bundle_id: BundleId,
         /// The accepted bundle.
         bundle: Box<AcceptedBundle>,
+        /// The searcher that submitted the bundle, when it authenticated with ingress.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        identity: Option<SearcherIdentity>,
+        /// How the bundle arrived at ingress, absent from events recorded before it was added.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        metadata: Option<IngressMetadata>,
+    },
+    /// Bundle was replaced by a newer version with the same UUID.
+    Updated {
+        /// Bundle identifier.
+        bundle_id: BundleId,
+        /// The replacement bundle.
+        bundle: Box<AcceptedBundle>,
+        /// How the replacement arrived at ingress, absent from events recorded before it was added.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        metadata: Option<IngressMetadata>,
     },
     /// Bundle was cancelled.
     Cancelled {
@@ -101,6 +135,7 @@ impl BundleEvent {
     pub const fn bundle_id(&self) -> BundleId {
         match self {
             Self::Received { bundle_id, .. } => *bundle_id,
+            Self::Updated { bundle_id, .. } => *bundle_id,
             Self::Cancelled { bundle_id, .. } => *bundle_id,
             Self::BuilderIncluded { bundle_id, .. } => *bundle_id,
             Self::BlockIncluded { bundle_id, .. } => *bundle_id,
@@ -108,10 +143,10 @@ impl BundleEvent {
         }
     }
 
-    /// Returns transaction IDs from this event (only for Received events).
+    /// Returns transaction IDs from this event (only for Received and Updated events).
     pub fn transaction_ids(&self) -> Vec<TransactionId> {
         match self {
-            Self::Received { bundle, .. } => bundle
+            Self::Received { bundle, .. } | Self::Updated { bundle, .. } => bundle
                 .txs
                 .iter()
                 .filter_map(|envelope| {
@@ -139,6 +174,12 @@ impl BundleEvent {
             } => {
                 format!("{bundle_id}-{block_hash}")
             }
+            Self::Updated {
+                bundle_id, bundle, ..
+            } => {
+                format!("{bundle_id}-v{}", bundle.version)
+            }
+            Self::Cancelled { bundle_id } => format!("{bundle_id}-cancelled"),
             _ => {
                 format!(
                     "{}-{}",
@@ -214,6 +255,47 @@ impl UserOpEvent {
     }
 }
 
+#[cfg(test)]
+mod bundle_event_tests {
+    use super::*;
+    use tips_core::test_utils::create_bundle_from_txn_data;
+
+    #[test]
+    fn test_generate_event_key_cancelled() {
+        let bundle_id = Uuid::new_v5(&Uuid::NAMESPACE_OID, b"bundle");
+        let dropped_key = BundleEvent::Dropped {
+            bundle_id,
+            reason: DropReason::TimedOut,
+        }
+        .generate_event_key();
+        let cancelled_key = BundleEvent::Cancelled { bundle_id }.generate_event_key();
+
+        assert_eq!(cancelled_key, format!("{bundle_id}-cancelled"));
+        assert_ne!(cancelled_key, dropped_key);
+    }
+
+    #[test]
+    fn test_generate_event_key_updated() {
+        let bundle = create_bundle_from_txn_data();
+        let bundle_id = *bundle.uuid();
+
+        let v1 = BundleEvent::Updated {
+            bundle_id,
+            bundle: Box::new(bundle.clone().with_version(1)),
+            metadata: None,
+        };
+        let v2 = BundleEvent::Updated {
+            bundle_id,
+            bundle: Box::new(bundle.with_version(2)),
+            metadata: None,
+        };
+
+        assert_eq!(v1.generate_event_key(), format!("{bundle_id}-v1"));
+        assert_ne!(v1.generate_event_key(), v2.generate_event_key());
+        assert_eq!(v1.transaction_ids().len(), 1);
+    }
+}
+
 #[cfg(test)]
 mod user_op_event_tests {
     use super::*;
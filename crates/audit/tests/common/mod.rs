@@ -11,6 +11,8 @@ pub struct TestHarness {
     pub kafka_producer: FutureProducer,
     #[allow(dead_code)] // TODO is read
     pub kafka_consumer: StreamConsumer,
+    #[allow(dead_code)] // only read by some test binaries
+    pub kafka_bootstrap_servers: String,
     _minio_container: testcontainers::ContainerAsync<MinIO>,
     _kafka_container: testcontainers::ContainerAsync<Kafka>,
 }
@@ -74,6 +76,7 @@ impl TestHarness {
             bucket_name,
             kafka_producer,
             kafka_consumer,
+            kafka_bootstrap_servers: bootstrap_servers,
             _minio_container: minio_container,
             _kafka_container: kafka_container,
         })
@@ -0,0 +1,67 @@
+use rdkafka::ClientConfig;
+use rdkafka::consumer::StreamConsumer;
+use rdkafka::producer::FutureRecord;
+use std::time::Duration;
+use tips_audit_lib::{KafkaConsumerLagCheck, S3BucketCheck};
+use tips_core::health::HealthCheck;
+
+mod common;
+use common::TestHarness;
+
+#[tokio::test]
+async fn test_s3_bucket_check() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let harness = TestHarness::new().await?;
+
+    let check = S3BucketCheck::new(harness.s3_client.clone(), harness.bucket_name.clone());
+    assert!(check.check().await.healthy);
+
+    let check = S3BucketCheck::new(harness.s3_client.clone(), "missing-bucket".to_string());
+    assert!(!check.check().await.healthy);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_kafka_consumer_lag_check() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let harness = TestHarness::new().await?;
+    let topic = "test-consumer-lag";
+
+    for i in 0..3 {
+        let key = format!("key-{i}");
+        harness
+            .kafka_producer
+            .send(
+                FutureRecord::to(topic).key(&key).payload("event"),
+                Duration::from_secs(5),
+            )
+            .await
+            .map_err(|(e, _)| e)?;
+    }
+
+    let lag_consumer = || -> Result<StreamConsumer, rdkafka::error::KafkaError> {
+        ClientConfig::new()
+            .set("group.id", "test-consumer-lag")
+            .set("bootstrap.servers", &harness.kafka_bootstrap_servers)
+            .create()
+    };
+
+    let check = KafkaConsumerLagCheck::new(
+        lag_consumer()?,
+        topic.to_string(),
+        5,
+        Duration::from_secs(5),
+    );
+    let status = check.check().await;
+    assert!(status.healthy);
+    assert_eq!(status.detail.as_deref(), Some("lag of 3 messages"));
+
+    let check = KafkaConsumerLagCheck::new(
+        lag_consumer()?,
+        topic.to_string(),
+        2,
+        Duration::from_secs(5),
+    );
+    assert!(!check.check().await.healthy);
+
+    Ok(())
+}
@@ -30,6 +30,8 @@ async fn test_kafka_publisher_s3_archiver_integration()
         BundleEvent::Received {
             bundle_id: test_bundle_id,
             bundle: Box::new(bundle.clone()),
+            identity: None,
+            metadata: None,
         },
         BundleEvent::Dropped {
             bundle_id: test_bundle_id,
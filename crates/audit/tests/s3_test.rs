@@ -23,6 +23,8 @@ fn create_test_event(key: &str, timestamp: i64, bundle_event: BundleEvent) -> Ev
         key: key.to_string(),
         timestamp,
         event: bundle_event,
+        partition: 0,
+        offset: 0,
     }
 }
 
@@ -39,6 +41,8 @@ async fn test_event_write_and_read() -> Result<(), Box<dyn std::error::Error + S
         BundleEvent::Received {
             bundle_id,
             bundle: Box::new(bundle.clone()),
+            identity: None,
+            metadata: None,
         },
     );
 
@@ -66,6 +70,8 @@ async fn test_event_write_and_read() -> Result<(), Box<dyn std::error::Error + S
         BundleEvent::Received {
             bundle_id: bundle_id_two,
             bundle: Box::new(bundle.clone()),
+            identity: None,
+            metadata: None,
         },
     );
 
@@ -97,6 +103,8 @@ async fn test_events_appended() -> Result<(), Box<dyn std::error::Error + Send +
             BundleEvent::Received {
                 bundle_id,
                 bundle: Box::new(bundle.clone()),
+                identity: None,
+                metadata: None,
             },
         ),
         create_test_event(
@@ -147,6 +155,8 @@ async fn test_event_deduplication() -> Result<(), Box<dyn std::error::Error + Se
         BundleEvent::Received {
             bundle_id,
             bundle: Box::new(bundle.clone()),
+            identity: None,
+            metadata: None,
         },
     );
 
@@ -199,6 +209,8 @@ async fn test_concurrent_writes_for_bundle() -> Result<(), Box<dyn std::error::E
         BundleEvent::Received {
             bundle_id,
             bundle: Box::new(bundle.clone()),
+            identity: None,
+            metadata: None,
         },
     );
 
@@ -221,6 +233,8 @@ async fn test_concurrent_writes_for_bundle() -> Result<(), Box<dyn std::error::E
             BundleEvent::Received {
                 bundle_id,
                 bundle: Box::new(bundle.clone()),
+                identity: None,
+                metadata: None,
             },
         );
 
@@ -0,0 +1,306 @@
+use async_trait::async_trait;
+use axum::{Json, Router, extract::State, http::StatusCode, response::IntoResponse, routing::get};
+use serde::Serialize;
+use std::fmt;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+/// Outcome of checking a single dependency
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckStatus {
+    pub healthy: bool,
+    pub detail: Option<String>,
+}
+
+impl CheckStatus {
+    pub const fn healthy() -> Self {
+        Self {
+            healthy: true,
+            detail: None,
+        }
+    }
+
+    pub fn healthy_with(detail: impl Into<String>) -> Self {
+        Self {
+            healthy: true,
+            detail: Some(detail.into()),
+        }
+    }
+
+    pub fn unhealthy(detail: impl Into<String>) -> Self {
+        Self {
+            healthy: false,
+            detail: Some(detail.into()),
+        }
+    }
+}
+
+/// A dependency that has to be reachable for the service to be ready
+#[async_trait]
+pub trait HealthCheck: Send + Sync {
+    /// Name of the check in the readiness report
+    fn name(&self) -> &str;
+
+    async fn check(&self) -> CheckStatus;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckReport {
+    pub name: String,
+    pub healthy: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    pub elapsed_ms: u64,
+}
+
+/// Body of the `/ready` endpoint, checks are listed in the order they were registered
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadinessReport {
+    pub ready: bool,
+    pub checks: Vec<CheckReport>,
+}
+
+/// Body of the `/live` endpoint
+#[derive(Debug, Clone, Copy, Serialize)]
+struct LivenessReport {
+    live: bool,
+}
+
+/// Runs the registered health checks concurrently. A check that doesn't complete within the
+/// timeout is reported as unhealthy.
+pub struct Readiness {
+    checks: Vec<Arc<dyn HealthCheck>>,
+    timeout: Duration,
+}
+
+impl fmt::Debug for Readiness {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Readiness")
+            .field(
+                "checks",
+                &self
+                    .checks
+                    .iter()
+                    .map(|check| check.name())
+                    .collect::<Vec<_>>(),
+            )
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+impl Readiness {
+    pub const fn new(timeout: Duration) -> Self {
+        Self {
+            checks: Vec::new(),
+            timeout,
+        }
+    }
+
+    pub fn with_check(mut self, check: impl HealthCheck + 'static) -> Self {
+        self.checks.push(Arc::new(check));
+        self
+    }
+
+    pub async fn report(&self) -> ReadinessReport {
+        let running: Vec<(String, JoinHandle<(CheckStatus, Duration)>)> = self
+            .checks
+            .iter()
+            .map(|check| {
+                let check = check.clone();
+                let timeout = self.timeout;
+                let handle = tokio::spawn(async move {
+                    let start = Instant::now();
+                    let status = tokio::time::timeout(timeout, check.check())
+                        .await
+                        .unwrap_or_else(|_| {
+                            CheckStatus::unhealthy(format!(
+                                "timed out after {}ms",
+                                timeout.as_millis()
+                            ))
+                        });
+                    (status, start.elapsed())
+                });
+                (check.name().to_string(), handle)
+            })
+            .collect();
+
+        let mut checks = Vec::with_capacity(running.len());
+        for (name, handle) in running {
+            let (status, elapsed) = handle.await.unwrap_or_else(|e| {
+                (
+                    CheckStatus::unhealthy(format!("check failed: {e}")),
+                    Duration::ZERO,
+                )
+            });
+            if !status.healthy {
+                warn!(message = "Readiness check failed", check = %name, detail = ?status.detail);
+            }
+            checks.push(CheckReport {
+                name,
+                healthy: status.healthy,
+                detail: status.detail,
+                elapsed_ms: elapsed.as_millis() as u64,
+            });
+        }
+
+        ReadinessReport {
+            ready: checks.iter().all(|check| check.healthy),
+            checks,
+        }
+    }
+}
+
+/// Kept for existing probes, always returns 200 OK
+async fn health() -> impl IntoResponse {
+    StatusCode::OK
+}
+
+/// Returns 200 OK as long as the process is serving requests
+async fn live() -> impl IntoResponse {
+    Json(LivenessReport { live: true })
+}
+
+/// Returns 200 OK when all checks pass and 503 otherwise, with the report as the body
+async fn ready(State(readiness): State<Arc<Readiness>>) -> impl IntoResponse {
+    let report = readiness.report().await;
+    let status = if report.ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(report))
+}
+
+/// Bind and start the health check server on the specified address, serving `/health`, `/live`
+/// and `/ready`. Returns a handle that can be awaited to run the server.
+pub async fn bind_health_server(
+    addr: SocketAddr,
+    readiness: Readiness,
+) -> anyhow::Result<(SocketAddr, JoinHandle<anyhow::Result<()>>)> {
+    let app = Router::new()
+        .route("/health", get(health))
+        .route("/live", get(live))
+        .route("/ready", get(ready))
+        .with_state(Arc::new(readiness));
+
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    let bound_addr = listener.local_addr()?;
+
+    info!(
+        message = "Health check server bound successfully",
+        address = %bound_addr
+    );
+
+    let handle = tokio::spawn(async move {
+        axum::serve(listener, app).await?;
+        Ok(())
+    });
+
+    Ok((bound_addr, handle))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct StaticCheck {
+        name: &'static str,
+        status: CheckStatus,
+        delay: Duration,
+    }
+
+    #[async_trait]
+    impl HealthCheck for StaticCheck {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        async fn check(&self) -> CheckStatus {
+            tokio::time::sleep(self.delay).await;
+            self.status.clone()
+        }
+    }
+
+    fn check(name: &'static str, status: CheckStatus, delay: Duration) -> StaticCheck {
+        StaticCheck {
+            name,
+            status,
+            delay,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_all_checks_healthy() {
+        let readiness = Readiness::new(Duration::from_secs(1))
+            .with_check(check("kafka", CheckStatus::healthy(), Duration::ZERO))
+            .with_check(check(
+                "s3",
+                CheckStatus::healthy_with("bucket reachable"),
+                Duration::ZERO,
+            ));
+
+        let report = readiness.report().await;
+        assert!(report.ready);
+        let names: Vec<_> = report.checks.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["kafka", "s3"]);
+        assert_eq!(report.checks[1].detail.as_deref(), Some("bucket reachable"));
+    }
+
+    #[tokio::test]
+    async fn test_failing_and_slow_checks() {
+        let readiness = Readiness::new(Duration::from_millis(50))
+            .with_check(check("kafka", CheckStatus::healthy(), Duration::ZERO))
+            .with_check(check(
+                "simulation",
+                CheckStatus::unhealthy("head is stale"),
+                Duration::ZERO,
+            ))
+            .with_check(check("s3", CheckStatus::healthy(), Duration::from_secs(5)));
+
+        let report = readiness.report().await;
+        assert!(!report.ready);
+        assert!(report.checks[0].healthy);
+        assert_eq!(
+            report.checks[1],
+            CheckReport {
+                name: "simulation".to_string(),
+                healthy: false,
+                detail: Some("head is stale".to_string()),
+                elapsed_ms: report.checks[1].elapsed_ms,
+            }
+        );
+        assert!(!report.checks[2].healthy);
+        assert_eq!(
+            report.checks[2].detail.as_deref(),
+            Some("timed out after 50ms")
+        );
+    }
+
+    #[test]
+    fn test_report_serialization() {
+        let report = ReadinessReport {
+            ready: false,
+            checks: vec![CheckReport {
+                name: "kafka".to_string(),
+                healthy: false,
+                detail: Some("broker down".to_string()),
+                elapsed_ms: 3,
+            }],
+        };
+        assert_eq!(
+            serde_json::to_value(&report).unwrap(),
+            serde_json::json!({
+                "ready": false,
+                "checks": [
+                    { "name": "kafka", "healthy": false, "detail": "broker down", "elapsedMs": 3 }
+                ]
+            })
+        );
+    }
+}
@@ -6,14 +6,16 @@
 
 use alloy_rpc_types as _;
 
+pub mod health;
 pub mod kafka;
 pub mod logger;
 pub mod metrics;
+pub mod shutdown;
 #[cfg(any(test, feature = "test-utils"))]
 pub mod test_utils;
 pub mod types;
 
 pub use types::{
-    AcceptedBundle, Bundle, BundleExtensions, BundleHash, BundleTxs, CancelBundle,
-    MeterBundleResponse,
+    AcceptedBundle, AuthMethod, Bundle, BundleExtensions, BundleHash, BundleTxs, BundleVerdict,
+    CallBundleResponse, CancelBundle, CancelledBundle, MeterBundleResponse, SearcherIdentity,
 };
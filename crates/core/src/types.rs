@@ -134,6 +134,17 @@ pub struct CancelBundle {
     pub replacement_uuid: String,
 }
 
+/// `CancelledBundle` is sent over the wire when a bundle is cancelled with `eth_cancelBundle`.
+/// It is keyed by the bundle hash, so it lands on the same partition as the `AcceptedBundle`
+/// it cancels. It's tagged with `"type": "CancelledBundle"` to tell it apart from the bundles on
+/// the ingress topic.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "type")]
+pub struct CancelledBundle {
+    pub uuid: Uuid,
+    pub bundle_hash: B256,
+}
+
 /// `AcceptedBundle` is the type that is sent over the wire.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AcceptedBundle {
@@ -178,10 +189,53 @@ pub struct AcceptedBundle {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub replacement_uuid: Option<Uuid>,
 
+    /// Transactions the builder may drop from the bundle if they fail, instead of discarding
+    /// the whole bundle. Metering simulates them like any other transaction.
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub dropping_tx_hashes: Vec<TxHash>,
 
+    /// Transactions that must not revert, i.e. the ones in neither `reverting_tx_hashes` nor
+    /// `dropping_tx_hashes`. The builder must not include the bundle if any of them reverts.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub protected_tx_hashes: Vec<TxHash>,
+
     pub meter_bundle_response: MeterBundleResponse,
+
+    /// Incremented each time the bundle is replaced through its `replacement_uuid`. Consumers
+    /// should only keep the highest version seen for a given `uuid`.
+    #[serde(default)]
+    pub version: u64,
+
+    /// The searcher that submitted the bundle, when it authenticated with ingress.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub identity: Option<SearcherIdentity>,
+}
+
+/// How a searcher authenticated with ingress.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum AuthMethod {
+    /// The `X-Api-Key` header.
+    ApiKey,
+    /// The `X-Flashbots-Signature` header, a signature over the request body.
+    FlashbotsSignature,
+}
+
+/// A searcher authenticated by ingress, along with the tier it is served with.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SearcherIdentity {
+    /// Name of the API key or signer in the key file, or the signer address for signers that
+    /// aren't registered.
+    pub id: String,
+    pub auth: AuthMethod,
+    /// Address that signed the request, for Flashbots signatures.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signer: Option<Address>,
+    pub tier: String,
+    /// Higher priority searchers are served first by builders that honor it.
+    #[serde(default)]
+    pub priority: u32,
 }
 
 pub trait BundleTxs {
@@ -229,6 +283,18 @@ impl<T: BundleTxs> BundleExtensions for T {
     }
 }
 
+impl ParsedBundle {
+    /// Hashes of the transactions that may neither revert nor be dropped
+    pub fn protected_tx_hashes(&self) -> Vec<TxHash> {
+        self.txn_hashes()
+            .into_iter()
+            .filter(|hash| {
+                !self.reverting_tx_hashes.contains(hash) && !self.dropping_tx_hashes.contains(hash)
+            })
+            .collect()
+    }
+}
+
 impl BundleTxs for ParsedBundle {
     fn transactions(&self) -> &Vec<Recovered<OpTxEnvelope>> {
         &self.txs
@@ -243,6 +309,7 @@ impl BundleTxs for AcceptedBundle {
 
 impl AcceptedBundle {
     pub fn new(bundle: ParsedBundle, meter_bundle_response: MeterBundleResponse) -> Self {
+        let protected_tx_hashes = bundle.protected_tx_hashes();
         Self {
             uuid: bundle.replacement_uuid.unwrap_or_else(|| {
                 Uuid::new_v5(&Uuid::NAMESPACE_OID, bundle.bundle_hash().as_slice())
@@ -256,13 +323,31 @@ impl AcceptedBundle {
             reverting_tx_hashes: bundle.reverting_tx_hashes,
             replacement_uuid: bundle.replacement_uuid,
             dropping_tx_hashes: bundle.dropping_tx_hashes,
+            protected_tx_hashes,
             meter_bundle_response,
+            version: 0,
+            identity: None,
         }
     }
 
+    pub fn with_version(mut self, version: u64) -> Self {
+        self.version = version;
+        self
+    }
+
+    pub fn with_identity(mut self, identity: Option<SearcherIdentity>) -> Self {
+        self.identity = identity;
+        self
+    }
+
     pub const fn uuid(&self) -> &Uuid {
         &self.uuid
     }
+
+    /// Returns true if this bundle supersedes an earlier version with the same `uuid`.
+    pub const fn is_replacement(&self) -> bool {
+        self.version > 0
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -278,6 +363,11 @@ pub struct TransactionResult {
     pub tx_hash: TxHash,
     pub value: U256,
     pub execution_time_us: u128,
+    /// Whether the transaction reverted during simulation, `None` when the node doesn't report
+    /// it. Revert protection rejects a bundle rather than assume a protected transaction
+    /// succeeded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reverted: Option<bool>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
@@ -302,6 +392,24 @@ pub struct MeterBundleResponse {
     pub state_root_time_us: u128,
 }
 
+/// A check a simulated bundle failed, that would have rejected it if it was sent with
+/// `eth_sendBundle`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleVerdict {
+    pub code: i32,
+    pub message: String,
+}
+
+/// `CallBundleResponse` is returned by `eth_callBundle`, the metering result of a bundle together
+/// with the checks it failed. An empty `verdicts` list means the bundle would be accepted.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct CallBundleResponse {
+    pub meter_bundle_response: MeterBundleResponse,
+    pub verdicts: Vec<BundleVerdict>,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -364,6 +472,8 @@ mod tests {
 
         assert_eq!(*bundle.uuid(), uuid);
         assert_eq!(bundle.replacement_uuid, Some(uuid));
+        assert_eq!(bundle.version, 0);
+        assert!(!bundle.is_replacement());
         assert_eq!(bundle.txn_hashes().len(), 2);
         assert_eq!(bundle.txn_hashes()[0], tx1.tx_hash());
         assert_eq!(bundle.txn_hashes()[1], tx2.tx_hash());
@@ -491,4 +601,155 @@ mod tests {
         // however, the UUID should be the same
         assert_eq!(bundle1.uuid(), bundle2.uuid());
     }
+
+    #[test]
+    fn test_accepted_bundle_version() {
+        let alice = PrivateKeySigner::random();
+        let tx = create_transaction(alice.clone(), 1, alice.address());
+        let bundle = AcceptedBundle::new(
+            Bundle {
+                txs: vec![tx.encoded_2718().into()],
+                block_number: 1,
+                ..Default::default()
+            }
+            .try_into()
+            .unwrap(),
+            create_test_meter_bundle_response(),
+        )
+        .with_version(2);
+
+        assert!(bundle.is_replacement());
+
+        let mut json = serde_json::to_value(&bundle).unwrap();
+        assert_eq!(json["version"], 2);
+
+        // payloads produced before versioning was introduced decode as the first version
+        json.as_object_mut().unwrap().remove("version");
+        let deserialized: AcceptedBundle = serde_json::from_value(json).unwrap();
+        assert_eq!(deserialized.version, 0);
+    }
+
+    #[test]
+    fn test_accepted_bundle_identity() {
+        let alice = PrivateKeySigner::random();
+        let tx = create_transaction(alice.clone(), 1, alice.address());
+        let bundle = AcceptedBundle::new(
+            Bundle {
+                txs: vec![tx.encoded_2718().into()],
+                block_number: 1,
+                ..Default::default()
+            }
+            .try_into()
+            .unwrap(),
+            create_test_meter_bundle_response(),
+        );
+
+        // anonymous bundles serialize as before
+        let json = serde_json::to_value(&bundle).unwrap();
+        assert!(json.get("identity").is_none());
+
+        let identity = SearcherIdentity {
+            id: "searcher".to_string(),
+            auth: AuthMethod::FlashbotsSignature,
+            signer: Some(alice.address()),
+            tier: "partner".to_string(),
+            priority: 10,
+        };
+        let json = serde_json::to_value(bundle.with_identity(Some(identity.clone()))).unwrap();
+        assert_eq!(json["identity"]["auth"], "flashbotsSignature");
+        let deserialized: AcceptedBundle = serde_json::from_value(json).unwrap();
+        assert_eq!(deserialized.identity, Some(identity));
+    }
+
+    #[test]
+    fn test_dropping_tx_hashes_carried_to_accepted_bundle() {
+        let alice = PrivateKeySigner::random();
+        let tx1 = create_transaction(alice.clone(), 1, alice.address());
+        let tx2 = create_transaction(alice.clone(), 2, alice.address());
+
+        let bundle = AcceptedBundle::new(
+            Bundle {
+                txs: vec![tx1.encoded_2718().into(), tx2.encoded_2718().into()],
+                block_number: 1,
+                dropping_tx_hashes: vec![tx2.tx_hash()],
+                ..Default::default()
+            }
+            .try_into()
+            .unwrap(),
+            create_test_meter_bundle_response(),
+        );
+        assert_eq!(bundle.dropping_tx_hashes, vec![tx2.tx_hash()]);
+
+        let json = serde_json::to_string(&bundle).unwrap();
+        let deserialized: AcceptedBundle = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized.dropping_tx_hashes, vec![tx2.tx_hash()]);
+    }
+
+    #[test]
+    fn test_protected_tx_hashes() {
+        let alice = PrivateKeySigner::random();
+        let tx1 = create_transaction(alice.clone(), 1, alice.address());
+        let tx2 = create_transaction(alice.clone(), 2, alice.address());
+        let tx3 = create_transaction(alice.clone(), 3, alice.address());
+
+        let bundle = AcceptedBundle::new(
+            Bundle {
+                txs: vec![
+                    tx1.encoded_2718().into(),
+                    tx2.encoded_2718().into(),
+                    tx3.encoded_2718().into(),
+                ],
+                block_number: 1,
+                reverting_tx_hashes: vec![tx1.tx_hash()],
+                dropping_tx_hashes: vec![tx3.tx_hash()],
+                ..Default::default()
+            }
+            .try_into()
+            .unwrap(),
+            create_test_meter_bundle_response(),
+        );
+
+        assert_eq!(bundle.protected_tx_hashes, vec![tx2.tx_hash()]);
+    }
+
+    #[test]
+    fn test_transaction_result_without_reverted() {
+        let json = r#"{
+            "coinbaseDiff": "0x0",
+            "ethSentToCoinbase": "0x0",
+            "fromAddress": "0x0000000000000000000000000000000000000001",
+            "gasFees": "0x0",
+            "gasPrice": "0x0",
+            "gasUsed": 21000,
+            "toAddress": null,
+            "txHash": "0x0000000000000000000000000000000000000000000000000000000000000002",
+            "value": "0x0",
+            "executionTimeUs": 10
+        }"#;
+
+        let result: TransactionResult = serde_json::from_str(json).unwrap();
+        assert_eq!(result.reverted, None);
+    }
+
+    #[test]
+    fn test_bundle_without_reverting_tx_hashes_protects_all_txs() {
+        let alice = PrivateKeySigner::random();
+        let tx1 = create_transaction(alice.clone(), 1, alice.address());
+        let tx2 = create_transaction(alice.clone(), 2, alice.address());
+
+        let bundle = AcceptedBundle::new(
+            Bundle {
+                txs: vec![tx1.encoded_2718().into(), tx2.encoded_2718().into()],
+                ..Default::default()
+            }
+            .try_into()
+            .unwrap(),
+            create_test_meter_bundle_response(),
+        );
+
+        assert_eq!(
+            bundle.protected_tx_hashes,
+            vec![tx1.tx_hash(), tx2.tx_hash()]
+        );
+    }
 }
@@ -0,0 +1,657 @@
+use alloy_primitives::bytes::Bytes;
+use alloy_primitives::{Address, Signature, hex, keccak256};
+use anyhow::{Context as _, bail};
+use jsonrpsee::{
+    core::BoxError,
+    server::{HttpBody, HttpRequest, HttpResponse},
+    types::Extensions,
+};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::future::{Future, poll_fn};
+use std::path::Path;
+use std::pin::{Pin, pin};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tips_core::{AuthMethod, SearcherIdentity};
+use tower::{Layer, Service};
+use tracing::debug;
+
+use crate::metrics::record_auth_rejected;
+
+/// Largest body buffered to check its signature, the default request size limit of the server
+const MAX_SIGNED_BODY_SIZE: usize = 10 * 1024 * 1024;
+
+/// Name of the tier used when the key file doesn't set a default one
+const DEFAULT_TIER: &str = "default";
+
+/// Limits and privileges of the searchers in a tier
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, rename_all = "camelCase", deny_unknown_fields)]
+pub struct Tier {
+    /// Key of the tier in the key file
+    #[serde(skip)]
+    pub name: String,
+    /// Scales the rate and burst of every rate limit
+    pub rate_limit_multiplier: f64,
+    /// Whether `eth_sendBackrunBundle` is allowed
+    pub backrun: bool,
+    /// Passed on to builders with the searcher identity
+    pub priority: u32,
+}
+
+impl Default for Tier {
+    fn default() -> Self {
+        Self {
+            name: DEFAULT_TIER.to_string(),
+            rate_limit_multiplier: 1.0,
+            backrun: true,
+            priority: 0,
+        }
+    }
+}
+
+/// The static key file the authenticator is loaded from
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct KeyFile {
+    #[serde(default)]
+    tiers: HashMap<String, Tier>,
+    /// Tier of unauthenticated requests and of signers that aren't listed
+    default_tier: Option<String>,
+    #[serde(default)]
+    api_keys: Vec<ApiKeyEntry>,
+    #[serde(default)]
+    signers: Vec<SignerEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ApiKeyEntry {
+    name: String,
+    key: String,
+    tier: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SignerEntry {
+    name: String,
+    address: Address,
+    tier: String,
+}
+
+/// The searcher sending a request and the tier it is served with, attached to the request
+/// extensions by [`AuthLayer`]. Requests without credentials are anonymous.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Caller {
+    pub identity: Option<SearcherIdentity>,
+    pub tier: Tier,
+}
+
+impl Caller {
+    fn authenticated(id: String, auth: AuthMethod, signer: Option<Address>, tier: Tier) -> Self {
+        Self {
+            identity: Some(SearcherIdentity {
+                id,
+                auth,
+                signer,
+                tier: tier.name.clone(),
+                priority: tier.priority,
+            }),
+            tier,
+        }
+    }
+
+    /// The caller of a request, anonymous with the default tier when authentication is disabled
+    pub fn from_extensions(ext: &Extensions) -> Self {
+        ext.get::<Self>().cloned().unwrap_or_default()
+    }
+}
+
+/// Why a request was rejected by the [`Authenticator`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthError {
+    /// Neither an API key nor a signature was sent, while authentication is required
+    MissingCredentials,
+    UnknownApiKey,
+    /// The `X-Flashbots-Signature` header isn't `<address>:<signature>`
+    MalformedSignature,
+    /// The signature doesn't match the address and the request body
+    InvalidSignature,
+    /// The body couldn't be read to check its signature
+    UnreadableBody,
+    /// A WebSocket upgrade request was signed. Its body is empty, so the signature could be
+    /// replayed to open connections as the signer
+    SignedWebSocket,
+}
+
+impl AuthError {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::MissingCredentials => "missing-credentials",
+            Self::UnknownApiKey => "unknown-api-key",
+            Self::MalformedSignature => "malformed-signature",
+            Self::InvalidSignature => "invalid-signature",
+            Self::UnreadableBody => "unreadable-body",
+            Self::SignedWebSocket => "signed-websocket",
+        }
+    }
+
+    const fn status(&self) -> u16 {
+        match self {
+            Self::UnreadableBody => 400,
+            _ => 401,
+        }
+    }
+}
+
+impl fmt::Display for AuthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::MissingCredentials => "An X-Api-Key or X-Flashbots-Signature header is required",
+            Self::UnknownApiKey => "Unknown API key",
+            Self::MalformedSignature => {
+                "Malformed X-Flashbots-Signature header, expected <address>:<signature>"
+            }
+            Self::InvalidSignature => "Invalid X-Flashbots-Signature",
+            Self::UnreadableBody => "Failed to read the request body",
+            Self::SignedWebSocket => {
+                "X-Flashbots-Signature can't authenticate WebSocket connections, use X-Api-Key"
+            }
+        })
+    }
+}
+
+/// Identifies searchers from their API key or Flashbots signature, using a static key file
+pub struct Authenticator {
+    api_keys: HashMap<String, Caller>,
+    signers: HashMap<Address, Caller>,
+    default_tier: Tier,
+    /// Rejects requests without credentials
+    required: bool,
+}
+
+impl fmt::Debug for Authenticator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Authenticator")
+            .field("api_keys", &self.api_keys.len())
+            .field("signers", &self.signers.len())
+            .field("default_tier", &self.default_tier.name)
+            .field("required", &self.required)
+            .finish()
+    }
+}
+
+impl Authenticator {
+    pub async fn load(path: &Path, required: bool) -> anyhow::Result<Self> {
+        let contents = tokio::fs::read(path).await?;
+        let key_file = serde_json::from_slice(&contents)
+            .with_context(|| format!("Invalid auth keys file {}", path.display()))?;
+        Self::from_key_file(key_file, required)
+    }
+
+    fn from_key_file(mut key_file: KeyFile, required: bool) -> anyhow::Result<Self> {
+        for (name, tier) in &mut key_file.tiers {
+            if !tier.rate_limit_multiplier.is_finite() || tier.rate_limit_multiplier <= 0.0 {
+                bail!("Tier '{name}' must have a positive rate limit multiplier");
+            }
+            tier.name = name.clone();
+        }
+        let tier = |name: &str| {
+            key_file
+                .tiers
+                .get(name)
+                .cloned()
+                .with_context(|| format!("Unknown tier '{name}'"))
+        };
+        let default_tier = match &key_file.default_tier {
+            Some(name) => tier(name)?,
+            None => Tier::default(),
+        };
+
+        let mut api_keys = HashMap::new();
+        for entry in key_file.api_keys {
+            let caller =
+                Caller::authenticated(entry.name, AuthMethod::ApiKey, None, tier(&entry.tier)?);
+            if api_keys.insert(entry.key, caller).is_some() {
+                bail!("Duplicate API key in the auth keys file");
+            }
+        }
+
+        let mut signers = HashMap::new();
+        for entry in key_file.signers {
+            let caller = Caller::authenticated(
+                entry.name,
+                AuthMethod::FlashbotsSignature,
+                Some(entry.address),
+                tier(&entry.tier)?,
+            );
+            if signers.insert(entry.address, caller).is_some() {
+                bail!("Duplicate signer {} in the auth keys file", entry.address);
+            }
+        }
+
+        Ok(Self {
+            api_keys,
+            signers,
+            default_tier,
+            required,
+        })
+    }
+
+    pub fn authenticate_api_key(&self, key: &str) -> Result<Caller, AuthError> {
+        self.api_keys
+            .get(key)
+            .cloned()
+            .ok_or(AuthError::UnknownApiKey)
+    }
+
+    /// Checks an `X-Flashbots-Signature` header, an EIP-191 signature of the hex encoded hash of
+    /// the body. Any valid signer is accepted, the ones not listed are served with the default tier.
+    pub fn authenticate_signature(&self, header: &str, body: &[u8]) -> Result<Caller, AuthError> {
+        let (address, signature) = header
+            .split_once(':')
+            .ok_or(AuthError::MalformedSignature)?;
+        let address: Address = address.parse().map_err(|_| AuthError::MalformedSignature)?;
+        let signature: Signature = signature
+            .parse()
+            .map_err(|_| AuthError::MalformedSignature)?;
+
+        let message = hex::encode_prefixed(keccak256(body));
+        let signer = signature
+            .recover_address_from_msg(message)
+            .map_err(|_| AuthError::InvalidSignature)?;
+        if signer != address {
+            return Err(AuthError::InvalidSignature);
+        }
+
+        Ok(self.signers.get(&signer).cloned().unwrap_or_else(|| {
+            Caller::authenticated(
+                signer.to_string(),
+                AuthMethod::FlashbotsSignature,
+                Some(signer),
+                self.default_tier.clone(),
+            )
+        }))
+    }
+
+    /// Requests without credentials are served with the default tier, unless authentication is
+    /// required
+    pub fn anonymous(&self) -> Result<Caller, AuthError> {
+        if self.required {
+            return Err(AuthError::MissingCredentials);
+        }
+        Ok(Caller {
+            identity: None,
+            tier: self.default_tier.clone(),
+        })
+    }
+}
+
+/// Authenticates HTTP and WebSocket requests with an [`Authenticator`], attaching the [`Caller`]
+/// to their extensions. Requests pass through unauthenticated if there is no authenticator.
+#[derive(Debug, Clone, Default)]
+pub struct AuthLayer {
+    authenticator: Option<Arc<Authenticator>>,
+}
+
+impl AuthLayer {
+    pub fn new(authenticator: Option<Authenticator>) -> Self {
+        Self {
+            authenticator: authenticator.map(Arc::new),
+        }
+    }
+}
+
+impl<S> Layer<S> for AuthLayer {
+    type Service = Auth<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Auth {
+            inner,
+            authenticator: self.authenticator.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Auth<S> {
+    inner: S,
+    authenticator: Option<Arc<Authenticator>>,
+}
+
+impl<S, B> Service<HttpRequest<B>> for Auth<S>
+where
+    S: Service<HttpRequest<HttpBody>, Response = HttpResponse> + Clone + Send + 'static,
+    S::Error: Into<BoxError> + 'static,
+    S::Future: Send + 'static,
+    B: http_body::Body<Data = Bytes> + Send + 'static,
+    B::Error: Into<BoxError>,
+{
+    type Response = HttpResponse;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<HttpResponse, BoxError>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, request: HttpRequest<B>) -> Self::Future {
+        let Some(authenticator) = self.authenticator.clone() else {
+            let response = self.inner.call(request.map(HttpBody::new));
+            return Box::pin(async move { response.await.map_err(Into::into) });
+        };
+
+        // the service made ready by `poll_ready` is taken, leaving a clone in its place
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        Box::pin(async move {
+            let (mut parts, body) = request.into_parts();
+            let header = |name: &str| {
+                parts
+                    .headers
+                    .get(name)
+                    .and_then(|value| value.to_str().ok())
+                    .filter(|value| !value.is_empty())
+                    .map(str::to_string)
+            };
+            let signature = header("x-flashbots-signature");
+            let api_key = header("x-api-key");
+            let websocket =
+                header("upgrade").is_some_and(|upgrade| upgrade.eq_ignore_ascii_case("websocket"));
+
+            let (caller, body) = match signature {
+                Some(_) if websocket => (Err(AuthError::SignedWebSocket), HttpBody::new(body)),
+                // only signed requests are buffered, the signature covers the exact body
+                Some(signature) => match read_body(body).await {
+                    Ok(body) => (
+                        authenticator.authenticate_signature(&signature, &body),
+                        HttpBody::from(body),
+                    ),
+                    Err(_) => (Err(AuthError::UnreadableBody), HttpBody::empty()),
+                },
+                None => (
+                    api_key.map_or_else(
+                        || authenticator.anonymous(),
+                        |key| authenticator.authenticate_api_key(&key),
+                    ),
+                    HttpBody::new(body),
+                ),
+            };
+
+            match caller {
+                Ok(caller) => {
+                    parts.extensions.insert(caller);
+                    inner
+                        .call(HttpRequest::from_parts(parts, body))
+                        .await
+                        .map_err(Into::into)
+                }
+                Err(err) => {
+                    record_auth_rejected(err);
+                    debug!(message = "Rejected request", reason = %err);
+                    Ok(rejected(err))
+                }
+            }
+        })
+    }
+}
+
+/// Reads the whole body, up to [`MAX_SIGNED_BODY_SIZE`]
+async fn read_body<B>(body: B) -> Result<Vec<u8>, BoxError>
+where
+    B: http_body::Body<Data = Bytes>,
+    B::Error: Into<BoxError>,
+{
+    let mut body = pin!(body);
+    let mut bytes = Vec::new();
+    while let Some(frame) = poll_fn(|cx| body.as_mut().poll_frame(cx)).await {
+        if let Ok(data) = frame.map_err(Into::into)?.into_data() {
+            if bytes.len() + data.len() > MAX_SIGNED_BODY_SIZE {
+                return Err("request body too large".into());
+            }
+            bytes.extend_from_slice(&data);
+        }
+    }
+    Ok(bytes)
+}
+
+fn rejected(err: AuthError) -> HttpResponse {
+    HttpResponse::builder()
+        .status(err.status())
+        .body(HttpBody::from(err.to_string()))
+        .expect("static response is valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::RATE_LIMITED_CODE;
+    use crate::rate_limit::{RateLimiter, start_ingress_server};
+    use alloy_signer::SignerSync;
+    use alloy_signer_local::PrivateKeySigner;
+    use jsonrpsee::RpcModule;
+    use serde_json::{Value, json};
+
+    fn authenticator(required: bool, signer: Address) -> Authenticator {
+        let key_file = serde_json::from_value(json!({
+            "tiers": {
+                "basic": { "backrun": false },
+                "partner": { "rateLimitMultiplier": 10, "priority": 5 }
+            },
+            "defaultTier": "basic",
+            "apiKeys": [{ "name": "acme", "key": "secret", "tier": "partner" }],
+            "signers": [{ "name": "searcher", "address": signer, "tier": "partner" }]
+        }))
+        .unwrap();
+        Authenticator::from_key_file(key_file, required).unwrap()
+    }
+
+    fn sign(signer: &PrivateKeySigner, body: &[u8]) -> String {
+        let message = hex::encode_prefixed(keccak256(body));
+        let signature = signer.sign_message_sync(message.as_bytes()).unwrap();
+        format!(
+            "{}:{}",
+            signer.address(),
+            hex::encode_prefixed(signature.as_bytes())
+        )
+    }
+
+    #[test]
+    fn test_key_file_validation() {
+        let load = |value: serde_json::Value| {
+            Authenticator::from_key_file(serde_json::from_value(value).unwrap(), false)
+        };
+        assert!(load(json!({ "defaultTier": "missing" })).is_err());
+        assert!(
+            load(json!({ "apiKeys": [{ "name": "a", "key": "k", "tier": "missing" }] })).is_err()
+        );
+        assert!(load(json!({ "tiers": { "zero": { "rateLimitMultiplier": 0 } } })).is_err());
+        assert!(
+            load(json!({
+                "tiers": { "basic": {} },
+                "apiKeys": [
+                    { "name": "a", "key": "k", "tier": "basic" },
+                    { "name": "b", "key": "k", "tier": "basic" }
+                ]
+            }))
+            .is_err()
+        );
+
+        let authenticator = load(json!({})).unwrap();
+        assert_eq!(authenticator.anonymous().unwrap(), Caller::default());
+    }
+
+    #[test]
+    fn test_authenticate_api_key() {
+        let authenticator = authenticator(false, Address::ZERO);
+
+        let caller = authenticator.authenticate_api_key("secret").unwrap();
+        let identity = caller.identity.unwrap();
+        assert_eq!(identity.id, "acme");
+        assert_eq!(identity.auth, AuthMethod::ApiKey);
+        assert_eq!(identity.tier, "partner");
+        assert_eq!(identity.priority, 5);
+        assert_eq!(caller.tier.rate_limit_multiplier, 10.0);
+
+        assert_eq!(
+            authenticator.authenticate_api_key("wrong"),
+            Err(AuthError::UnknownApiKey)
+        );
+    }
+
+    #[test]
+    fn test_authenticate_signature() {
+        let searcher = PrivateKeySigner::random();
+        let unlisted = PrivateKeySigner::random();
+        let authenticator = authenticator(false, searcher.address());
+        let body = br#"{"jsonrpc":"2.0","id":1,"method":"eth_sendBundle","params":[]}"#;
+
+        let caller = authenticator
+            .authenticate_signature(&sign(&searcher, body), body)
+            .unwrap();
+        let identity = caller.identity.unwrap();
+        assert_eq!(identity.id, "searcher");
+        assert_eq!(identity.signer, Some(searcher.address()));
+        assert_eq!(identity.tier, "partner");
+
+        // signers that aren't listed are served with the default tier
+        let caller = authenticator
+            .authenticate_signature(&sign(&unlisted, body), body)
+            .unwrap();
+        assert_eq!(caller.identity.unwrap().id, unlisted.address().to_string());
+        assert!(!caller.tier.backrun);
+
+        assert_eq!(
+            authenticator.authenticate_signature(&sign(&searcher, body), b"{}"),
+            Err(AuthError::InvalidSignature)
+        );
+        let signature = sign(&searcher, body);
+        let (_, signature) = signature.split_once(':').unwrap();
+        assert_eq!(
+            authenticator
+                .authenticate_signature(&format!("{}:{signature}", unlisted.address()), body),
+            Err(AuthError::InvalidSignature)
+        );
+        assert_eq!(
+            authenticator.authenticate_signature("0x1234", body),
+            Err(AuthError::MalformedSignature)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ingress_server_authentication() {
+        let searcher = PrivateKeySigner::random();
+        let mut module = RpcModule::new(());
+        module
+            .register_method("tips_caller", |_, _, ext| {
+                Caller::from_extensions(ext)
+                    .identity
+                    .map(|identity| identity.id)
+            })
+            .unwrap();
+        // the partner tier has 10 times the limits
+        let limiter = RateLimiter::new(["tips_caller:ip=0.001/1".parse().unwrap()]);
+        let (addr, handle) = start_ingress_server(
+            "127.0.0.1:0".parse().unwrap(),
+            limiter,
+            Some(authenticator(false, searcher.address())),
+            true,
+            module,
+        )
+        .await
+        .unwrap();
+
+        let body =
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "tips_caller", "params": [] }).to_string();
+        let send = |ip: &'static str, header: Option<(&'static str, String)>| {
+            let mut request = reqwest::Client::new()
+                .post(format!("http://{addr}"))
+                .header("content-type", "application/json")
+                .header("x-forwarded-for", ip)
+                .body(body.clone());
+            if let Some((name, value)) = header {
+                request = request.header(name, value);
+            }
+            request.send()
+        };
+
+        let response = send("10.0.0.1", Some(("x-api-key", "wrong".to_string())))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 401);
+
+        let response = send("10.0.0.1", None).await.unwrap();
+        let response: Value = response.json().await.unwrap();
+        assert_eq!(response["result"], Value::Null);
+
+        let signature = sign(&searcher, body.as_bytes());
+        for _ in 0..10 {
+            let response = send(
+                "10.0.0.2",
+                Some(("x-flashbots-signature", signature.clone())),
+            )
+            .await
+            .unwrap();
+            let response: Value = response.json().await.unwrap();
+            assert_eq!(response["result"], json!("searcher"));
+        }
+        let response = send("10.0.0.2", Some(("x-flashbots-signature", signature)))
+            .await
+            .unwrap();
+        let response: Value = response.json().await.unwrap();
+        assert_eq!(response["error"]["code"], json!(RATE_LIMITED_CODE));
+
+        let response = send(
+            "10.0.0.3",
+            Some(("x-flashbots-signature", sign(&searcher, b"{}"))),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.status(), 401);
+
+        handle.stop().unwrap();
+    }
+
+    #[test]
+    fn test_anonymous() {
+        let caller = authenticator(false, Address::ZERO).anonymous().unwrap();
+        assert!(caller.identity.is_none());
+        assert!(!caller.tier.backrun);
+
+        assert_eq!(
+            authenticator(true, Address::ZERO).anonymous(),
+            Err(AuthError::MissingCredentials)
+        );
+    }
+    #[tokio::test]
+    async fn test_signed_websocket_rejected() {
+        let searcher = PrivateKeySigner::random();
+        let (addr, handle) = start_ingress_server(
+            "127.0.0.1:0".parse().unwrap(),
+            RateLimiter::new([]),
+            Some(authenticator(false, searcher.address())),
+            false,
+            RpcModule::new(()),
+        )
+        .await
+        .unwrap();
+
+        // the signature of an empty upgrade request could be replayed by anyone who saw it
+        let response = reqwest::Client::new()
+            .get(format!("http://{addr}"))
+            .header("connection", "upgrade")
+            .header("upgrade", "websocket")
+            .header("sec-websocket-version", "13")
+            .header("sec-websocket-key", "dGhlIHNhbXBsZSBub25jZQ==")
+            .header("x-flashbots-signature", sign(&searcher, b""))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 401);
+
+        handle.stop().unwrap();
+    }
+}
@@ -0,0 +1,627 @@
+use alloy_primitives::{TxHash, hex};
+use async_trait::async_trait;
+use backon::{ExponentialBuilder, Retryable};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use hmac::{Hmac, Mac};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use sha2::Sha256;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tips_core::{AcceptedBundle, MeterBundleResponse};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::{Semaphore, broadcast, mpsc, watch};
+use tokio::task::JoinSet;
+use tokio::time::Duration;
+use tracing::{error, info, warn};
+use url::{Position, Url};
+
+use crate::Config;
+use crate::metrics::{
+    record_builder_broadcast_without_receivers, record_builder_delivery, record_builder_lagged,
+};
+
+/// Limits of the delivery pipeline to each builder
+#[derive(Debug, Clone)]
+pub struct BuilderDeliveryConfig {
+    /// Maximum number of messages waiting to be delivered, newer messages are dropped when full
+    pub queue_size: usize,
+    /// Maximum number of in-flight requests
+    pub max_concurrent_requests: usize,
+    /// Number of times a failed request is retried before the message is dropped
+    pub max_retries: usize,
+    /// Time a single request may take, including connecting
+    pub request_timeout: Duration,
+    /// Time connecting to the builder may take
+    pub connect_timeout: Duration,
+}
+
+impl From<&Config> for BuilderDeliveryConfig {
+    fn from(config: &Config) -> Self {
+        Self {
+            queue_size: config.builder_queue_size,
+            max_concurrent_requests: config.builder_max_concurrent_requests,
+            max_retries: config.builder_max_retries,
+            request_timeout: Duration::from_millis(config.builder_request_timeout_ms),
+            connect_timeout: Duration::from_millis(config.builder_connect_timeout_ms),
+        }
+    }
+}
+
+/// How ingress authenticates to a builder
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum BuilderAuth {
+    /// Engine API style JWT, signed with the hex encoded 32 byte secret and renewed on every
+    /// request
+    Jwt { secret: String },
+    /// Static bearer token
+    Bearer { token: String },
+}
+
+impl fmt::Debug for BuilderAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.kind())
+    }
+}
+
+impl BuilderAuth {
+    pub const fn kind(&self) -> &'static str {
+        match self {
+            Self::Jwt { .. } => "jwt",
+            Self::Bearer { .. } => "bearer",
+        }
+    }
+
+    /// The bearer token to send with a request
+    fn token(&self) -> anyhow::Result<String> {
+        match self {
+            Self::Jwt { secret } => {
+                let iat = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+                encode_jwt(&hex::decode(secret.trim())?, iat)
+            }
+            Self::Bearer { token } => Ok(token.clone()),
+        }
+    }
+}
+
+/// Encodes an HS256 JWT with an `iat` claim, as expected by the Engine API
+fn encode_jwt(secret: &[u8], iat: u64) -> anyhow::Result<String> {
+    if secret.len() != 32 {
+        anyhow::bail!("JWT secret must be 32 bytes, got {}", secret.len());
+    }
+    let header = URL_SAFE_NO_PAD.encode(json!({ "alg": "HS256", "typ": "JWT" }).to_string());
+    let claims = URL_SAFE_NO_PAD.encode(json!({ "iat": iat }).to_string());
+    let message = format!("{header}.{claims}");
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts keys of any size");
+    mac.update(message.as_bytes());
+    let signature = URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes());
+    Ok(format!("{message}.{signature}"))
+}
+
+/// A builder ingress delivers metering information and backrun bundles to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuilderEndpoint {
+    pub url: Url,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<BuilderAuth>,
+}
+
+impl BuilderEndpoint {
+    /// Host and port of the builder, the URL may carry credentials
+    pub fn label(&self) -> String {
+        self.url[Position::BeforeHost..Position::AfterPort].to_string()
+    }
+}
+
+#[derive(Deserialize)]
+struct JsonRpcResponse {
+    #[serde(default)]
+    result: Option<Value>,
+    #[serde(default)]
+    error: Option<Value>,
+}
+
+/// JSON-RPC client of a builder that authenticates every request
+#[derive(Debug, Clone)]
+pub struct BuilderClient {
+    http: reqwest::Client,
+    endpoint: BuilderEndpoint,
+}
+
+impl BuilderClient {
+    pub fn new(endpoint: BuilderEndpoint, config: &BuilderDeliveryConfig) -> Self {
+        let http = reqwest::Client::builder()
+            .timeout(config.request_timeout)
+            .connect_timeout(config.connect_timeout)
+            .build()
+            .expect("TLS backend can be initialized");
+        Self { http, endpoint }
+    }
+
+    pub async fn request<P, R>(&self, method: &str, params: P) -> anyhow::Result<R>
+    where
+        P: Serialize,
+        R: DeserializeOwned,
+    {
+        let body = json!({
+            "jsonrpc": "2.0",
+            "id": 0,
+            "method": method,
+            "params": params,
+        });
+        let mut request = self
+            .http
+            .post(self.endpoint.url.clone())
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(serde_json::to_vec(&body)?);
+        if let Some(auth) = &self.endpoint.auth {
+            request = request.bearer_auth(auth.token()?);
+        }
+
+        let response = request.send().await?;
+        let status = response.status();
+        let body = response.bytes().await?;
+        let response: JsonRpcResponse = serde_json::from_slice(&body)
+            .map_err(|e| anyhow::anyhow!("Invalid response from builder ({status}): {e}"))?;
+        if let Some(error) = response.error {
+            anyhow::bail!("Builder returned an error: {error}");
+        }
+        Ok(serde_json::from_value(
+            response.result.unwrap_or(Value::Null),
+        )?)
+    }
+}
+
+/// A message ingress delivers to the builders
+#[async_trait]
+pub trait BuilderMessage: Clone + Send + Sync + 'static {
+    /// Label of the message kind in logs and metrics
+    const KIND: &'static str;
+
+    /// Messages that can't be delivered are skipped instead of queued
+    fn is_deliverable(&self) -> bool {
+        true
+    }
+
+    async fn deliver(&self, builder: &BuilderClient) -> anyhow::Result<()>;
+}
+
+#[async_trait]
+impl BuilderMessage for MeterBundleResponse {
+    const KIND: &'static str = "metering";
+
+    fn is_deliverable(&self) -> bool {
+        if self.results.is_empty() {
+            warn!(message = "received metering information with no transactions", hash = %self.bundle_hash);
+            return false;
+        }
+        true
+    }
+
+    async fn deliver(&self, builder: &BuilderClient) -> anyhow::Result<()> {
+        let tx_hash: TxHash = self.results[0].tx_hash;
+        builder
+            .request::<_, ()>("base_setMeteringInformation", (tx_hash, self))
+            .await
+    }
+}
+
+#[async_trait]
+impl BuilderMessage for AcceptedBundle {
+    const KIND: &'static str = "backrun";
+
+    async fn deliver(&self, builder: &BuilderClient) -> anyhow::Result<()> {
+        builder
+            .request::<_, ()>("base_sendBackrunBundle", (self,))
+            .await
+    }
+}
+
+/// Broadcasts a message to the delivery pipelines of all registered builders. With no builder
+/// registered, e.g. before the builders file is loaded, there is nobody to deliver to and the
+/// message is dropped.
+pub fn broadcast_to_builders<M: BuilderMessage>(tx: &broadcast::Sender<M>, message: M) {
+    if tx.send(message).is_err() {
+        record_builder_broadcast_without_receivers(M::KIND);
+    }
+}
+
+/// Delivers the messages broadcast to `rx` to a single builder, with its tasks spawned on
+/// `tasks`. Messages are buffered in a bounded queue and sent with limited concurrency, failed
+/// requests are retried with backoff. A builder that falls behind the broadcast skips the messages
+/// it missed and carries on. Delivery stops once `shutdown` is signalled or its sender is dropped,
+/// aborting the requests still in flight and their retries.
+pub fn spawn_builder_delivery<M: BuilderMessage>(
+    rx: broadcast::Receiver<M>,
+    builder: BuilderClient,
+    config: &BuilderDeliveryConfig,
+    shutdown: watch::Receiver<()>,
+    tasks: &mut JoinSet<()>,
+) {
+    let builder_label = builder.endpoint.label();
+    let (queue_tx, queue_rx) = mpsc::channel(config.queue_size);
+    tasks.spawn(enqueue_messages(
+        rx,
+        queue_tx,
+        builder_label.clone(),
+        shutdown.clone(),
+    ));
+    tasks.spawn(deliver_messages(
+        queue_rx,
+        builder,
+        builder_label,
+        config.clone(),
+        shutdown,
+    ));
+}
+
+async fn enqueue_messages<M: BuilderMessage>(
+    mut rx: broadcast::Receiver<M>,
+    queue_tx: mpsc::Sender<M>,
+    builder_label: String,
+    mut shutdown: watch::Receiver<()>,
+) {
+    loop {
+        let received = tokio::select! {
+            _ = shutdown.changed() => return,
+            received = rx.recv() => received,
+        };
+        match received {
+            Ok(message) if message.is_deliverable() => match queue_tx.try_send(message) {
+                Ok(()) => {}
+                Err(TrySendError::Full(_)) => {
+                    warn!(message = "Builder delivery queue full, dropping message", builder = %builder_label, kind = M::KIND);
+                    record_builder_delivery(&builder_label, M::KIND, false);
+                }
+                Err(TrySendError::Closed(_)) => return,
+            },
+            Ok(_) => {}
+            Err(RecvError::Lagged(skipped)) => {
+                warn!(message = "Builder delivery lagged behind, skipping messages", builder = %builder_label, kind = M::KIND, skipped);
+                record_builder_lagged(&builder_label, M::KIND, skipped);
+            }
+            Err(RecvError::Closed) => return,
+        }
+    }
+}
+
+async fn deliver_messages<M: BuilderMessage>(
+    mut queue_rx: mpsc::Receiver<M>,
+    builder: BuilderClient,
+    builder_label: String,
+    config: BuilderDeliveryConfig,
+    mut shutdown: watch::Receiver<()>,
+) {
+    let permits = Arc::new(Semaphore::new(config.max_concurrent_requests));
+    let mut in_flight = JoinSet::new();
+    loop {
+        let message = tokio::select! {
+            _ = shutdown.changed() => break,
+            Some(_) = in_flight.join_next() => continue,
+            message = queue_rx.recv() => match message {
+                Some(message) => message,
+                None => break,
+            },
+        };
+        let permit = tokio::select! {
+            _ = shutdown.changed() => break,
+            permit = permits.clone().acquire_owned() => match permit {
+                Ok(permit) => permit,
+                Err(_) => break,
+            },
+        };
+        let builder = builder.clone();
+        let builder_label = builder_label.clone();
+        in_flight.spawn(async move {
+            let result = (|| message.deliver(&builder))
+                .retry(
+                    ExponentialBuilder::default()
+                        .with_min_delay(Duration::from_millis(100))
+                        .with_max_delay(Duration::from_secs(5))
+                        .with_max_times(config.max_retries),
+                )
+                .notify(|err: &anyhow::Error, dur: Duration| {
+                    info!(message = "Retrying builder delivery", builder = %builder_label, kind = M::KIND, error = %err, retry_in = ?dur);
+                })
+                .await;
+            match result {
+                Ok(()) => record_builder_delivery(&builder_label, M::KIND, true),
+                Err(e) => {
+                    error!(message = "Failed to deliver to builder, dropping message", builder = %builder_label, kind = M::KIND, error = %e);
+                    record_builder_delivery(&builder_label, M::KIND, false);
+                }
+            }
+            drop(permit);
+        });
+    }
+    in_flight.shutdown().await;
+    info!(message = "Stopped builder delivery", builder = %builder_label, kind = M::KIND);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_signer_local::PrivateKeySigner;
+    use serde_json::json;
+    use tips_core::test_utils::{create_test_bundle, create_transaction};
+    use wiremock::{
+        Mock, MockServer, ResponseTemplate,
+        matchers::{header, method},
+    };
+
+    fn delivery_config() -> BuilderDeliveryConfig {
+        BuilderDeliveryConfig {
+            queue_size: 10,
+            max_concurrent_requests: 2,
+            max_retries: 3,
+            request_timeout: Duration::from_millis(500),
+            connect_timeout: Duration::from_millis(500),
+        }
+    }
+
+    fn backrun_bundle() -> AcceptedBundle {
+        let alice = PrivateKeySigner::random();
+        create_test_bundle(
+            vec![create_transaction(alice.clone(), 1, alice.address())],
+            None,
+            None,
+            None,
+        )
+    }
+
+    fn builder_client(mock_server: &MockServer, auth: Option<BuilderAuth>) -> BuilderClient {
+        BuilderClient::new(
+            BuilderEndpoint {
+                url: mock_server.uri().parse().unwrap(),
+                auth,
+            },
+            &delivery_config(),
+        )
+    }
+
+    fn ok() -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_json(json!({
+            "jsonrpc": "2.0",
+            "id": 0,
+            "result": null
+        }))
+    }
+
+    async fn wait_for_requests(mock_server: &MockServer, count: usize) {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while mock_server.received_requests().await.unwrap().len() < count {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("builder didn't receive the expected requests");
+    }
+
+    #[tokio::test]
+    async fn test_failed_delivery_is_retried() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ok())
+            .mount(&mock_server)
+            .await;
+
+        let (tx, rx) = broadcast::channel(10);
+        let (_shutdown_tx, shutdown_rx) = watch::channel(());
+        let mut tasks = JoinSet::new();
+        spawn_builder_delivery(
+            rx,
+            builder_client(&mock_server, None),
+            &delivery_config(),
+            shutdown_rx,
+            &mut tasks,
+        );
+
+        tx.send(backrun_bundle()).unwrap();
+        wait_for_requests(&mock_server, 2).await;
+    }
+
+    #[tokio::test]
+    async fn test_delivery_recovers_from_lag() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ok())
+            .mount(&mock_server)
+            .await;
+
+        let (tx, rx) = broadcast::channel(1);
+        // overflow the channel before the delivery worker gets to read it
+        tx.send(backrun_bundle()).unwrap();
+        tx.send(backrun_bundle()).unwrap();
+        tx.send(backrun_bundle()).unwrap();
+
+        let (_shutdown_tx, shutdown_rx) = watch::channel(());
+        let mut tasks = JoinSet::new();
+        spawn_builder_delivery(
+            rx,
+            builder_client(&mock_server, None),
+            &delivery_config(),
+            shutdown_rx,
+            &mut tasks,
+        );
+        wait_for_requests(&mock_server, 1).await;
+
+        tx.send(backrun_bundle()).unwrap();
+        wait_for_requests(&mock_server, 2).await;
+    }
+
+    #[tokio::test]
+    async fn test_delivery_stops_on_shutdown() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ok())
+            .mount(&mock_server)
+            .await;
+
+        let (tx, rx) = broadcast::channel(10);
+        let (shutdown_tx, shutdown_rx) = watch::channel(());
+        let mut tasks = JoinSet::new();
+        spawn_builder_delivery(
+            rx,
+            builder_client(&mock_server, None),
+            &delivery_config(),
+            shutdown_rx,
+            &mut tasks,
+        );
+        tx.send(backrun_bundle()).unwrap();
+        wait_for_requests(&mock_server, 1).await;
+
+        drop(shutdown_tx);
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while tx.receiver_count() > 0 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("builder delivery didn't stop");
+    }
+
+    #[tokio::test]
+    async fn test_timed_out_request_releases_its_permit() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ok().set_delay(Duration::from_secs(30)))
+            .up_to_n_times(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ok())
+            .mount(&mock_server)
+            .await;
+
+        let config = BuilderDeliveryConfig {
+            max_concurrent_requests: 1,
+            max_retries: 0,
+            request_timeout: Duration::from_millis(100),
+            ..delivery_config()
+        };
+        let (tx, rx) = broadcast::channel(10);
+        let (_shutdown_tx, shutdown_rx) = watch::channel(());
+        let mut tasks = JoinSet::new();
+        spawn_builder_delivery(
+            rx,
+            builder_client(&mock_server, None),
+            &config,
+            shutdown_rx,
+            &mut tasks,
+        );
+
+        // the second message is sent once the first request times out, long before the builder
+        // would have answered it
+        tx.send(backrun_bundle()).unwrap();
+        tx.send(backrun_bundle()).unwrap();
+        wait_for_requests(&mock_server, 2).await;
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_aborts_retries() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(503))
+            .mount(&mock_server)
+            .await;
+
+        let config = BuilderDeliveryConfig {
+            max_retries: 10,
+            ..delivery_config()
+        };
+        let (tx, rx) = broadcast::channel(10);
+        let (shutdown_tx, shutdown_rx) = watch::channel(());
+        let mut tasks = JoinSet::new();
+        spawn_builder_delivery(
+            rx,
+            builder_client(&mock_server, None),
+            &config,
+            shutdown_rx,
+            &mut tasks,
+        );
+        tx.send(backrun_bundle()).unwrap();
+        wait_for_requests(&mock_server, 1).await;
+
+        drop(shutdown_tx);
+        tokio::time::timeout(Duration::from_secs(5), tasks.join_all())
+            .await
+            .expect("builder delivery didn't stop");
+        let requests = mock_server.received_requests().await.unwrap().len();
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert_eq!(
+            mock_server.received_requests().await.unwrap().len(),
+            requests
+        );
+    }
+
+    #[tokio::test]
+    async fn test_bearer_auth() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(header("authorization", "Bearer secret-token"))
+            .respond_with(ok())
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let builder = builder_client(
+            &mock_server,
+            Some(BuilderAuth::Bearer {
+                token: "secret-token".to_string(),
+            }),
+        );
+        backrun_bundle().deliver(&builder).await.unwrap();
+
+        let unauthenticated = builder_client(&mock_server, None);
+        assert!(backrun_bundle().deliver(&unauthenticated).await.is_err());
+    }
+
+    #[test]
+    fn test_jwt_is_signed_with_secret() {
+        let secret = [7u8; 32];
+        let token = encode_jwt(&secret, 1_700_000_000).unwrap();
+        let parts: Vec<&str> = token.split('.').collect();
+        assert_eq!(parts.len(), 3);
+
+        let claims: Value =
+            serde_json::from_slice(&URL_SAFE_NO_PAD.decode(parts[1]).unwrap()).unwrap();
+        assert_eq!(claims, json!({ "iat": 1_700_000_000 }));
+
+        let mut mac = Hmac::<Sha256>::new_from_slice(&secret).unwrap();
+        mac.update(format!("{}.{}", parts[0], parts[1]).as_bytes());
+        mac.verify_slice(&URL_SAFE_NO_PAD.decode(parts[2]).unwrap())
+            .unwrap();
+
+        assert!(encode_jwt(&[7u8; 16], 0).is_err());
+    }
+
+    #[test]
+    fn test_builder_endpoint_from_json() {
+        let endpoints: Vec<BuilderEndpoint> = serde_json::from_value(json!([
+            { "url": "http://builder-a:8545" },
+            { "url": "http://builder-b:8545", "auth": { "type": "jwt", "secret": "0x00" } },
+            { "url": "http://builder-c:8545", "auth": { "type": "bearer", "token": "abc" } },
+        ]))
+        .unwrap();
+        assert_eq!(endpoints[0].auth, None);
+        assert_eq!(endpoints[1].auth.as_ref().unwrap().kind(), "jwt");
+        assert_eq!(endpoints[2].label(), "builder-c:8545");
+    }
+
+    #[test]
+    fn test_metering_without_transactions_is_not_deliverable() {
+        let response = MeterBundleResponse::default();
+        assert!(!response.is_deliverable());
+    }
+}
@@ -0,0 +1,297 @@
+use alloy_primitives::TxHash;
+use jsonrpsee::types::error::INVALID_PARAMS_CODE;
+use jsonrpsee::types::{ErrorObject, ErrorObjectOwned};
+use serde_json::{Value, json};
+use std::fmt;
+
+use crate::rate_limit::RateLimitKey;
+use crate::tracker::CancelBundleError;
+use crate::validation::TxValidationError;
+
+// Client errors, in the -38000 range. The request is rejected and retrying it unchanged will fail
+// again.
+pub const OVERSIZED_DATA_CODE: i32 = -38001;
+pub const INTRINSIC_GAS_TOO_LOW_CODE: i32 = -38002;
+pub const INITCODE_TOO_LARGE_CODE: i32 = -38003;
+pub const TIP_ABOVE_FEE_CAP_CODE: i32 = -38004;
+pub const GAS_LIMIT_EXCEEDS_BLOCK_GAS_LIMIT_CODE: i32 = -38005;
+pub const WRONG_CHAIN_ID_CODE: i32 = -38006;
+pub const UNPROTECTED_TRANSACTION_CODE: i32 = -38007;
+pub const NONCE_TOO_LOW_CODE: i32 = -38008;
+pub const INSUFFICIENT_FUNDS_CODE: i32 = -38009;
+pub const DEPOSIT_TRANSACTION_CODE: i32 = -38010;
+pub const TX_TYPE_NOT_ALLOWED_CODE: i32 = -38011;
+pub const EXECUTION_TIME_EXCEEDED_CODE: i32 = -38020;
+pub const PROTECTED_TRANSACTION_REVERTED_CODE: i32 = -38021;
+pub const REPLACEMENT_UNDERPRICED_CODE: i32 = -38022;
+pub const INVALID_BUNDLE_CODE: i32 = -38023;
+pub const INVALID_UUID_CODE: i32 = -38030;
+pub const UNKNOWN_BUNDLE_CODE: i32 = -38031;
+pub const BUNDLE_ALREADY_INCLUDED_CODE: i32 = -38032;
+pub const NOT_BUNDLE_OWNER_CODE: i32 = -38033;
+pub const BACKRUN_DISABLED_CODE: i32 = -38040;
+pub const BACKRUN_NOT_ALLOWED_CODE: i32 = -38041;
+pub const FIREHOSE_UNAUTHORIZED_CODE: i32 = -38050;
+
+// Rate limited requests can be retried once the `retryAfterMs` of the error data has passed.
+pub const RATE_LIMITED_CODE: i32 = -38060;
+
+// Server errors, in the JSON-RPC -32000 to -32099 range. Ingress or one of its dependencies is
+// unavailable and the request can be retried.
+pub const QUEUE_UNAVAILABLE_CODE: i32 = -32001;
+pub const METERING_TIMEOUT_CODE: i32 = -32002;
+pub const METERING_UNAVAILABLE_CODE: i32 = -32003;
+pub const AUDIT_STORE_UNAVAILABLE_CODE: i32 = -32005;
+pub const DRAINING_CODE: i32 = -32006;
+
+/// Errors returned by the ingress RPC. Each variant maps to a stable JSON-RPC error code, and
+/// carries structured `data` where the client can act on it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IngressError {
+    /// The request parameters are malformed
+    InvalidParams(String),
+    /// A transaction failed validation before it was metered
+    InvalidTransaction(TxValidationError),
+    /// An `eth_cancelBundle` request was rejected
+    CancelBundle(CancelBundleError),
+    /// Executing the bundle takes longer than the block time
+    ExecutionTimeExceeded {
+        execution_time_us: u64,
+        limit_us: u64,
+    },
+    /// A transaction that is not allowed to revert reverted while metering the bundle
+    ProtectedTransactionReverted(TxHash),
+    /// The simulation node rejected the bundle with a JSON-RPC error while metering it
+    InvalidBundle(String),
+    /// A transaction replacing a live one with the same sender and nonce doesn't pay enough more
+    ReplacementUnderpriced {
+        tx_hash: TxHash,
+        min_bump_percent: u64,
+    },
+    /// Backrun bundle submission is disabled
+    BackrunDisabled,
+    /// The tier of the searcher doesn't allow backrun bundles
+    BackrunNotAllowed { tier: String },
+    /// The client is not authorized to subscribe to the events of all bundles
+    FirehoseUnauthorized,
+    /// The sender, client IP or API key sent more requests than its rate limit allows
+    RateLimited {
+        limit: RateLimitKey,
+        retry_after_ms: u64,
+    },
+    /// Publishing to the queue failed
+    QueueUnavailable(String),
+    /// Metering the bundle didn't complete in time
+    MeteringTimeout { timeout_ms: u64 },
+    /// The metering request failed
+    MeteringUnavailable(String),
+    /// Reading from the audit store failed
+    AuditStoreUnavailable(String),
+    /// Ingress is draining and doesn't accept new submissions
+    Draining,
+}
+
+impl fmt::Display for IngressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidParams(message) => write!(f, "{message}"),
+            Self::InvalidTransaction(err) => write!(f, "{err}"),
+            Self::CancelBundle(err) => write!(f, "{err}"),
+            Self::ExecutionTimeExceeded {
+                execution_time_us,
+                limit_us,
+            } => write!(
+                f,
+                "Bundle simulation took too long: {execution_time_us}us > {limit_us}us"
+            ),
+            Self::ProtectedTransactionReverted(tx_hash) => {
+                write!(f, "Protected transaction reverted in simulation: {tx_hash}")
+            }
+            Self::InvalidBundle(err) => write!(f, "Invalid bundle: {err}"),
+            Self::ReplacementUnderpriced {
+                tx_hash,
+                min_bump_percent,
+            } => write!(
+                f,
+                "Replacement transaction underpriced: fees must be at least {min_bump_percent}% higher than transaction {tx_hash}"
+            ),
+            Self::BackrunDisabled => write!(f, "Backrun bundle submission is disabled"),
+            Self::BackrunNotAllowed { tier } => {
+                write!(f, "Backrun bundles are not allowed for tier {tier}")
+            }
+            Self::FirehoseUnauthorized => write!(f, "Not authorized to subscribe to all bundles"),
+            Self::RateLimited {
+                limit,
+                retry_after_ms,
+            } => write!(
+                f,
+                "Rate limit exceeded for {limit}, retry after {retry_after_ms}ms"
+            ),
+            Self::QueueUnavailable(what) => write!(f, "Failed to queue {what}"),
+            Self::MeteringTimeout { timeout_ms } => {
+                write!(f, "Timeout on requesting metering after {timeout_ms}ms")
+            }
+            Self::MeteringUnavailable(err) => write!(f, "Failed to request metering: {err}"),
+            Self::AuditStoreUnavailable(err) => write!(f, "Failed to read audit store: {err}"),
+            Self::Draining => write!(f, "Ingress is draining, not accepting new submissions"),
+        }
+    }
+}
+
+impl std::error::Error for IngressError {}
+
+impl IngressError {
+    pub const fn code(&self) -> i32 {
+        match self {
+            Self::InvalidParams(_) => INVALID_PARAMS_CODE,
+            Self::InvalidTransaction(err) => err.code(),
+            Self::CancelBundle(err) => err.code(),
+            Self::ExecutionTimeExceeded { .. } => EXECUTION_TIME_EXCEEDED_CODE,
+            Self::ProtectedTransactionReverted(_) => PROTECTED_TRANSACTION_REVERTED_CODE,
+            Self::InvalidBundle(_) => INVALID_BUNDLE_CODE,
+            Self::ReplacementUnderpriced { .. } => REPLACEMENT_UNDERPRICED_CODE,
+            Self::BackrunDisabled => BACKRUN_DISABLED_CODE,
+            Self::BackrunNotAllowed { .. } => BACKRUN_NOT_ALLOWED_CODE,
+            Self::FirehoseUnauthorized => FIREHOSE_UNAUTHORIZED_CODE,
+            Self::RateLimited { .. } => RATE_LIMITED_CODE,
+            Self::QueueUnavailable(_) => QUEUE_UNAVAILABLE_CODE,
+            Self::MeteringTimeout { .. } => METERING_TIMEOUT_CODE,
+            Self::MeteringUnavailable(_) => METERING_UNAVAILABLE_CODE,
+            Self::AuditStoreUnavailable(_) => AUDIT_STORE_UNAVAILABLE_CODE,
+            Self::Draining => DRAINING_CODE,
+        }
+    }
+
+    /// Server errors are caused by ingress or its dependencies, the request can be retried
+    pub const fn is_server_error(&self) -> bool {
+        matches!(self.code(), -32099..=-32000)
+    }
+
+    /// The structured `data` of the JSON-RPC error
+    pub fn data(&self) -> Option<Value> {
+        match self {
+            Self::InvalidTransaction(err) => err.data(),
+            Self::CancelBundle(err) => err.data(),
+            Self::ExecutionTimeExceeded {
+                execution_time_us,
+                limit_us,
+            } => Some(json!({
+                "executionTimeUs": execution_time_us,
+                "limitUs": limit_us,
+            })),
+            Self::ProtectedTransactionReverted(tx_hash) => Some(json!({ "txHash": tx_hash })),
+            Self::ReplacementUnderpriced {
+                tx_hash,
+                min_bump_percent,
+            } => Some(json!({
+                "txHash": tx_hash,
+                "minBumpPercent": min_bump_percent,
+            })),
+            Self::RateLimited {
+                limit,
+                retry_after_ms,
+            } => Some(json!({
+                "limit": limit.to_string(),
+                "retryAfterMs": retry_after_ms,
+            })),
+            Self::BackrunNotAllowed { tier } => Some(json!({ "tier": tier })),
+            Self::MeteringTimeout { timeout_ms } => Some(json!({ "timeoutMs": timeout_ms })),
+            Self::InvalidParams(_)
+            | Self::InvalidBundle(_)
+            | Self::BackrunDisabled
+            | Self::FirehoseUnauthorized
+            | Self::QueueUnavailable(_)
+            | Self::MeteringUnavailable(_)
+            | Self::AuditStoreUnavailable(_)
+            | Self::Draining => None,
+        }
+    }
+}
+
+impl From<TxValidationError> for IngressError {
+    fn from(err: TxValidationError) -> Self {
+        Self::InvalidTransaction(err)
+    }
+}
+
+impl From<CancelBundleError> for IngressError {
+    fn from(err: CancelBundleError) -> Self {
+        Self::CancelBundle(err)
+    }
+}
+
+impl From<IngressError> for ErrorObjectOwned {
+    fn from(err: IngressError) -> Self {
+        ErrorObject::owned(err.code(), err.to_string(), err.data())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::U256;
+
+    #[test]
+    fn test_rpc_error_has_code_and_data() {
+        let err: ErrorObjectOwned = IngressError::ExecutionTimeExceeded {
+            execution_time_us: 2_500_000,
+            limit_us: 2_000_000,
+        }
+        .into();
+        assert_eq!(err.code(), EXECUTION_TIME_EXCEEDED_CODE);
+        assert_eq!(
+            err.message(),
+            "Bundle simulation took too long: 2500000us > 2000000us"
+        );
+        let data: Value = serde_json::from_str(err.data().unwrap().get()).unwrap();
+        assert_eq!(
+            data,
+            json!({ "executionTimeUs": 2_500_000, "limitUs": 2_000_000 })
+        );
+
+        let err: ErrorObjectOwned = IngressError::InvalidParams("bad request".into()).into();
+        assert_eq!(err.code(), INVALID_PARAMS_CODE);
+        assert!(err.data().is_none());
+    }
+
+    #[test]
+    fn test_wrapped_errors_keep_their_codes() {
+        let err = IngressError::from(TxValidationError::InsufficientFunds {
+            cost: U256::from(2),
+            balance: U256::from(1),
+        });
+        assert_eq!(err.code(), INSUFFICIENT_FUNDS_CODE);
+        assert!(err.data().is_some());
+
+        let err = IngressError::from(CancelBundleError::InvalidUuid("nope".into()));
+        assert_eq!(err.code(), INVALID_UUID_CODE);
+    }
+
+    #[test]
+    fn test_server_errors() {
+        assert!(IngressError::QueueUnavailable("bundle".into()).is_server_error());
+        assert!(IngressError::MeteringTimeout { timeout_ms: 100 }.is_server_error());
+        assert!(IngressError::MeteringUnavailable("connection refused".into()).is_server_error());
+        assert!(IngressError::AuditStoreUnavailable("access denied".into()).is_server_error());
+        assert!(IngressError::Draining.is_server_error());
+
+        assert!(!IngressError::BackrunDisabled.is_server_error());
+        assert!(
+            !IngressError::BackrunNotAllowed {
+                tier: "default".into()
+            }
+            .is_server_error()
+        );
+        assert!(!IngressError::FirehoseUnauthorized.is_server_error());
+        assert!(
+            !IngressError::RateLimited {
+                limit: RateLimitKey::Sender,
+                retry_after_ms: 100,
+            }
+            .is_server_error()
+        );
+        assert!(!IngressError::InvalidParams("bad request".into()).is_server_error());
+        assert!(!IngressError::ProtectedTransactionReverted(TxHash::ZERO).is_server_error());
+        assert!(!IngressError::InvalidBundle("nonce too low".into()).is_server_error());
+    }
+}
@@ -1,31 +1,365 @@
-use axum::{Router, http::StatusCode, response::IntoResponse, routing::get};
-use std::net::SocketAddr;
-use tracing::info;
+use alloy_primitives::U64;
+use alloy_provider::{Provider, RootProvider};
+use async_trait::async_trait;
+use op_alloy_network::Optimism;
+use rdkafka::producer::{FutureProducer, Producer};
+use serde::Deserialize;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tips_core::health::{CheckStatus, HealthCheck};
+use tokio::task::{AbortHandle, JoinSet};
 
-/// Health check handler that always returns 200 OK
-async fn health() -> impl IntoResponse {
-    StatusCode::OK
+use crate::admin::RuntimeControls;
+use crate::builder::BuilderClient;
+use crate::builder_registry::BuilderRegistry;
+
+/// Checks that the Kafka brokers are reachable and serve metadata for the producer's topic
+pub struct KafkaProducerCheck {
+    name: String,
+    producer: FutureProducer,
+    topic: String,
+    timeout: Duration,
+}
+
+impl KafkaProducerCheck {
+    pub fn new(
+        name: impl Into<String>,
+        producer: FutureProducer,
+        topic: String,
+        timeout: Duration,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            producer,
+            topic,
+            timeout,
+        }
+    }
+}
+
+#[async_trait]
+impl HealthCheck for KafkaProducerCheck {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn check(&self) -> CheckStatus {
+        let producer = self.producer.clone();
+        let topic = self.topic.clone();
+        let timeout = self.timeout;
+        // fetching metadata blocks until the brokers respond or the timeout expires
+        let metadata = tokio::task::spawn_blocking(move || {
+            producer.client().fetch_metadata(Some(&topic), timeout)
+        })
+        .await;
+
+        match metadata {
+            Ok(Ok(metadata)) => match metadata.topics().iter().find_map(|topic| topic.error()) {
+                Some(error) => CheckStatus::unhealthy(format!("topic {}: {error:?}", self.topic)),
+                None => CheckStatus::healthy_with(format!("{} brokers", metadata.brokers().len())),
+            },
+            Ok(Err(e)) => CheckStatus::unhealthy(e.to_string()),
+            Err(e) => CheckStatus::unhealthy(e.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct BlockHead {
+    number: U64,
+    timestamp: U64,
+}
+
+/// Checks that a simulation node is reachable and its latest block is recent
+pub struct SimulationHeadCheck {
+    name: String,
+    provider: RootProvider<Optimism>,
+    max_head_age: Duration,
+}
+
+impl SimulationHeadCheck {
+    pub fn new(
+        name: impl Into<String>,
+        provider: RootProvider<Optimism>,
+        max_head_age: Duration,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            provider,
+            max_head_age,
+        }
+    }
+}
+
+#[async_trait]
+impl HealthCheck for SimulationHeadCheck {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn check(&self) -> CheckStatus {
+        let head: Option<BlockHead> = match self
+            .provider
+            .client()
+            .request("eth_getBlockByNumber", ("latest", false))
+            .await
+        {
+            Ok(head) => head,
+            Err(e) => return CheckStatus::unhealthy(e.to_string()),
+        };
+        match head {
+            Some(head) => head_status(
+                head.number.to(),
+                head.timestamp.to(),
+                SystemTime::now(),
+                self.max_head_age,
+            ),
+            None => CheckStatus::unhealthy("latest block not found"),
+        }
+    }
+}
+
+fn head_status(number: u64, timestamp: u64, now: SystemTime, max_age: Duration) -> CheckStatus {
+    let now = now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let age = now.saturating_sub(timestamp);
+    if age > max_age.as_secs() {
+        CheckStatus::unhealthy(format!("head block {number} is {age}s old"))
+    } else {
+        CheckStatus::healthy_with(format!("head block {number} is {age}s old"))
+    }
+}
+
+/// Checks that at least one of the registered builders is reachable. Ready when no builders are
+/// registered, since delivering to builders is optional.
+pub struct BuilderCheck {
+    registry: Arc<BuilderRegistry>,
+}
+
+impl BuilderCheck {
+    pub fn new(registry: Arc<BuilderRegistry>) -> Self {
+        Self { registry }
+    }
+}
+
+#[async_trait]
+impl HealthCheck for BuilderCheck {
+    fn name(&self) -> &str {
+        "builders"
+    }
+
+    async fn check(&self) -> CheckStatus {
+        let endpoints = self.registry.endpoints();
+        if endpoints.is_empty() {
+            return CheckStatus::healthy_with("no builders registered");
+        }
+
+        let total = endpoints.len();
+        let mut pings = JoinSet::new();
+        for endpoint in endpoints {
+            let label = endpoint.label();
+            let builder = BuilderClient::new(endpoint, self.registry.delivery_config());
+            pings.spawn(async move {
+                let result = builder.request::<_, U64>("eth_blockNumber", [(); 0]).await;
+                (label, result)
+            });
+        }
+
+        let mut unreachable = Vec::new();
+        while let Some(joined) = pings.join_next().await {
+            match joined {
+                Ok((_, Ok(_))) => {}
+                Ok((label, Err(e))) => unreachable.push(format!("{label}: {e}")),
+                Err(e) => unreachable.push(e.to_string()),
+            }
+        }
+        unreachable.sort();
+
+        let detail = if unreachable.is_empty() {
+            format!("{total}/{total} builders reachable")
+        } else {
+            format!(
+                "{}/{total} builders reachable, unreachable: {}",
+                total - unreachable.len(),
+                unreachable.join(", ")
+            )
+        };
+        if unreachable.len() < total {
+            CheckStatus::healthy_with(detail)
+        } else {
+            CheckStatus::unhealthy(detail)
+        }
+    }
+}
+
+/// Checks that the mempool engine task consuming user operations is still running
+pub struct MempoolEngineCheck {
+    task: AbortHandle,
+}
+
+impl MempoolEngineCheck {
+    pub fn new(task: AbortHandle) -> Self {
+        Self { task }
+    }
 }
 
-/// Bind and start the health check server on the specified address.
-/// Returns a handle that can be awaited to run the server.
-pub async fn bind_health_server(
-    addr: SocketAddr,
-) -> anyhow::Result<(SocketAddr, tokio::task::JoinHandle<anyhow::Result<()>>)> {
-    let app = Router::new().route("/health", get(health));
+#[async_trait]
+impl HealthCheck for MempoolEngineCheck {
+    fn name(&self) -> &str {
+        "mempool_engine"
+    }
+
+    async fn check(&self) -> CheckStatus {
+        if self.task.is_finished() {
+            CheckStatus::unhealthy("mempool engine task exited")
+        } else {
+            CheckStatus::healthy()
+        }
+    }
+}
+
+/// Not ready while draining, so load balancers stop sending new submissions
+pub struct DrainingCheck {
+    controls: Arc<RuntimeControls>,
+}
+
+impl DrainingCheck {
+    pub fn new(controls: Arc<RuntimeControls>) -> Self {
+        Self { controls }
+    }
+}
+
+#[async_trait]
+impl HealthCheck for DrainingCheck {
+    fn name(&self) -> &str {
+        "draining"
+    }
+
+    async fn check(&self) -> CheckStatus {
+        if self.controls.is_draining() {
+            CheckStatus::unhealthy(format!(
+                "draining, {} submissions in flight",
+                self.controls.in_flight()
+            ))
+        } else {
+            CheckStatus::healthy()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::{BuilderDeliveryConfig, BuilderEndpoint};
+    use serde_json::{Value, json};
+    use tokio::sync::broadcast;
+    use wiremock::{Mock, MockServer, ResponseTemplate, matchers::method};
+
+    async fn mock_node(response: ResponseTemplate) -> MockServer {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(response)
+            .mount(&mock_server)
+            .await;
+        mock_server
+    }
+
+    fn ok(result: Value) -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_json(json!({
+            "jsonrpc": "2.0",
+            "id": 0,
+            "result": result
+        }))
+    }
+
+    fn unix_now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+
+    #[test]
+    fn test_head_status() {
+        let now = UNIX_EPOCH + Duration::from_secs(1_000);
+        let max_age = Duration::from_secs(30);
+
+        assert!(head_status(10, 990, now, max_age).healthy);
+        assert!(head_status(10, 970, now, max_age).healthy);
+        assert_eq!(
+            head_status(10, 900, now, max_age),
+            CheckStatus::unhealthy("head block 10 is 100s old")
+        );
+        // a head ahead of the local clock isn't stale
+        assert!(head_status(10, 1_005, now, max_age).healthy);
+    }
+
+    #[tokio::test]
+    async fn test_simulation_head_check() {
+        let fresh = mock_node(ok(json!({
+            "number": "0x10",
+            "timestamp": format!("{:#x}", unix_now())
+        })))
+        .await;
+        let stale = mock_node(ok(json!({
+            "number": "0x10",
+            "timestamp": format!("{:#x}", unix_now() - 600)
+        })))
+        .await;
+        let check = |server: &MockServer| {
+            SimulationHeadCheck::new(
+                "simulation",
+                RootProvider::new_http(server.uri().parse().unwrap()),
+                Duration::from_secs(30),
+            )
+        };
+
+        assert!(check(&fresh).check().await.healthy);
+        assert!(!check(&stale).check().await.healthy);
+    }
+
+    #[tokio::test]
+    async fn test_builder_check() {
+        let (metering_tx, _) = broadcast::channel(10);
+        let (backrun_tx, _) = broadcast::channel(10);
+        let registry = Arc::new(BuilderRegistry::new(
+            metering_tx,
+            backrun_tx,
+            BuilderDeliveryConfig {
+                queue_size: 10,
+                max_concurrent_requests: 1,
+                max_retries: 0,
+                request_timeout: Duration::from_secs(1),
+                connect_timeout: Duration::from_secs(1),
+            },
+        ));
+        let check = BuilderCheck::new(registry.clone());
+        assert!(check.check().await.healthy);
 
-    let listener = tokio::net::TcpListener::bind(addr).await?;
-    let bound_addr = listener.local_addr()?;
+        let down = mock_node(ResponseTemplate::new(503)).await;
+        registry.add(BuilderEndpoint {
+            url: down.uri().parse().unwrap(),
+            auth: None,
+        });
+        assert!(!check.check().await.healthy);
 
-    info!(
-        message = "Health check server bound successfully",
-        address = %bound_addr
-    );
+        let up = mock_node(ok(json!("0x10"))).await;
+        registry.add(BuilderEndpoint {
+            url: up.uri().parse().unwrap(),
+            auth: None,
+        });
+        let status = check.check().await;
+        assert!(status.healthy);
+        assert!(status.detail.unwrap().starts_with("1/2 builders reachable"));
+    }
 
-    let handle = tokio::spawn(async move {
-        axum::serve(listener, app).await?;
-        Ok(())
-    });
+    #[tokio::test]
+    async fn test_mempool_engine_check() {
+        let task = tokio::spawn(std::future::pending::<()>());
+        let check = MempoolEngineCheck::new(task.abort_handle());
+        assert!(check.check().await.healthy);
 
-    Ok((bound_addr, handle))
+        task.abort();
+        let _ = task.await;
+        assert!(!check.check().await.healthy);
+    }
 }
@@ -1,20 +1,35 @@
+pub mod admin;
+pub mod auth;
+pub mod builder;
+pub mod builder_registry;
+pub mod errors;
 pub mod health;
+pub mod metering_cache;
 pub mod metrics;
 pub mod queue;
+pub mod rate_limit;
 pub mod service;
+pub mod simulation;
+pub mod status;
+pub mod subscriptions;
+pub mod tracker;
 pub mod validation;
-use alloy_primitives::TxHash;
-use alloy_provider::{Provider, ProviderBuilder, RootProvider};
+use builder::{BuilderClient, BuilderDeliveryConfig, BuilderEndpoint, spawn_builder_delivery};
 use clap::Parser;
-use op_alloy_network::Optimism;
+use rate_limit::RateLimitRule;
+use serde::{Serialize, Serializer};
+use std::fmt;
 use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
 use std::str::FromStr;
+use tips_audit_lib::OverflowPolicy;
 use tips_core::{AcceptedBundle, MeterBundleResponse};
-use tokio::sync::broadcast;
-use tracing::{error, warn};
+use tokio::sync::{broadcast, watch};
+use tokio::task::JoinSet;
 use url::Url;
+use validation::TxType;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TxSubmissionMethod {
     Mempool,
     Kafka,
@@ -38,8 +53,63 @@ impl FromStr for TxSubmissionMethod {
     }
 }
 
-#[derive(Parser, Debug, Clone)]
+impl fmt::Display for TxSubmissionMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Mempool => "mempool",
+            Self::Kafka => "kafka",
+            Self::MempoolAndKafka => "mempool,kafka",
+            Self::None => "none",
+        })
+    }
+}
+
+impl Serialize for TxSubmissionMethod {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Serializes secrets in the config dump without revealing them
+fn redact<S: Serializer>(secrets: &[String], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(secrets.iter().map(|_| "<redacted>"))
+}
+
+/// Serializes an optional secret in the config dump without revealing it
+fn redact_option<S: Serializer>(secret: &Option<String>, serializer: S) -> Result<S::Ok, S::Error> {
+    secret.as_ref().map(|_| "<redacted>").serialize(serializer)
+}
+
+/// The URL without its userinfo and query, which may carry credentials
+pub fn redact_url(url: &Url) -> Url {
+    let mut url = url.clone();
+    let _ = url.set_username("");
+    let _ = url.set_password(None);
+    url.set_query(None);
+    url
+}
+
+/// Serializes a URL in the config dump without its userinfo and query
+fn redact_credentials<S: Serializer>(url: &Url, serializer: S) -> Result<S::Ok, S::Error> {
+    redact_url(url).serialize(serializer)
+}
+
+/// Serializes URLs in the config dump without their userinfo and query
+fn redact_credentials_seq<S: Serializer>(urls: &[Url], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(urls.iter().map(redact_url))
+}
+
+/// Serializes an optional URL in the config dump without its userinfo and query
+fn redact_credentials_option<S: Serializer>(
+    url: &Option<Url>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    url.as_ref().map(redact_url).serialize(serializer)
+}
+
+#[derive(Parser, Debug, Clone, Serialize)]
 #[command(author, version, about, long_about = None)]
+#[serde(rename_all = "camelCase")]
 pub struct Config {
     /// Address to bind the RPC server to
     #[arg(long, env = "TIPS_INGRESS_ADDRESS", default_value = "0.0.0.0")]
@@ -51,6 +121,7 @@ pub struct Config {
 
     /// URL of the mempool service to proxy transactions to
     #[arg(long, env = "TIPS_INGRESS_RPC_MEMPOOL")]
+    #[serde(serialize_with = "redact_credentials")]
     pub mempool_url: Url,
 
     /// Method to submit transactions to the mempool
@@ -85,6 +156,37 @@ pub struct Config {
     )]
     pub audit_topic: String,
 
+    /// Maximum number of audit events buffered in memory while waiting to be published
+    #[arg(
+        long,
+        env = "TIPS_INGRESS_AUDIT_CHANNEL_CAPACITY",
+        default_value = "10000"
+    )]
+    pub audit_channel_capacity: usize,
+
+    /// What happens to audit events while the channel is full: block, drop-oldest or spill
+    #[arg(
+        long,
+        env = "TIPS_INGRESS_AUDIT_OVERFLOW_POLICY",
+        default_value = "block"
+    )]
+    pub audit_overflow_policy: OverflowPolicy,
+
+    /// Append-only file audit events are spilled to and replayed from once Kafka recovers.
+    /// Required by the spill policy, with the other policies only used for events that keep
+    /// failing to publish
+    #[arg(long, env = "TIPS_INGRESS_AUDIT_SPILL_FILE")]
+    pub audit_spill_file: Option<PathBuf>,
+
+    /// Time in milliseconds a request waits for room in the full audit channel before its audit
+    /// event is spilled, or dropped without a spill file
+    #[arg(
+        long,
+        env = "TIPS_INGRESS_AUDIT_SEND_TIMEOUT_MS",
+        default_value = "1000"
+    )]
+    pub audit_send_timeout_ms: u64,
+
     /// Kafka properties file for the user operation consumer
     #[arg(
         long,
@@ -124,8 +226,40 @@ pub struct Config {
 
     /// URL of the simulation RPC service for bundle metering
     #[arg(long, env = "TIPS_INGRESS_RPC_SIMULATION")]
+    #[serde(serialize_with = "redact_credentials")]
     pub simulation_rpc: Url,
 
+    /// URLs of fallback simulation RPC services, tried in order when the primary one fails or is slow
+    #[arg(
+        long,
+        env = "TIPS_INGRESS_RPC_SIMULATION_FALLBACKS",
+        value_delimiter = ','
+    )]
+    #[serde(serialize_with = "redact_credentials_seq")]
+    pub simulation_fallback_rpcs: Vec<Url>,
+
+    /// Delay in milliseconds after which a simulation request is also sent to the next endpoint,
+    /// hedging is disabled if unset
+    #[arg(long, env = "TIPS_INGRESS_SIMULATION_HEDGE_DELAY_MS")]
+    pub simulation_hedge_delay_ms: Option<u64>,
+
+    /// Number of consecutive failures to reach a simulation endpoint after which it is taken out
+    /// of rotation. JSON-RPC error responses don't count, the endpoint answered.
+    #[arg(
+        long,
+        env = "TIPS_INGRESS_SIMULATION_FAILURE_THRESHOLD",
+        default_value = "3"
+    )]
+    pub simulation_failure_threshold: u32,
+
+    /// Time in milliseconds a failing simulation endpoint stays out of rotation
+    #[arg(
+        long,
+        env = "TIPS_INGRESS_SIMULATION_COOLDOWN_MS",
+        default_value = "30000"
+    )]
+    pub simulation_cooldown_ms: u64,
+
     /// Port to bind the Prometheus metrics server to
     #[arg(
         long,
@@ -159,8 +293,30 @@ pub struct Config {
 
     /// URLs of the builder RPC service for setting metering information
     #[arg(long, env = "TIPS_INGRESS_BUILDER_RPCS", value_delimiter = ',')]
+    #[serde(serialize_with = "redact_credentials_seq")]
     pub builder_rpcs: Vec<Url>,
 
+    /// JSON file listing builders and their credentials, reloaded when modified
+    #[arg(long, env = "TIPS_INGRESS_BUILDERS_FILE")]
+    pub builders_file: Option<PathBuf>,
+
+    /// Interval in seconds at which the builders file is checked for changes
+    #[arg(
+        long,
+        env = "TIPS_INGRESS_BUILDERS_FILE_POLL_INTERVAL_SECS",
+        default_value = "5"
+    )]
+    pub builders_file_poll_interval_secs: u64,
+
+    /// Address to bind the admin RPC server to, the admin methods are disabled if unset
+    #[arg(long, env = "TIPS_INGRESS_ADMIN_ADDR")]
+    pub admin_addr: Option<SocketAddr>,
+
+    /// Bearer tokens authorizing requests to the admin RPC server, required when it is enabled
+    #[arg(long, env = "TIPS_INGRESS_ADMIN_AUTH_TOKENS", value_delimiter = ',')]
+    #[serde(serialize_with = "redact")]
+    pub admin_auth_tokens: Vec<String>,
+
     /// Maximum number of `MeterBundleResponse`s to buffer in memory
     #[arg(
         long,
@@ -169,6 +325,38 @@ pub struct Config {
     )]
     pub max_buffered_meter_bundle_responses: usize,
 
+    /// Maximum number of messages queued for delivery to each builder
+    #[arg(long, env = "TIPS_INGRESS_BUILDER_QUEUE_SIZE", default_value = "1000")]
+    pub builder_queue_size: usize,
+
+    /// Maximum number of concurrent requests to each builder
+    #[arg(
+        long,
+        env = "TIPS_INGRESS_BUILDER_MAX_CONCURRENT_REQUESTS",
+        default_value = "16"
+    )]
+    pub builder_max_concurrent_requests: usize,
+
+    /// Number of times a failed request to a builder is retried before the message is dropped
+    #[arg(long, env = "TIPS_INGRESS_BUILDER_MAX_RETRIES", default_value = "3")]
+    pub builder_max_retries: usize,
+
+    /// Timeout in milliseconds of a request to a builder, including connecting
+    #[arg(
+        long,
+        env = "TIPS_INGRESS_BUILDER_REQUEST_TIMEOUT_MS",
+        default_value = "2000"
+    )]
+    pub builder_request_timeout_ms: u64,
+
+    /// Timeout in milliseconds for connecting to a builder
+    #[arg(
+        long,
+        env = "TIPS_INGRESS_BUILDER_CONNECT_TIMEOUT_MS",
+        default_value = "500"
+    )]
+    pub builder_connect_timeout_ms: u64,
+
     /// Maximum number of backrun bundles to buffer in memory
     #[arg(
         long,
@@ -185,10 +373,72 @@ pub struct Config {
     )]
     pub health_check_addr: SocketAddr,
 
+    /// Timeout in milliseconds for each dependency check behind the `/ready` endpoint
+    #[arg(
+        long,
+        env = "TIPS_INGRESS_READY_CHECK_TIMEOUT_MS",
+        default_value = "2000"
+    )]
+    pub ready_check_timeout_ms: u64,
+
+    /// Maximum age in seconds of a simulation node's latest block for ingress to be ready
+    #[arg(
+        long,
+        env = "TIPS_INGRESS_READY_MAX_HEAD_AGE_SECS",
+        default_value = "30"
+    )]
+    pub ready_max_head_age_secs: u64,
+
+    /// Time in seconds to wait on shutdown for in-flight requests to complete and the audit events
+    /// and Kafka messages still buffered to be delivered
+    #[arg(long, env = "TIPS_INGRESS_SHUTDOWN_TIMEOUT_SECS", default_value = "30")]
+    pub shutdown_timeout_secs: u64,
+
+    /// Token bucket rate limits, as comma separated `<method>:<key>=<per second>/<burst>` entries
+    /// keyed by `sender`, `ip` or `api-key`, e.g. `eth_sendBundle:sender=5/10`. No limits if unset
+    #[arg(long, env = "TIPS_INGRESS_RATE_LIMITS", value_delimiter = ',')]
+    pub rate_limits: Vec<RateLimitRule>,
+
+    /// Identify clients by the last address of the `X-Forwarded-For` header rather than the peer
+    /// address of their connection. Only enable behind a load balancer that sets the header
+    #[arg(
+        long,
+        env = "TIPS_INGRESS_TRUST_FORWARDED_FOR",
+        default_value = "false"
+    )]
+    pub trust_forwarded_for: bool,
+
+    /// JSON file of the API keys and Flashbots signers searchers authenticate with, and the tiers
+    /// setting their limits, backrun access and priority. Authentication is disabled if unset
+    #[arg(long, env = "TIPS_INGRESS_AUTH_KEYS_FILE")]
+    pub auth_keys_file: Option<PathBuf>,
+
+    /// Rejects requests without an API key or Flashbots signature, requires the auth keys file
+    #[arg(long, env = "TIPS_INGRESS_AUTH_REQUIRED", default_value = "false")]
+    pub auth_required: bool,
+
+    /// Id of this ingress instance recorded on received bundle events, defaults to the hostname
+    #[arg(long, env = "TIPS_INGRESS_INSTANCE_ID")]
+    pub instance_id: Option<String>,
+
+    /// Key of the HMAC-SHA256 client IPs are hashed with before being recorded on received and
+    /// updated bundle events. Client IPs are not recorded if unset
+    #[arg(long, env = "TIPS_INGRESS_CLIENT_IP_HASH_KEY")]
+    #[serde(serialize_with = "redact_option")]
+    pub client_ip_hash_key: Option<String>,
+
     /// chain id
     #[arg(long, env = "TIPS_INGRESS_CHAIN_ID", default_value = "11")]
     pub chain_id: u64,
 
+    /// Accept legacy transactions without EIP-155 replay protection
+    #[arg(
+        long,
+        env = "TIPS_INGRESS_ALLOW_UNPROTECTED_TXS",
+        default_value = "false"
+    )]
+    pub allow_unprotected_txs: bool,
+
     /// Enable backrun bundle submission to op-rbuilder
     #[arg(long, env = "TIPS_INGRESS_BACKRUN_ENABLED", default_value = "false")]
     pub backrun_enabled: bool,
@@ -201,62 +451,163 @@ pub struct Config {
     #[arg(long, env = "MAX_BACKRUN_GAS_LIMIT", default_value = "5000000")]
     pub max_backrun_gas_limit: u64,
 
+    /// Maximum number of transactions allowed in a bundle sent with `eth_sendBundle`
+    #[arg(long, env = "TIPS_INGRESS_MAX_BUNDLE_TXS", default_value = "3")]
+    pub max_bundle_txs: usize,
+
+    /// Maximum total gas limit for all transactions in a bundle sent with `eth_sendBundle`
+    #[arg(long, env = "TIPS_INGRESS_MAX_BUNDLE_GAS", default_value = "25000000")]
+    pub max_bundle_gas: u64,
+
+    /// Maximum gas limit of a transaction sent with `eth_sendRawTransaction`
+    #[arg(long, env = "TIPS_INGRESS_MAX_RAW_TX_GAS", default_value = "25000000")]
+    pub max_raw_tx_gas: u64,
+
+    /// Maximum gas limit of any single transaction, in any kind of bundle
+    #[arg(long, env = "TIPS_INGRESS_MAX_TX_GAS", default_value = "25000000")]
+    pub max_tx_gas: u64,
+
+    /// Minimum number of seconds a bundle's max_timestamp must be in the future
+    #[arg(
+        long,
+        env = "TIPS_INGRESS_BUNDLE_MIN_TIME_WINDOW_SECS",
+        default_value = "0"
+    )]
+    pub bundle_min_time_window_secs: u64,
+
+    /// Maximum number of seconds a bundle's max_timestamp may be in the future
+    #[arg(
+        long,
+        env = "TIPS_INGRESS_BUNDLE_MAX_TIME_WINDOW_SECS",
+        default_value = "3600"
+    )]
+    pub bundle_max_time_window_secs: u64,
+
+    /// Transaction types accepted with `eth_sendRawTransaction`, deposit transactions are always rejected
+    #[arg(
+        long,
+        env = "TIPS_INGRESS_RAW_TX_ALLOWED_TYPES",
+        value_delimiter = ',',
+        default_values = ["legacy", "eip2930", "eip1559", "eip7702"]
+    )]
+    pub raw_tx_allowed_types: Vec<TxType>,
+
+    /// Transaction types accepted in bundles and backrun bundles, deposit transactions are always rejected
+    #[arg(
+        long,
+        env = "TIPS_INGRESS_BUNDLE_ALLOWED_TX_TYPES",
+        value_delimiter = ',',
+        default_values = ["legacy", "eip2930", "eip1559", "eip7702"]
+    )]
+    pub bundle_allowed_tx_types: Vec<TxType>,
+
+    /// Maximum size in bytes of an encoded transaction
+    #[arg(long, env = "TIPS_INGRESS_MAX_TX_SIZE_BYTES", default_value = "131072")]
+    pub max_tx_size_bytes: usize,
+
+    /// Block gas limit, transactions with a higher gas limit are rejected
+    #[arg(long, env = "TIPS_INGRESS_BLOCK_GAS_LIMIT", default_value = "30000000")]
+    pub block_gas_limit: u64,
+
     /// URL of third-party RPC endpoint to forward raw transactions to (enables forwarding if set)
     #[arg(long, env = "TIPS_INGRESS_RAW_TX_FORWARD_RPC")]
+    #[serde(serialize_with = "redact_credentials_option")]
     pub raw_tx_forward_rpc: Option<Url>,
 
     /// TTL for bundle cache in seconds
     #[arg(long, env = "TIPS_INGRESS_BUNDLE_CACHE_TTL", default_value = "20")]
     pub bundle_cache_ttl: u64,
 
+    /// Maximum number of metering results cached for bundles resubmitted within the same
+    /// flashblock, 0 disables the cache
+    #[arg(
+        long,
+        env = "TIPS_INGRESS_METERING_CACHE_SIZE",
+        default_value = "10000"
+    )]
+    pub metering_cache_size: u64,
+
+    /// Interval in milliseconds at which the simulation node's pending block is checked for a
+    /// new block or flashblock, which invalidates the metering cache
+    #[arg(
+        long,
+        env = "TIPS_INGRESS_METERING_CACHE_POLL_INTERVAL_MS",
+        default_value = "200"
+    )]
+    pub metering_cache_poll_interval_ms: u64,
+
+    /// TTL in seconds for tracking accepted bundles that can still be replaced or cancelled
+    #[arg(long, env = "TIPS_INGRESS_LIVE_BUNDLE_TTL", default_value = "10800")]
+    pub live_bundle_ttl: u64,
+
+    /// Minimum fee increase, in percent, for a raw transaction to replace one with the same sender and nonce
+    #[arg(
+        long,
+        env = "TIPS_INGRESS_REPLACEMENT_FEE_BUMP_PERCENT",
+        default_value = "10"
+    )]
+    pub replacement_fee_bump_percent: u64,
+
+    /// Check transactions against their sender's nonce and balance, including the L1 data fee,
+    /// before metering them
+    #[arg(
+        long,
+        env = "TIPS_INGRESS_VALIDATE_ACCOUNT_STATE",
+        default_value = "true"
+    )]
+    pub validate_account_state: bool,
+
+    /// S3 bucket of the audit store, enables the `tips_getBundleStatus` and
+    /// `tips_getTransactionStatus` methods if set
+    #[arg(long, env = "TIPS_INGRESS_AUDIT_S3_BUCKET")]
+    pub audit_s3_bucket: Option<String>,
+
+    /// Custom S3 endpoint of the audit store, e.g. for a local MinIO
+    #[arg(long, env = "TIPS_INGRESS_AUDIT_S3_ENDPOINT")]
+    pub audit_s3_endpoint: Option<String>,
+
+    /// Kafka properties file for reading inclusion events back from the audit topic for
+    /// `tips_subscribeBundle`, subscribers only receive the events produced by ingress if unset
+    #[arg(long, env = "TIPS_INGRESS_KAFKA_AUDIT_SUBSCRIPTION_PROPERTIES_FILE")]
+    pub audit_subscription_kafka_properties: Option<String>,
+
+    /// Tokens authorizing `tips_subscribeAllBundles`, the firehose is disabled if empty
+    #[arg(long, env = "TIPS_INGRESS_FIREHOSE_AUTH_TOKENS", value_delimiter = ',')]
+    #[serde(serialize_with = "redact")]
+    pub firehose_auth_tokens: Vec<String>,
+
+    /// Maximum number of bundle events to buffer for each subscriber
+    #[arg(
+        long,
+        env = "TIPS_INGRESS_MAX_BUFFERED_BUNDLE_EVENTS",
+        default_value = "1000"
+    )]
+    pub max_buffered_bundle_events: usize,
+
     /// Enable sending to builder
     #[arg(long, env = "TIPS_INGRESS_SEND_TO_BUILDER", default_value = "false")]
     pub send_to_builder: bool,
 }
 
+/// Starts a delivery pipeline of metering information and backrun bundles to a builder, until
+/// `shutdown` is signalled or its sender is dropped. Dropping the returned tasks aborts delivery
+/// right away.
 pub fn connect_ingress_to_builder(
     metering_rx: broadcast::Receiver<MeterBundleResponse>,
     backrun_rx: broadcast::Receiver<AcceptedBundle>,
-    builder_rpc: Url,
-) {
-    let builder: RootProvider<Optimism> = ProviderBuilder::new()
-        .disable_recommended_fillers()
-        .network::<Optimism>()
-        .connect_http(builder_rpc);
-
-    let metering_builder = builder.clone();
-    tokio::spawn(async move {
-        let mut event_rx = metering_rx;
-        while let Ok(event) = event_rx.recv().await {
-            if event.results.is_empty() {
-                warn!(message = "received metering information with no transactions", hash=%event.bundle_hash);
-                continue;
-            }
-
-            let tx_hash = event.results[0].tx_hash;
-            if let Err(e) = metering_builder
-                .client()
-                .request::<(TxHash, MeterBundleResponse), ()>(
-                    "base_setMeteringInformation",
-                    (tx_hash, event),
-                )
-                .await
-            {
-                error!(error = %e, "Failed to set metering information for tx hash: {tx_hash}");
-            }
-        }
-    });
-
-    tokio::spawn(async move {
-        let mut event_rx = backrun_rx;
-        while let Ok(accepted_bundle) = event_rx.recv().await {
-            if let Err(e) = builder
-                .client()
-                .request::<(AcceptedBundle,), ()>("base_sendBackrunBundle", (accepted_bundle,))
-                .await
-            {
-                error!(error = ?e, "Failed to send backrun bundle to builder");
-            }
-        }
-    });
+    builder: BuilderEndpoint,
+    delivery_config: &BuilderDeliveryConfig,
+    shutdown: watch::Receiver<()>,
+) -> JoinSet<()> {
+    let builder = BuilderClient::new(builder, delivery_config);
+    let mut tasks = JoinSet::new();
+    spawn_builder_delivery(
+        metering_rx,
+        builder.clone(),
+        delivery_config,
+        shutdown.clone(),
+        &mut tasks,
+    );
+    spawn_builder_delivery(backrun_rx, builder, delivery_config, shutdown, &mut tasks);
+    tasks
 }
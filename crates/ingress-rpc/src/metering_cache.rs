@@ -0,0 +1,293 @@
+use alloy_consensus::private::alloy_eips::{BlockId, BlockNumberOrTag};
+use alloy_primitives::{B256, keccak256};
+use alloy_provider::{Provider, RootProvider};
+use async_trait::async_trait;
+use moka::future::Cache;
+use op_alloy_network::Optimism;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tips_core::{Bundle, MeterBundleResponse};
+use tokio::time::{Duration, interval};
+use tracing::warn;
+
+/// Block and flashblock a bundle was metered on top of
+type StateBlock = (u64, Option<u64>);
+
+/// Caches metering results by bundle and the state they were metered against, so a bundle
+/// resubmitted within the same flashblock isn't metered again. Results are keyed on the full
+/// bundle sent to `base_meterBundle`, as its block number, timestamps and reverting hashes change
+/// the result as much as its transactions. All results are invalidated when the simulation node's
+/// pending state changes, see [`watch_state_changes`], or a result for a newer block or
+/// flashblock comes in, and expire after the TTL in case neither happens.
+pub struct MeteringCache {
+    responses: Cache<(B256, u64, Option<u64>), MeterBundleResponse>,
+    latest_state: Mutex<Option<StateBlock>>,
+    /// Bumped on every invalidation, so results requested before it are not cached
+    generation: AtomicU64,
+}
+
+impl MeteringCache {
+    pub fn new(max_capacity: u64, ttl: Duration) -> Self {
+        Self {
+            responses: Cache::builder()
+                .max_capacity(max_capacity)
+                .time_to_live(ttl)
+                .build(),
+            latest_state: Mutex::new(None),
+            generation: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the result of metering the bundle against the latest known state
+    pub async fn get(&self, bundle: &Bundle) -> Option<MeterBundleResponse> {
+        let (block_number, flashblock_index) = self.latest_state()?;
+        self.responses
+            .get(&(metering_key(bundle)?, block_number, flashblock_index))
+            .await
+    }
+
+    /// The generation to insert a result with that is requested now
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    /// Caches a metering result requested at `generation`. A result requested before the cache
+    /// was last invalidated, or for an older state than the latest known one, is not cached. A
+    /// result for a newer state invalidates the cache.
+    pub async fn insert(&self, bundle: &Bundle, generation: u64, response: MeterBundleResponse) {
+        let Some(key) = metering_key(bundle) else {
+            return;
+        };
+        let state = (response.state_block_number, response.state_flashblock_index);
+        {
+            let mut latest_state = self.latest_state.lock().unwrap();
+            if generation != self.generation() {
+                return;
+            }
+            match *latest_state {
+                Some(latest) if state < latest => return,
+                Some(latest) if state == latest => {}
+                _ => {
+                    *latest_state = Some(state);
+                    self.responses.invalidate_all();
+                }
+            }
+        }
+        self.responses
+            .insert((key, state.0, state.1), response)
+            .await;
+
+        // the cache may have been invalidated while inserting
+        if generation != self.generation() {
+            self.responses.invalidate(&(key, state.0, state.1)).await;
+        }
+    }
+
+    /// Invalidates all results, once the state bundles are metered against has changed
+    pub fn invalidate(&self) {
+        let _latest_state = self.latest_state.lock().unwrap();
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        self.responses.invalidate_all();
+    }
+
+    fn latest_state(&self) -> Option<StateBlock> {
+        *self.latest_state.lock().unwrap()
+    }
+}
+
+/// Hash of everything sent to `base_meterBundle` for the bundle
+fn metering_key(bundle: &Bundle) -> Option<B256> {
+    serde_json::to_vec(bundle).ok().map(keccak256)
+}
+
+/// Interface for fetching the pending state of the simulation node
+#[async_trait]
+pub trait PendingStateLookup: Send + Sync + 'static {
+    /// Number of the pending block and the number of transactions in it, which grows with every
+    /// flashblock
+    async fn fetch_pending_state(&self) -> anyhow::Result<(u64, usize)>;
+}
+
+/// Implementation of the `PendingStateLookup` trait for the `RootProvider`
+#[async_trait]
+impl PendingStateLookup for RootProvider<Optimism> {
+    async fn fetch_pending_state(&self) -> anyhow::Result<(u64, usize)> {
+        let block = self
+            .get_block(BlockId::Number(BlockNumberOrTag::Pending))
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("no pending block"))?;
+        Ok((block.header.number, block.transactions.len()))
+    }
+}
+
+/// Polls the pending state of the simulation node and invalidates the metering cache whenever it
+/// changes, i.e. on every new block, and on every flashblock when the node serves them
+pub fn watch_state_changes(
+    cache: Arc<MeteringCache>,
+    node: impl PendingStateLookup,
+    poll_interval: Duration,
+) {
+    tokio::spawn(async move {
+        let mut last_state = None;
+        let mut ticker = interval(poll_interval);
+        loop {
+            ticker.tick().await;
+            let state = match node.fetch_pending_state().await {
+                Ok(state) => state,
+                Err(e) => {
+                    warn!(message = "Failed to fetch pending state", error = %e);
+                    continue;
+                }
+            };
+            if last_state.is_some_and(|last_state| last_state != state) {
+                cache.invalidate();
+            }
+            last_state = Some(state);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::TxHash;
+
+    fn response(
+        state_block_number: u64,
+        state_flashblock_index: Option<u64>,
+    ) -> MeterBundleResponse {
+        MeterBundleResponse {
+            state_block_number,
+            state_flashblock_index,
+            ..Default::default()
+        }
+    }
+
+    fn bundle(block_number: u64) -> Bundle {
+        Bundle {
+            block_number,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hit_within_same_state() {
+        let cache = MeteringCache::new(100, Duration::from_secs(60));
+        let bundle = bundle(10);
+
+        assert_eq!(cache.get(&bundle).await, None);
+
+        cache
+            .insert(&bundle, cache.generation(), response(10, Some(1)))
+            .await;
+        assert_eq!(cache.get(&bundle).await, Some(response(10, Some(1))));
+        assert_eq!(cache.get(&Bundle::default()).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_key_covers_all_bundle_params() {
+        let cache = MeteringCache::new(100, Duration::from_secs(60));
+        let bundle = bundle(10);
+        cache
+            .insert(&bundle, cache.generation(), response(10, Some(1)))
+            .await;
+
+        // same transactions, but metered with different parameters
+        let variants = [
+            Bundle {
+                block_number: 11,
+                ..bundle.clone()
+            },
+            Bundle {
+                min_timestamp: Some(1),
+                ..bundle.clone()
+            },
+            Bundle {
+                max_timestamp: Some(1),
+                ..bundle.clone()
+            },
+            Bundle {
+                reverting_tx_hashes: vec![TxHash::random()],
+                ..bundle.clone()
+            },
+        ];
+        for variant in &variants {
+            assert_eq!(cache.get(variant).await, None);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_new_flashblock_invalidates() {
+        let cache = MeteringCache::new(100, Duration::from_secs(60));
+        let first = bundle(1);
+        let second = bundle(2);
+
+        cache
+            .insert(&first, cache.generation(), response(10, Some(1)))
+            .await;
+        cache
+            .insert(&second, cache.generation(), response(10, Some(2)))
+            .await;
+        assert_eq!(cache.get(&first).await, None);
+        assert_eq!(cache.get(&second).await, Some(response(10, Some(2))));
+
+        // a result metered against an older state is not cached
+        cache
+            .insert(&first, cache.generation(), response(10, Some(1)))
+            .await;
+        assert_eq!(cache.get(&first).await, None);
+
+        cache
+            .insert(&first, cache.generation(), response(11, None))
+            .await;
+        assert_eq!(cache.get(&first).await, Some(response(11, None)));
+        assert_eq!(cache.get(&second).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_invalidate_drops_results_in_flight() {
+        let cache = MeteringCache::new(100, Duration::from_secs(60));
+        let bundle = bundle(10);
+        cache
+            .insert(&bundle, cache.generation(), response(10, Some(1)))
+            .await;
+
+        let requested_at = cache.generation();
+        cache.invalidate();
+        assert_eq!(cache.get(&bundle).await, None);
+
+        // metered before the state changed
+        cache
+            .insert(&bundle, requested_at, response(10, Some(1)))
+            .await;
+        assert_eq!(cache.get(&bundle).await, None);
+    }
+
+    struct MockNode(Mutex<(u64, usize)>);
+
+    #[async_trait]
+    impl PendingStateLookup for Arc<MockNode> {
+        async fn fetch_pending_state(&self) -> anyhow::Result<(u64, usize)> {
+            Ok(*self.0.lock().unwrap())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_watch_state_changes() {
+        let cache = Arc::new(MeteringCache::new(100, Duration::from_secs(60)));
+        let node = Arc::new(MockNode(Mutex::new((10, 3))));
+        watch_state_changes(cache.clone(), node.clone(), Duration::from_millis(10));
+
+        let bundle = bundle(10);
+        cache
+            .insert(&bundle, cache.generation(), response(10, Some(1)))
+            .await;
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(cache.get(&bundle).await, Some(response(10, Some(1))));
+
+        // a new flashblock adds transactions to the pending block
+        *node.0.lock().unwrap() = (10, 5);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(cache.get(&bundle).await, None);
+    }
+}
@@ -2,11 +2,46 @@ use metrics::{Counter, Histogram};
 use metrics_derive::Metrics;
 use tokio::time::Duration;
 
-pub fn record_histogram(rpc_latency: Duration, rpc: String) {
-    metrics::histogram!("tips_ingress_rpc_rpc_latency", "rpc" => rpc)
+use crate::auth::AuthError;
+use crate::rate_limit::RateLimitKey;
+
+pub fn record_histogram(rpc_latency: Duration, rpc: String, endpoint: &str) {
+    metrics::histogram!("tips_ingress_rpc_rpc_latency", "rpc" => rpc, "endpoint" => endpoint.to_string())
         .record(rpc_latency.as_secs_f64());
 }
 
+pub fn record_tx_type(tx_type: String, accepted: bool) {
+    let result = if accepted { "accepted" } else { "rejected" };
+    metrics::counter!("tips_ingress_rpc_transactions_by_type", "tx_type" => tx_type, "result" => result)
+        .increment(1);
+}
+
+pub fn record_builder_delivery(builder: &str, kind: &'static str, delivered: bool) {
+    let result = if delivered { "delivered" } else { "dropped" };
+    metrics::counter!("tips_ingress_rpc_builder_deliveries", "builder" => builder.to_string(), "kind" => kind, "result" => result)
+        .increment(1);
+}
+
+pub fn record_builder_lagged(builder: &str, kind: &'static str, skipped: u64) {
+    metrics::counter!("tips_ingress_rpc_builder_lagged_messages", "builder" => builder.to_string(), "kind" => kind)
+        .increment(skipped);
+}
+
+pub fn record_builder_broadcast_without_receivers(kind: &'static str) {
+    metrics::counter!("tips_ingress_rpc_builder_broadcasts_without_receivers", "kind" => kind)
+        .increment(1);
+}
+
+pub fn record_rate_limited(method: &str, limit: RateLimitKey) {
+    metrics::counter!("tips_ingress_rpc_rate_limited_requests", "method" => method.to_string(), "limit" => limit.to_string())
+        .increment(1);
+}
+
+pub fn record_auth_rejected(reason: AuthError) {
+    metrics::counter!("tips_ingress_rpc_auth_rejected_requests", "reason" => reason.as_str())
+        .increment(1);
+}
+
 #[derive(Metrics, Clone)]
 #[metrics(scope = "tips_ingress_rpc")]
 pub struct Metrics {
@@ -51,4 +86,22 @@ pub struct Metrics {
 
     #[metric(describe = "Number of bundles that exceeded the metering time")]
     pub bundles_exceeded_metering_time: Counter,
+
+    #[metric(describe = "Number of metering results served from the metering cache")]
+    pub metering_cache_hits: Counter,
+
+    #[metric(describe = "Number of bundles metered because their result wasn't cached")]
+    pub metering_cache_misses: Counter,
+
+    #[metric(describe = "Number of bundles simulated with eth_callBundle")]
+    pub bundles_called: Counter,
+
+    #[metric(describe = "Number of bundles cancelled")]
+    pub bundles_cancelled: Counter,
+
+    #[metric(describe = "Number of bundles replaced by a newer version")]
+    pub bundles_replaced: Counter,
+
+    #[metric(describe = "Number of bundles rejected because a protected transaction reverted")]
+    pub bundles_rejected_reverted: Counter,
 }
@@ -8,7 +8,7 @@ use async_trait::async_trait;
 use backon::{ExponentialBuilder, Retryable};
 use rdkafka::producer::{FutureProducer, FutureRecord};
 use std::sync::Arc;
-use tips_core::AcceptedBundle;
+use tips_core::{AcceptedBundle, CancelledBundle};
 use tokio::time::Duration;
 use tracing::{error, info};
 
@@ -118,6 +118,14 @@ impl<Q: MessageQueue> BundleQueuePublisher<Q> {
         let payload = serde_json::to_vec(bundle)?;
         self.queue.publish(&self.topic, &key, &payload).await
     }
+
+    /// Publishes a cancellation on the ingress topic, keyed by the hash of the bundle it cancels
+    /// so it's read after that bundle
+    pub async fn publish_cancellation(&self, cancellation: &CancelledBundle) -> Result<()> {
+        let key = cancellation.bundle_hash.to_string();
+        let payload = serde_json::to_vec(cancellation)?;
+        self.queue.publish(&self.topic, &key, &payload).await
+    }
 }
 
 #[cfg(test)]
@@ -128,6 +136,7 @@ mod tests {
         AcceptedBundle, Bundle, BundleExtensions, test_utils::create_test_meter_bundle_response,
     };
     use tokio::time::{Duration, Instant};
+    use uuid::Uuid;
 
     fn create_test_bundle() -> Bundle {
         Bundle::default()
@@ -164,4 +173,44 @@ mod tests {
         assert!(result.is_err());
         assert!(elapsed >= Duration::from_millis(100));
     }
+
+    #[derive(Default)]
+    struct RecordingQueue {
+        published: std::sync::Mutex<Vec<(String, String, Vec<u8>)>>,
+    }
+
+    #[async_trait]
+    impl MessageQueue for RecordingQueue {
+        async fn publish(&self, topic: &str, key: &str, payload: &[u8]) -> Result<()> {
+            self.published.lock().unwrap().push((
+                topic.to_string(),
+                key.to_string(),
+                payload.to_vec(),
+            ));
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cancellation_published_on_ingress_topic() {
+        let queue = Arc::new(RecordingQueue::default());
+        let publisher = BundleQueuePublisher::new(queue.clone(), "tips-ingress".to_string());
+        let cancellation = CancelledBundle {
+            uuid: Uuid::new_v4(),
+            bundle_hash: B256::repeat_byte(1),
+        };
+
+        publisher.publish_cancellation(&cancellation).await.unwrap();
+
+        let published = queue.published.lock().unwrap();
+        let (topic, key, payload) = &published[0];
+        assert_eq!(topic, "tips-ingress");
+        assert_eq!(key, &cancellation.bundle_hash.to_string());
+        let record: serde_json::Value = serde_json::from_slice(payload).unwrap();
+        assert_eq!(record["type"], "CancelledBundle");
+        assert_eq!(
+            serde_json::from_value::<CancelledBundle>(record).unwrap(),
+            cancellation
+        );
+    }
 }
@@ -0,0 +1,787 @@
+use alloy_consensus::transaction::SignerRecoverable;
+use alloy_primitives::{Address, Bytes};
+use alloy_provider::network::eip2718::Decodable2718;
+use jsonrpsee::{
+    Methods,
+    core::middleware::{Batch, BatchEntry, BatchEntryErr, Notification, RpcServiceT},
+    server::{
+        HttpRequest, MethodResponse, RpcServiceBuilder, Server, ServerHandle,
+        serve_with_graceful_shutdown, stop_channel,
+    },
+    types::{ErrorObjectOwned, Extensions, Params, Request},
+};
+use moka::future::Cache;
+use op_alloy_consensus::OpTxEnvelope;
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use tokio::net::TcpListener;
+use tokio::time::{Duration, Instant};
+use tower::{Layer, Service};
+use tracing::{info, warn};
+
+use crate::auth::{AuthLayer, Authenticator, Caller};
+use crate::errors::IngressError;
+use crate::metrics::record_rate_limited;
+
+/// Upper bound on the number of token buckets kept in memory, the least recently used are evicted
+const MAX_TRACKED_BUCKETS: u64 = 1_000_000;
+
+/// What a rate limit is keyed by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RateLimitKey {
+    /// Each signer of the submitted transactions
+    Sender,
+    /// The client IP, see [`ClientInfo::ip`]
+    Ip,
+    /// The `X-Api-Key` header, requests without one aren't limited by it
+    ApiKey,
+}
+
+impl FromStr for RateLimitKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sender" => Ok(Self::Sender),
+            "ip" => Ok(Self::Ip),
+            "api-key" => Ok(Self::ApiKey),
+            _ => Err(format!(
+                "Invalid rate limit key: '{s}'. Valid options: sender, ip, api-key"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for RateLimitKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Sender => "sender",
+            Self::Ip => "ip",
+            Self::ApiKey => "api-key",
+        })
+    }
+}
+
+/// Token bucket limit on one RPC method, written as `<method>:<key>=<per second>/<burst>`, e.g.
+/// `eth_sendBundle:sender=5/10`
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimitRule {
+    pub method: String,
+    pub key: RateLimitKey,
+    /// Tokens added to each bucket per second
+    pub per_second: f64,
+    /// Size of each bucket, the number of requests accepted in a burst
+    pub burst: u32,
+}
+
+impl FromStr for RateLimitRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid =
+            || format!("Invalid rate limit: '{s}'. Expected <method>:<key>=<per second>/<burst>");
+        let (method, limit) = s.split_once(':').ok_or_else(invalid)?;
+        let (key, limit) = limit.split_once('=').ok_or_else(invalid)?;
+        let (per_second, burst) = limit.split_once('/').ok_or_else(invalid)?;
+
+        let per_second: f64 = per_second.parse().map_err(|_| invalid())?;
+        let burst: u32 = burst.parse().map_err(|_| invalid())?;
+        if method.is_empty() || !per_second.is_finite() || per_second <= 0.0 || burst == 0 {
+            return Err(invalid());
+        }
+        Ok(Self {
+            method: method.to_string(),
+            key: key.parse()?,
+            per_second,
+            burst,
+        })
+    }
+}
+
+impl fmt::Display for RateLimitRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}={}/{}",
+            self.method, self.key, self.per_second, self.burst
+        )
+    }
+}
+
+impl Serialize for RateLimitRule {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl TokenBucket {
+    fn full(burst: u32, now: Instant) -> Self {
+        Self {
+            tokens: burst as f64,
+            refilled_at: now,
+        }
+    }
+
+    /// Adds the tokens accrued since the last refill, and returns how long until there is a token
+    /// to take if there is none
+    fn refill(&mut self, per_second: f64, burst: u32, now: Instant) -> Result<(), Duration> {
+        let elapsed = now.saturating_duration_since(self.refilled_at);
+        self.tokens = (self.tokens + elapsed.as_secs_f64() * per_second).min(burst as f64);
+        self.refilled_at = now;
+        if self.tokens >= 1.0 {
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - self.tokens) / per_second))
+        }
+    }
+
+    fn take(&mut self) {
+        // a concurrent request may have taken the last token since the refill
+        self.tokens = (self.tokens - 1.0).max(0.0);
+    }
+}
+
+/// Address, API key and request id of the client sending a request, read from the connection and
+/// HTTP headers by [`ClientInfoLayer`] and available to the RPC middleware in the request
+/// extensions
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClientInfo {
+    /// The peer address of the connection, or the last address of the `X-Forwarded-For` header
+    /// added by the load balancer if forwarded headers are trusted
+    pub ip: Option<IpAddr>,
+    pub api_key: Option<String>,
+    /// The `X-Request-Id` header set by the load balancer
+    pub request_id: Option<String>,
+}
+
+impl ClientInfo {
+    /// The client of a request, unknown if the request didn't go through [`ClientInfoLayer`]
+    pub fn from_extensions(ext: &Extensions) -> Self {
+        ext.get::<Self>().cloned().unwrap_or_default()
+    }
+
+    fn from_request<B>(request: &HttpRequest<B>, peer: IpAddr, trust_forwarded_for: bool) -> Self {
+        let header = |name: &str| {
+            request
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+        };
+        let forwarded_for = header("x-forwarded-for")
+            .filter(|_| trust_forwarded_for)
+            .and_then(|forwarded| forwarded.rsplit(',').next())
+            .and_then(|ip| ip.trim().parse().ok());
+        Self {
+            ip: Some(forwarded_for.unwrap_or(peer)),
+            api_key: header("x-api-key")
+                .filter(|key| !key.is_empty())
+                .map(str::to_string),
+            request_id: header("x-request-id")
+                .filter(|id| !id.is_empty())
+                .map(str::to_string),
+        }
+    }
+}
+
+/// Attaches the [`ClientInfo`] of HTTP and WebSocket requests on a connection to their extensions
+#[derive(Debug, Clone, Copy)]
+pub struct ClientInfoLayer {
+    peer: IpAddr,
+    /// Whether the `X-Forwarded-For` header is set by a trusted load balancer, rather than by
+    /// clients who could claim any address
+    trust_forwarded_for: bool,
+}
+
+impl ClientInfoLayer {
+    pub fn new(peer: IpAddr, trust_forwarded_for: bool) -> Self {
+        Self {
+            peer,
+            trust_forwarded_for,
+        }
+    }
+}
+
+impl<S> Layer<S> for ClientInfoLayer {
+    type Service = ClientInfoService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ClientInfoService {
+            inner,
+            peer: self.peer,
+            trust_forwarded_for: self.trust_forwarded_for,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ClientInfoService<S> {
+    inner: S,
+    peer: IpAddr,
+    trust_forwarded_for: bool,
+}
+
+impl<S, B> Service<HttpRequest<B>> for ClientInfoService<S>
+where
+    S: Service<HttpRequest<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: HttpRequest<B>) -> Self::Future {
+        let client = ClientInfo::from_request(&request, self.peer, self.trust_forwarded_for);
+        request.extensions_mut().insert(client);
+        self.inner.call(request)
+    }
+}
+
+/// The fields of a bundle needed to find its senders
+#[derive(Deserialize)]
+struct BundleTxs {
+    txs: Vec<Bytes>,
+}
+
+/// Recovers the distinct signers of the transactions submitted by a request. Transactions that
+/// fail to decode are skipped, the service rejects them.
+fn request_senders(method: &str, params: Params<'_>) -> Vec<Address> {
+    let txs = match method {
+        "eth_sendRawTransaction" => params.one::<Bytes>().map(|tx| vec![tx]),
+        "eth_sendBundle" | "eth_sendBackrunBundle" | "eth_callBundle" => {
+            params.one::<BundleTxs>().map(|bundle| bundle.txs)
+        }
+        _ => return Vec::new(),
+    };
+
+    let mut senders: Vec<Address> = txs
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|tx| {
+            OpTxEnvelope::decode_2718_exact(tx)
+                .ok()?
+                .recover_signer()
+                .ok()
+        })
+        .collect();
+    senders.sort();
+    senders.dedup();
+    senders
+}
+
+type BucketKey = (String, RateLimitKey, String);
+
+/// Token bucket rate limits per RPC method, keyed by sender, client IP or API key
+pub struct RateLimiter {
+    rules: HashMap<String, Vec<RateLimitRule>>,
+    buckets: Cache<BucketKey, Arc<Mutex<TokenBucket>>>,
+}
+
+impl fmt::Debug for RateLimiter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RateLimiter")
+            .field("rules", &self.rules)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RateLimiter {
+    pub fn new(rules: impl IntoIterator<Item = RateLimitRule>) -> Self {
+        let mut by_method: HashMap<String, Vec<RateLimitRule>> = HashMap::new();
+        for rule in rules {
+            by_method.entry(rule.method.clone()).or_default().push(rule);
+        }
+
+        // An idle bucket is full again after burst / rate, after which it can be dropped
+        let refill_secs = by_method
+            .values()
+            .flatten()
+            .map(|rule| rule.burst as f64 / rule.per_second)
+            .fold(1.0, f64::max);
+        Self {
+            rules: by_method,
+            buckets: Cache::builder()
+                .max_capacity(MAX_TRACKED_BUCKETS)
+                .time_to_idle(Duration::from_secs_f64(refill_secs))
+                .build(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Takes a token from every bucket the request falls into. Rejects the request without taking
+    /// any if one of them is empty, with the longest wait until all of them have a token again.
+    /// The limits are scaled by the tier of the caller.
+    pub async fn check(&self, request: &Request<'_>) -> Result<(), IngressError> {
+        let method = request.method_name();
+        let Some(rules) = self.rules.get(method) else {
+            return Ok(());
+        };
+
+        let client = request
+            .extensions()
+            .get::<ClientInfo>()
+            .cloned()
+            .unwrap_or_default();
+        let multiplier = request
+            .extensions()
+            .get::<Caller>()
+            .map_or(1.0, |caller| caller.tier.rate_limit_multiplier);
+        let senders = if rules.iter().any(|rule| rule.key == RateLimitKey::Sender) {
+            request_senders(method, request.params())
+        } else {
+            Vec::new()
+        };
+
+        let mut buckets = Vec::new();
+        let mut limited: Option<(RateLimitKey, Duration)> = None;
+        for rule in rules {
+            let per_second = rule.per_second * multiplier;
+            let burst = (rule.burst as f64 * multiplier).ceil().max(1.0) as u32;
+            let identities: Vec<String> = match rule.key {
+                RateLimitKey::Sender => senders.iter().map(Address::to_string).collect(),
+                RateLimitKey::Ip => client.ip.iter().map(IpAddr::to_string).collect(),
+                RateLimitKey::ApiKey => client.api_key.iter().cloned().collect(),
+            };
+
+            for identity in identities {
+                let now = Instant::now();
+                let bucket = self
+                    .buckets
+                    .get_with((method.to_string(), rule.key, identity.clone()), async {
+                        Arc::new(Mutex::new(TokenBucket::full(burst, now)))
+                    })
+                    .await;
+                let refilled = bucket.lock().unwrap().refill(per_second, burst, now);
+
+                if let Err(retry_after) = refilled {
+                    record_rate_limited(method, rule.key);
+                    // API keys are secrets, only senders and IPs are logged
+                    let client = match rule.key {
+                        RateLimitKey::ApiKey => "<redacted>",
+                        RateLimitKey::Sender | RateLimitKey::Ip => identity.as_str(),
+                    };
+                    info!(
+                        message = "Rate limited request",
+                        method,
+                        limit = %rule.key,
+                        client,
+                        retry_after_ms = retry_after.as_millis() as u64
+                    );
+                    if limited.is_none_or(|(_, longest)| retry_after > longest) {
+                        limited = Some((rule.key, retry_after));
+                    }
+                }
+                buckets.push(bucket);
+            }
+        }
+
+        match limited {
+            Some((limit, retry_after)) => Err(IngressError::RateLimited {
+                limit,
+                // Rounded up so retrying after the hint succeeds
+                retry_after_ms: retry_after.as_micros().div_ceil(1000) as u64,
+            }),
+            None => {
+                for bucket in buckets {
+                    bucket.lock().unwrap().take();
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// RPC middleware rejecting requests over the limits of a [`RateLimiter`], including the calls
+/// within a batch
+#[derive(Debug, Clone)]
+pub struct RateLimitLayer {
+    limiter: Arc<RateLimiter>,
+}
+
+impl RateLimitLayer {
+    pub fn new(limiter: RateLimiter) -> Self {
+        Self {
+            limiter: Arc::new(limiter),
+        }
+    }
+}
+
+impl<S> Layer<S> for RateLimitLayer {
+    type Service = RateLimit<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimit {
+            inner,
+            limiter: self.limiter.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RateLimit<S> {
+    inner: S,
+    limiter: Arc<RateLimiter>,
+}
+
+impl<S> RpcServiceT for RateLimit<S>
+where
+    S: RpcServiceT<
+            MethodResponse = MethodResponse,
+            BatchResponse = MethodResponse,
+            NotificationResponse = MethodResponse,
+        > + Send
+        + Sync
+        + Clone
+        + 'static,
+{
+    type MethodResponse = S::MethodResponse;
+    type NotificationResponse = S::NotificationResponse;
+    type BatchResponse = S::BatchResponse;
+
+    fn call<'a>(
+        &self,
+        request: Request<'a>,
+    ) -> impl Future<Output = Self::MethodResponse> + Send + 'a {
+        let inner = self.inner.clone();
+        let limiter = self.limiter.clone();
+        async move {
+            match limiter.check(&request).await {
+                Ok(()) => inner.call(request).await,
+                Err(err) => MethodResponse::error(request.id, ErrorObjectOwned::from(err)),
+            }
+        }
+    }
+
+    fn batch<'a>(
+        &self,
+        mut batch: Batch<'a>,
+    ) -> impl Future<Output = Self::BatchResponse> + Send + 'a {
+        let inner = self.inner.clone();
+        let limiter = self.limiter.clone();
+        async move {
+            for entry in batch.iter_mut() {
+                let Ok(BatchEntry::Call(request)) = entry else {
+                    continue;
+                };
+                if let Err(err) = limiter.check(request).await {
+                    let id = request.id();
+                    *entry = Err(BatchEntryErr::new(id, ErrorObjectOwned::from(err)));
+                }
+            }
+            inner.batch(batch).await
+        }
+    }
+
+    fn notification<'a>(
+        &self,
+        notification: Notification<'a>,
+    ) -> impl Future<Output = Self::NotificationResponse> + Send + 'a {
+        self.inner.notification(notification)
+    }
+}
+
+/// Starts the ingress RPC server serving HTTP and WebSocket, with authentication and the rate
+/// limits applied in front of the methods. Clients are identified by the peer address of their
+/// connection, or by the `X-Forwarded-For` header if `trust_forwarded_for` is set.
+pub async fn start_ingress_server(
+    addr: SocketAddr,
+    limiter: RateLimiter,
+    authenticator: Option<Authenticator>,
+    trust_forwarded_for: bool,
+    methods: impl Into<Methods>,
+) -> anyhow::Result<(SocketAddr, ServerHandle)> {
+    let listener = TcpListener::bind(addr).await?;
+    let bound_addr = listener.local_addr()?;
+    let methods = methods.into();
+    let (stop_handle, server_handle) = stop_channel();
+    // shared by every connection, so the rate limits apply across them
+    let service_builder = Server::builder()
+        .set_rpc_middleware(RpcServiceBuilder::new().layer(RateLimitLayer::new(limiter)))
+        .to_service_builder();
+    let auth = AuthLayer::new(authenticator);
+
+    tokio::spawn(async move {
+        loop {
+            let (stream, peer) = tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        warn!(message = "Failed to accept connection", error = %e);
+                        continue;
+                    }
+                },
+                _ = stop_handle.clone().shutdown() => break,
+            };
+            // the peer address is only known once connected, so the HTTP middleware is built
+            // for each connection
+            let service = service_builder
+                .clone()
+                .set_http_middleware(
+                    tower::ServiceBuilder::new()
+                        .layer(ClientInfoLayer::new(peer.ip(), trust_forwarded_for))
+                        .layer(auth.clone()),
+                )
+                .build(methods.clone(), stop_handle.clone());
+            tokio::spawn(serve_with_graceful_shutdown(
+                stream,
+                service,
+                stop_handle.clone().shutdown(),
+            ));
+        }
+    });
+    Ok((bound_addr, server_handle))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::RATE_LIMITED_CODE;
+    use alloy_primitives::hex;
+    use alloy_provider::network::eip2718::Encodable2718;
+    use alloy_signer_local::PrivateKeySigner;
+    use jsonrpsee::RpcModule;
+    use serde_json::{Value, json};
+    use tips_core::test_utils::create_transaction;
+
+    fn rule(s: &str) -> RateLimitRule {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse_rule() {
+        assert_eq!(
+            rule("eth_sendBundle:sender=0.5/10"),
+            RateLimitRule {
+                method: "eth_sendBundle".to_string(),
+                key: RateLimitKey::Sender,
+                per_second: 0.5,
+                burst: 10,
+            }
+        );
+        assert_eq!(
+            rule("eth_sendRawTransaction:api-key=100/200").to_string(),
+            "eth_sendRawTransaction:api-key=100/200"
+        );
+        for invalid in [
+            "eth_sendBundle",
+            "eth_sendBundle:sender=5",
+            "eth_sendBundle:account=5/10",
+            "eth_sendBundle:sender=0/10",
+            "eth_sendBundle:sender=5/0",
+            ":ip=5/10",
+        ] {
+            assert!(invalid.parse::<RateLimitRule>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_token_bucket() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::full(2, start);
+
+        assert!(bucket.try_take(1.0, 2, start).is_ok());
+        assert!(bucket.try_take(1.0, 2, start).is_ok());
+        assert_eq!(bucket.try_take(1.0, 2, start), Err(Duration::from_secs(1)));
+
+        let later = start + Duration::from_millis(500);
+        assert_eq!(
+            bucket.try_take(1.0, 2, later),
+            Err(Duration::from_millis(500))
+        );
+        assert!(
+            bucket
+                .try_take(1.0, 2, start + Duration::from_secs(1))
+                .is_ok()
+        );
+
+        // refills up to the burst only
+        let idle = start + Duration::from_secs(60);
+        assert!(bucket.try_take(1.0, 2, idle).is_ok());
+        assert!(bucket.try_take(1.0, 2, idle).is_ok());
+        assert!(bucket.try_take(1.0, 2, idle).is_err());
+    }
+
+    #[test]
+    fn test_request_senders() {
+        let alice = PrivateKeySigner::random();
+        let bob = PrivateKeySigner::random();
+        let encode = |signer: &PrivateKeySigner, nonce| {
+            hex::encode_prefixed(
+                create_transaction(signer.clone(), nonce, Address::ZERO).encoded_2718(),
+            )
+        };
+
+        let params = json!([encode(&alice, 0)]).to_string();
+        assert_eq!(
+            request_senders("eth_sendRawTransaction", Params::new(Some(&params))),
+            vec![alice.address()]
+        );
+
+        let params = json!([{
+            "txs": [encode(&alice, 0), encode(&bob, 0), encode(&alice, 1), "0x01"],
+            "blockNumber": "0x1"
+        }])
+        .to_string();
+        let mut expected = vec![alice.address(), bob.address()];
+        expected.sort();
+        assert_eq!(
+            request_senders("eth_sendBundle", Params::new(Some(&params))),
+            expected
+        );
+
+        assert!(request_senders("eth_cancelBundle", Params::new(Some(&params))).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited_by_ip() {
+        let mut module = RpcModule::new(());
+        module
+            .register_method("eth_sendRawTransaction", |_, _, _| "ok")
+            .unwrap();
+        module
+            .register_method("eth_chainId", |_, _, _| "0x1")
+            .unwrap();
+        let limiter = RateLimiter::new([rule("eth_sendRawTransaction:ip=0.001/1")]);
+        let (addr, handle) =
+            start_ingress_server("127.0.0.1:0".parse().unwrap(), limiter, None, true, module)
+                .await
+                .unwrap();
+
+        let send = |ip: &'static str, body: Value| async move {
+            reqwest::Client::new()
+                .post(format!("http://{addr}"))
+                .header("content-type", "application/json")
+                .header("x-forwarded-for", format!("10.0.0.1, {ip}"))
+                .body(body.to_string())
+                .send()
+                .await
+                .unwrap()
+                .json::<Value>()
+                .await
+                .unwrap()
+        };
+        let call =
+            |method: &str| json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": ["0x"] });
+
+        let response = send("192.168.0.1", call("eth_sendRawTransaction")).await;
+        assert_eq!(response["result"], json!("ok"));
+
+        let response = send("192.168.0.1", call("eth_sendRawTransaction")).await;
+        assert_eq!(response["error"]["code"], json!(RATE_LIMITED_CODE));
+        assert_eq!(response["error"]["data"]["limit"], json!("ip"));
+        assert!(response["error"]["data"]["retryAfterMs"].as_u64().unwrap() > 0);
+
+        // other clients and methods without limits aren't affected
+        let response = send("192.168.0.2", call("eth_sendRawTransaction")).await;
+        assert_eq!(response["result"], json!("ok"));
+        let response = send("192.168.0.1", call("eth_chainId")).await;
+        assert_eq!(response["result"], json!("0x1"));
+
+        // calls within a batch are limited one by one
+        let response = send(
+            "192.168.0.3",
+            json!([call("eth_sendRawTransaction"), {
+                "jsonrpc": "2.0", "id": 2, "method": "eth_sendRawTransaction", "params": ["0x"]
+            }]),
+        )
+        .await;
+        let mut responses = response.as_array().unwrap().clone();
+        responses.sort_by_key(|response| response["id"].as_u64());
+        assert_eq!(responses[0]["result"], json!("ok"));
+        assert_eq!(responses[1]["error"]["code"], json!(RATE_LIMITED_CODE));
+
+        handle.stop().unwrap();
+    }
+    async fn send_raw_transaction(
+        addr: SocketAddr,
+        headers: &[(&'static str, &'static str)],
+    ) -> Value {
+        let mut request = reqwest::Client::new()
+            .post(format!("http://{addr}"))
+            .header("content-type", "application/json")
+            .body(
+                json!({
+                    "jsonrpc": "2.0", "id": 1, "method": "eth_sendRawTransaction", "params": ["0x"]
+                })
+                .to_string(),
+            );
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        request.send().await.unwrap().json().await.unwrap()
+    }
+
+    fn raw_transaction_module() -> RpcModule<()> {
+        let mut module = RpcModule::new(());
+        module
+            .register_method("eth_sendRawTransaction", |_, _, _| "ok")
+            .unwrap();
+        module
+    }
+
+    #[tokio::test]
+    async fn test_untrusted_forwarded_for_is_ignored() {
+        let limiter = RateLimiter::new([rule("eth_sendRawTransaction:ip=0.001/1")]);
+        let (addr, handle) = start_ingress_server(
+            "127.0.0.1:0".parse().unwrap(),
+            limiter,
+            None,
+            false,
+            raw_transaction_module(),
+        )
+        .await
+        .unwrap();
+
+        let response = send_raw_transaction(addr, &[("x-forwarded-for", "192.168.0.1")]).await;
+        assert_eq!(response["result"], json!("ok"));
+        // limited by the peer address, whatever the client claims
+        let response = send_raw_transaction(addr, &[("x-forwarded-for", "192.168.0.2")]).await;
+        assert_eq!(response["error"]["code"], json!(RATE_LIMITED_CODE));
+
+        handle.stop().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_rejected_request_takes_no_tokens() {
+        let limiter = RateLimiter::new([
+            rule("eth_sendRawTransaction:ip=0.001/2"),
+            rule("eth_sendRawTransaction:api-key=0.001/1"),
+        ]);
+        let (addr, handle) = start_ingress_server(
+            "127.0.0.1:0".parse().unwrap(),
+            limiter,
+            None,
+            false,
+            raw_transaction_module(),
+        )
+        .await
+        .unwrap();
+
+        let response = send_raw_transaction(addr, &[("x-api-key", "key")]).await;
+        assert_eq!(response["result"], json!("ok"));
+        let response = send_raw_transaction(addr, &[("x-api-key", "key")]).await;
+        assert_eq!(response["error"]["data"]["limit"], json!("api-key"));
+        // the rejected request left the IP bucket's second token
+        let response = send_raw_transaction(addr, &[]).await;
+        assert_eq!(response["result"], json!("ok"));
+
+        handle.stop().unwrap();
+    }
+}
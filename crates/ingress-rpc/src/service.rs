@@ -5,29 +5,51 @@ use account_abstraction_core::services::interfaces::user_op_validator::UserOpera
 use account_abstraction_core::{Mempool, MempoolEngine};
 use alloy_consensus::transaction::Recovered;
 use alloy_consensus::{Transaction, transaction::SignerRecoverable};
-use alloy_primitives::{Address, B256, Bytes, FixedBytes};
+use alloy_op_hardforks::OpChainHardforks;
+use alloy_primitives::{Address, B256, Bytes, FixedBytes, hex};
 use alloy_provider::{Provider, RootProvider, network::eip2718::Decodable2718};
+use alloy_transport::TransportError;
 use base_reth_rpc_types::EthApiError;
+use futures::future::try_join_all;
+use hmac::{Hmac, Mac};
 use jsonrpsee::{
     core::{RpcResult, async_trait},
     proc_macros::rpc,
+    types::Extensions,
 };
 use moka::future::Cache;
 use op_alloy_consensus::OpTxEnvelope;
 use op_alloy_network::Optimism;
+use sha2::Sha256;
+use std::collections::{HashMap, HashSet};
+use std::iter;
 use std::time::{SystemTime, UNIX_EPOCH};
-use tips_audit_lib::BundleEvent;
+use tips_audit_lib::{AuditSender, BundleEvent, IngressMetadata};
 use tips_core::types::ParsedBundle;
 use tips_core::{
-    AcceptedBundle, Bundle, BundleExtensions, BundleHash, CancelBundle, MeterBundleResponse,
+    AcceptedBundle, Bundle, BundleExtensions, BundleHash, BundleVerdict, CallBundleResponse,
+    CancelBundle, CancelledBundle, MeterBundleResponse,
 };
-use tokio::sync::{broadcast, mpsc};
+use tokio::sync::broadcast;
 use tokio::time::{Duration, Instant, timeout};
 use tracing::{debug, info, warn};
-
-use crate::metrics::{Metrics, record_histogram};
+use uuid::Uuid;
+
+use crate::admin::RuntimeControls;
+use crate::auth::Caller;
+use crate::builder::broadcast_to_builders;
+use crate::errors::IngressError;
+use crate::metering_cache::MeteringCache;
+use crate::metrics::{Metrics, record_tx_type};
 use crate::queue::{BundleQueuePublisher, MessageQueue, UserOpQueuePublisher};
-use crate::validation::validate_bundle;
+use crate::rate_limit::ClientInfo;
+use crate::simulation::SimulationPool;
+use crate::tracker::{BundleTracker, CancelBundleError, LiveBundle};
+use crate::validation::{
+    AccountInfo, AccountInfoLookup, BundleKind, BundleValidationPolicy, HeadL1BlockInfo,
+    L1BlockInfoLookup, TxType, TxValidationError, chain_hardforks, l1_fee_spec,
+    validate_bundle_txs, validate_chain_id, validate_revert_protection,
+};
 use crate::{Config, TxSubmissionMethod};
 use account_abstraction_core::domain::entrypoints::version::EntryPointVersion;
 use account_abstraction_core::domain::types::{UserOperationRequest, VersionedUserOperation};
@@ -37,24 +59,30 @@ use std::sync::Arc;
 pub struct Providers {
     pub mempool: RootProvider<Optimism>,
     pub simulation: RootProvider<Optimism>,
+    /// Fallback simulation nodes for metering and user operation validation, in order of preference
+    pub simulation_fallbacks: Vec<RootProvider<Optimism>>,
     pub raw_tx_forward: Option<RootProvider<Optimism>>,
 }
 
 #[rpc(server, namespace = "eth")]
 pub trait IngressApi {
     /// `eth_sendBundle` can be used to send your bundles to the builder.
-    #[method(name = "sendBundle")]
+    #[method(name = "sendBundle", with_extensions)]
     async fn send_bundle(&self, bundle: Bundle) -> RpcResult<BundleHash>;
 
-    #[method(name = "sendBackrunBundle")]
+    #[method(name = "sendBackrunBundle", with_extensions)]
     async fn send_backrun_bundle(&self, bundle: Bundle) -> RpcResult<BundleHash>;
 
+    /// `eth_callBundle` meters a bundle without queueing it or sending it to the builder.
+    #[method(name = "callBundle")]
+    async fn call_bundle(&self, bundle: Bundle) -> RpcResult<CallBundleResponse>;
+
     /// `eth_cancelBundle` is used to prevent a submitted bundle from being included on-chain.
-    #[method(name = "cancelBundle")]
+    #[method(name = "cancelBundle", with_extensions)]
     async fn cancel_bundle(&self, request: CancelBundle) -> RpcResult<()>;
 
     /// Handler for: `eth_sendRawTransaction`
-    #[method(name = "sendRawTransaction")]
+    #[method(name = "sendRawTransaction", with_extensions)]
     async fn send_raw_transaction(&self, tx: Bytes) -> RpcResult<B256>;
 
     /// Handler for: `eth_sendUserOperation`
@@ -69,31 +97,37 @@ pub trait IngressApi {
 pub struct IngressService<Q: MessageQueue, M: Mempool> {
     mempool_provider: Arc<RootProvider<Optimism>>,
     simulation_provider: Arc<RootProvider<Optimism>>,
+    simulation_pool: Arc<SimulationPool>,
     raw_tx_forward_provider: Option<Arc<RootProvider<Optimism>>>,
     user_op_validator: BaseNodeValidator,
-    tx_submission_method: TxSubmissionMethod,
     bundle_queue_publisher: BundleQueuePublisher<Q>,
     user_op_queue_publisher: UserOpQueuePublisher<Q>,
     reputation_service: Option<Arc<ReputationServiceImpl<M>>>,
-    audit_channel: mpsc::UnboundedSender<BundleEvent>,
+    audit_channel: AuditSender,
     send_transaction_default_lifetime_seconds: u64,
     metrics: Metrics,
     block_time_milliseconds: u64,
-    meter_bundle_timeout_ms: u64,
     builder_tx: broadcast::Sender<MeterBundleResponse>,
-    backrun_enabled: bool,
     builder_backrun_tx: broadcast::Sender<AcceptedBundle>,
-    max_backrun_txs: usize,
-    max_backrun_gas_limit: u64,
+    validation_policy: BundleValidationPolicy,
+    validate_account_state: bool,
+    chain_id: u64,
+    hardforks: Option<OpChainHardforks>,
+    allow_unprotected_txs: bool,
     bundle_cache: Cache<B256, ()>,
-    send_to_builder: bool,
+    metering_cache: Arc<MeteringCache>,
+    bundle_tracker: BundleTracker,
+    replacement_fee_bump_percent: u64,
+    controls: Arc<RuntimeControls>,
+    instance_id: String,
+    client_ip_hash_key: Option<String>,
 }
 
 impl<Q: MessageQueue, M: Mempool> IngressService<Q, M> {
     pub fn new(
         providers: Providers,
         queue: Q,
-        audit_channel: mpsc::UnboundedSender<BundleEvent>,
+        audit_channel: AuditSender,
         builder_tx: broadcast::Sender<MeterBundleResponse>,
         builder_backrun_tx: broadcast::Sender<AcceptedBundle>,
         mempool_engine: impl Into<Option<Arc<MempoolEngine<M>>>>,
@@ -101,10 +135,16 @@ impl<Q: MessageQueue, M: Mempool> IngressService<Q, M> {
     ) -> Self {
         let mempool_engine = mempool_engine.into();
         let mempool_provider = Arc::new(providers.mempool);
+        let simulation_pool = Arc::new(SimulationPool::new(
+            iter::once(providers.simulation.clone()).chain(providers.simulation_fallbacks),
+            config.simulation_hedge_delay_ms.map(Duration::from_millis),
+            config.simulation_failure_threshold,
+            Duration::from_millis(config.simulation_cooldown_ms),
+        ));
         let simulation_provider = Arc::new(providers.simulation);
         let raw_tx_forward_provider = providers.raw_tx_forward.map(Arc::new);
         let user_op_validator = BaseNodeValidator::new(
-            simulation_provider.clone(),
+            simulation_pool.clone(),
             config.validate_user_operation_timeout_ms,
         );
         let queue_connection = Arc::new(queue);
@@ -116,12 +156,19 @@ impl<Q: MessageQueue, M: Mempool> IngressService<Q, M> {
         let bundle_cache = Cache::builder()
             .time_to_live(Duration::from_secs(config.bundle_cache_ttl))
             .build();
+
+        // Pods get their name as hostname, so received events can be traced back to one
+        let instance_id = config
+            .instance_id
+            .clone()
+            .or_else(|| std::env::var("HOSTNAME").ok())
+            .unwrap_or_else(|| Uuid::new_v4().to_string());
         Self {
             mempool_provider,
             simulation_provider,
+            simulation_pool,
             raw_tx_forward_provider,
             user_op_validator,
-            tx_submission_method: config.tx_submission_method,
             user_op_queue_publisher: UserOpQueuePublisher::new(
                 queue_connection.clone(),
                 config.user_operation_topic,
@@ -136,75 +183,70 @@ impl<Q: MessageQueue, M: Mempool> IngressService<Q, M> {
                 .send_transaction_default_lifetime_seconds,
             metrics: Metrics::default(),
             block_time_milliseconds: config.block_time_milliseconds,
-            meter_bundle_timeout_ms: config.meter_bundle_timeout_ms,
             builder_tx,
-            backrun_enabled: config.backrun_enabled,
             builder_backrun_tx,
-            max_backrun_txs: config.max_backrun_txs,
-            max_backrun_gas_limit: config.max_backrun_gas_limit,
+            validation_policy: BundleValidationPolicy::from(&config),
+            validate_account_state: config.validate_account_state,
+            chain_id: config.chain_id,
+            hardforks: chain_hardforks(config.chain_id),
+            allow_unprotected_txs: config.allow_unprotected_txs,
             bundle_cache,
-            send_to_builder: config.send_to_builder,
+            metering_cache: Arc::new(MeteringCache::new(
+                config.metering_cache_size,
+                Duration::from_millis(config.block_time_milliseconds),
+            )),
+            bundle_tracker: BundleTracker::new(Duration::from_secs(config.live_bundle_ttl)),
+            replacement_fee_bump_percent: config.replacement_fee_bump_percent,
+            controls: Arc::new(RuntimeControls::from(&config)),
+            instance_id,
+            client_ip_hash_key: config.client_ip_hash_key,
         }
     }
-}
 
-fn validate_backrun_bundle_limits(
-    txs_count: usize,
-    total_gas_limit: u64,
-    max_backrun_txs: usize,
-    max_backrun_gas_limit: u64,
-) -> Result<(), String> {
-    if txs_count < 2 {
-        return Err(
-            "Backrun bundle must have at least 2 transactions (target + backrun)".to_string(),
-        );
+    /// Settings of the service that can be changed at runtime
+    pub fn runtime_controls(&self) -> Arc<RuntimeControls> {
+        self.controls.clone()
     }
-    if txs_count > max_backrun_txs {
-        return Err(format!(
-            "Backrun bundle exceeds max transaction count: {txs_count} > {max_backrun_txs}",
-        ));
+
+    /// Cache deduplicating raw transactions
+    pub fn bundle_cache(&self) -> Cache<B256, ()> {
+        self.bundle_cache.clone()
     }
-    if total_gas_limit > max_backrun_gas_limit {
-        return Err(format!(
-            "Backrun bundle exceeds max gas limit: {total_gas_limit} > {max_backrun_gas_limit}",
-        ));
+
+    /// Cache of metering results, to invalidate when the simulation node's state changes
+    pub fn metering_cache(&self) -> Arc<MeteringCache> {
+        self.metering_cache.clone()
     }
-    Ok(())
 }
 
 #[async_trait]
 impl<Q: MessageQueue + 'static, M: Mempool + 'static> IngressApiServer for IngressService<Q, M> {
-    async fn send_backrun_bundle(&self, bundle: Bundle) -> RpcResult<BundleHash> {
-        if !self.backrun_enabled {
-            return Err(
-                EthApiError::InvalidParams("Backrun bundle submission is disabled".into())
-                    .into_rpc_err(),
-            );
+    async fn send_backrun_bundle(&self, ext: &Extensions, bundle: Bundle) -> RpcResult<BundleHash> {
+        let metadata = self.ingress_metadata("eth_sendBackrunBundle", ext);
+        let _in_flight = self.controls.start_submission()?;
+        if !self.controls.backrun_enabled() {
+            return Err(IngressError::BackrunDisabled.into());
+        }
+        let caller = Caller::from_extensions(ext);
+        if !caller.tier.backrun {
+            return Err(IngressError::BackrunNotAllowed {
+                tier: caller.tier.name,
+            }
+            .into());
         }
 
         let start = Instant::now();
-        let (accepted_bundle, bundle_hash) =
-            self.validate_parse_and_meter_bundle(&bundle, false).await?;
-
-        let total_gas_limit: u64 = accepted_bundle.txs.iter().map(|tx| tx.gas_limit()).sum();
-        validate_backrun_bundle_limits(
-            accepted_bundle.txs.len(),
-            total_gas_limit,
-            self.max_backrun_txs,
-            self.max_backrun_gas_limit,
-        )
-        .map_err(|e| EthApiError::InvalidParams(e).into_rpc_err())?;
+        let (accepted_bundle, bundle_hash) = self
+            .validate_parse_and_meter_bundle(&bundle, BundleKind::Backrun, false)
+            .await?;
+        let accepted_bundle = accepted_bundle.with_identity(caller.identity);
 
         self.metrics.backrun_bundles_received_total.increment(1);
 
-        self.builder_backrun_tx
-            .send(accepted_bundle.clone())
-            .map_err(|e| {
-                EthApiError::InvalidParams(format!("Failed to send backrun bundle: {e}"))
-                    .into_rpc_err()
-            })?;
+        broadcast_to_builders(&self.builder_backrun_tx, accepted_bundle.clone());
 
-        self.send_audit_event(&accepted_bundle, bundle_hash);
+        self.send_audit_event(&accepted_bundle, bundle_hash, metadata)
+            .await;
 
         self.metrics
             .backrun_bundles_sent_duration
@@ -213,17 +255,28 @@ impl<Q: MessageQueue + 'static, M: Mempool + 'static> IngressApiServer for Ingre
         Ok(BundleHash { bundle_hash })
     }
 
-    async fn send_bundle(&self, bundle: Bundle) -> RpcResult<BundleHash> {
-        let (accepted_bundle, bundle_hash) =
-            self.validate_parse_and_meter_bundle(&bundle, true).await?;
+    async fn send_bundle(&self, ext: &Extensions, bundle: Bundle) -> RpcResult<BundleHash> {
+        let metadata = self.ingress_metadata("eth_sendBundle", ext);
+        let _in_flight = self.controls.start_submission()?;
+        let (accepted_bundle, bundle_hash) = self
+            .validate_parse_and_meter_bundle(&bundle, BundleKind::Bundle, true)
+            .await?;
+        let accepted_bundle = accepted_bundle.with_identity(Caller::from_extensions(ext).identity);
+
+        // track the bundle so it can be replaced or cancelled while it's still live. A bundle sent
+        // with the replacement UUID of a live bundle supersedes it, only its owner can send one
+        let version = self
+            .bundle_tracker
+            .reserve(&accepted_bundle)
+            .await
+            .map_err(CancelBundleError::into_rpc_err)?;
+        let accepted_bundle = accepted_bundle.with_version(version);
 
         // Get meter_bundle_response for builder broadcast
         let meter_bundle_response = accepted_bundle.meter_bundle_response.clone();
 
         // asynchronously send the meter bundle response to the builder
-        self.builder_tx
-            .send(meter_bundle_response)
-            .map_err(|e| EthApiError::InvalidParams(e.to_string()).into_rpc_err())?;
+        broadcast_to_builders(&self.builder_tx, meter_bundle_response);
 
         // publish the bundle to the queue
         if let Err(e) = self
@@ -232,40 +285,142 @@ impl<Q: MessageQueue + 'static, M: Mempool + 'static> IngressApiServer for Ingre
             .await
         {
             warn!(message = "Failed to publish bundle to queue", bundle_hash = %bundle_hash, error = %e);
-            return Err(EthApiError::InvalidParams("Failed to queue bundle".into()).into_rpc_err());
+            return Err(IngressError::QueueUnavailable("bundle".into()).into());
         }
 
         info!(
             message = "queued bundle",
             bundle_hash = %bundle_hash,
+            bundle_id = %accepted_bundle.uuid(),
+            version = accepted_bundle.version,
         );
 
-        // asynchronously send the audit event to the audit channel
-        self.send_audit_event(&accepted_bundle, bundle_hash);
+        if accepted_bundle.is_replacement() {
+            self.metrics.bundles_replaced.increment(1);
+        }
+
+        // send the audit event to the audit channel, waits while it is full with the block policy
+        self.send_audit_event(&accepted_bundle, bundle_hash, metadata)
+            .await;
 
         Ok(BundleHash { bundle_hash })
     }
 
-    async fn cancel_bundle(&self, _request: CancelBundle) -> RpcResult<()> {
-        warn!(
-            message = "TODO: implement cancel_bundle",
-            method = "cancel_bundle"
+    async fn call_bundle(&self, bundle: Bundle) -> RpcResult<CallBundleResponse> {
+        self.validate_bundle(&bundle, BundleKind::Bundle).await?;
+        let parsed_bundle: ParsedBundle = bundle
+            .clone()
+            .try_into()
+            .map_err(|e: String| EthApiError::InvalidParams(e).into_rpc_err())?;
+        let bundle_hash = parsed_bundle.bundle_hash();
+
+        let meter_bundle_response = self.request_metering(&bundle, &bundle_hash).await?;
+        let verdicts = [
+            self.check_execution_time(&meter_bundle_response),
+            validate_revert_protection(
+                &parsed_bundle.protected_tx_hashes(),
+                &meter_bundle_response,
+            ),
+        ]
+        .into_iter()
+        .filter_map(Result::err)
+        .map(|err| BundleVerdict {
+            code: err.code(),
+            message: err.to_string(),
+        })
+        .collect();
+
+        self.metrics.bundles_called.increment(1);
+        debug!(message = "simulated bundle", bundle_hash = %bundle_hash);
+
+        Ok(CallBundleResponse {
+            meter_bundle_response,
+            verdicts,
+        })
+    }
+
+    async fn cancel_bundle(&self, ext: &Extensions, request: CancelBundle) -> RpcResult<()> {
+        let uuid = Uuid::parse_str(&request.replacement_uuid).map_err(|_| {
+            CancelBundleError::InvalidUuid(request.replacement_uuid.clone()).into_rpc_err()
+        })?;
+
+        let live_bundle = self
+            .bundle_tracker
+            .get(&uuid)
+            .await
+            .ok_or_else(|| CancelBundleError::UnknownBundle(uuid).into_rpc_err())?;
+
+        let caller = Caller::from_extensions(ext);
+        if !live_bundle.owner.may_cancel(caller.identity.as_ref()) {
+            return Err(CancelBundleError::NotOwner(uuid).into_rpc_err());
+        }
+
+        if self.is_bundle_included(&live_bundle).await {
+            self.bundle_tracker.remove(&uuid).await;
+            return Err(CancelBundleError::AlreadyIncluded(uuid).into_rpc_err());
+        }
+
+        let cancellation = CancelledBundle {
+            uuid,
+            bundle_hash: live_bundle.bundle_hash,
+        };
+        if let Err(e) = self
+            .bundle_queue_publisher
+            .publish_cancellation(&cancellation)
+            .await
+        {
+            warn!(message = "Failed to publish bundle cancellation to queue", bundle_id = %uuid, error = %e);
+            return Err(IngressError::QueueUnavailable("bundle cancellation".into()).into());
+        }
+
+        self.bundle_tracker.remove(&uuid).await;
+        self.metrics.bundles_cancelled.increment(1);
+
+        info!(
+            message = "cancelled bundle",
+            bundle_id = %uuid,
+            bundle_hash = %live_bundle.bundle_hash,
         );
-        todo!("implement cancel_bundle")
+
+        if let Err(e) = self
+            .audit_channel
+            .send(BundleEvent::Cancelled { bundle_id: uuid })
+            .await
+        {
+            warn!(
+                message = "failed to send audit event",
+                bundle_id = %uuid,
+                error = %e
+            );
+        }
+
+        Ok(())
     }
 
-    async fn send_raw_transaction(&self, data: Bytes) -> RpcResult<B256> {
+    async fn send_raw_transaction(&self, ext: &Extensions, data: Bytes) -> RpcResult<B256> {
+        let metadata = self.ingress_metadata("eth_sendRawTransaction", ext);
+        let _in_flight = self.controls.start_submission()?;
         let start = Instant::now();
         let transaction = self.get_tx(&data).await?;
+        self.validate_tx_type(&transaction, BundleKind::RawTransaction)?;
+        self.validate_chain_id(&transaction)?;
+        self.validation_policy
+            .validate_intrinsic(transaction.inner(), &data)
+            .map_err(TxValidationError::into_rpc_err)?;
+        self.validation_policy
+            .validate_limits(BundleKind::RawTransaction, &[transaction.gas_limit()])?;
+        self.validate_txs(&[(transaction.clone(), data.clone())])
+            .await?;
 
         self.metrics.transactions_received.increment(1);
 
+        let tx_submission_method = self.controls.tx_submission_method();
         let send_to_kafka = matches!(
-            self.tx_submission_method,
+            tx_submission_method,
             TxSubmissionMethod::Kafka | TxSubmissionMethod::MempoolAndKafka
         );
         let send_to_mempool = matches!(
-            self.tx_submission_method,
+            tx_submission_method,
             TxSubmissionMethod::Mempool | TxSubmissionMethod::MempoolAndKafka
         );
 
@@ -302,7 +457,7 @@ impl<Q: MessageQueue + 'static, M: Mempool + 'static> IngressApiServer for Ingre
             ..Default::default()
         };
 
-        let parsed_bundle: ParsedBundle = bundle
+        let mut parsed_bundle: ParsedBundle = bundle
             .clone()
             .try_into()
             .map_err(|e: String| EthApiError::InvalidParams(e).into_rpc_err())?;
@@ -316,6 +471,30 @@ impl<Q: MessageQueue + 'static, M: Mempool + 'static> IngressApiServer for Ingre
                 transaction_hash = %transaction.tx_hash(),
             );
         } else {
+            // a transaction with the same sender and nonce as a live one replaces its bundle.
+            // Only transactions published to Kafka can be replaced, their version is reserved
+            // before publishing so concurrent replacements get distinct versions
+            let reservation = if send_to_kafka {
+                let bundle_id = Uuid::new_v5(&Uuid::NAMESPACE_OID, bundle_hash.as_slice());
+                Some(
+                    self.bundle_tracker
+                        .reserve_transaction(
+                            &transaction,
+                            bundle_id,
+                            self.replacement_fee_bump_percent,
+                        )
+                        .await?,
+                )
+            } else {
+                None
+            };
+            let replaced = reservation
+                .as_ref()
+                .and_then(|reservation| reservation.replaced.as_ref());
+            if let Some(live_transaction) = replaced {
+                parsed_bundle.replacement_uuid = Some(live_transaction.bundle_id);
+            }
+
             self.bundle_cache.insert(*bundle_hash, ()).await;
             self.metrics.bundles_parsed.increment(1);
 
@@ -336,23 +515,49 @@ impl<Q: MessageQueue + 'static, M: Mempool + 'static> IngressApiServer for Ingre
 
             if let Some(meter_info) = meter_bundle_response.as_ref() {
                 self.metrics.successful_simulations.increment(1);
-                if self.send_to_builder {
-                    _ = self.builder_tx.send(meter_info.clone());
+                if self.controls.send_to_builder() {
+                    broadcast_to_builders(&self.builder_tx, meter_info.clone());
                 }
             } else {
                 self.metrics.failed_simulations.increment(1);
             }
 
             let accepted_bundle =
-                AcceptedBundle::new(parsed_bundle, meter_bundle_response.unwrap_or_default());
+                AcceptedBundle::new(parsed_bundle, meter_bundle_response.unwrap_or_default())
+                    .with_version(
+                        reservation
+                            .as_ref()
+                            .map_or(0, |reservation| reservation.reserved.version),
+                    )
+                    .with_identity(Caller::from_extensions(ext).identity);
+
+            if let Some(live_transaction) = replaced {
+                self.metrics.bundles_replaced.increment(1);
+                info!(
+                    message = "replacing transaction",
+                    bundle_id = %accepted_bundle.uuid(),
+                    replaced_transaction_hash = %live_transaction.tx_hash,
+                    transaction_hash = %transaction.tx_hash(),
+                    version = accepted_bundle.version,
+                );
+            }
 
             if send_to_kafka {
-                if let Err(e) = self
+                match self
                     .bundle_queue_publisher
                     .publish(&accepted_bundle, bundle_hash)
                     .await
                 {
-                    warn!(message = "Failed to publish Queue::enqueue_bundle", bundle_hash = %bundle_hash, error = %e);
+                    Ok(()) => self.bundle_tracker.track(&accepted_bundle).await,
+                    Err(e) => {
+                        warn!(message = "Failed to publish Queue::enqueue_bundle", bundle_hash = %bundle_hash, error = %e);
+                        // only published transactions can be replaced, like bundles
+                        if let Some(reservation) = reservation {
+                            self.bundle_tracker
+                                .release_transaction(&transaction, reservation)
+                                .await;
+                        }
+                    }
                 }
 
                 self.metrics.sent_to_kafka.increment(1);
@@ -381,7 +586,8 @@ impl<Q: MessageQueue + 'static, M: Mempool + 'static> IngressApiServer for Ingre
                 transaction_hash = %transaction.tx_hash(),
             );
 
-            self.send_audit_event(&accepted_bundle, accepted_bundle.bundle_hash());
+            self.send_audit_event(&accepted_bundle, accepted_bundle.bundle_hash(), metadata)
+                .await;
         }
 
         self.metrics
@@ -396,6 +602,7 @@ impl<Q: MessageQueue + 'static, M: Mempool + 'static> IngressApiServer for Ingre
         rpc_user_operation: VersionedUserOperation,
         entry_point: Address,
     ) -> RpcResult<FixedBytes<32>> {
+        let _in_flight = self.controls.start_submission()?;
         let entry_point_version = EntryPointVersion::try_from(entry_point).map_err(|_| {
             EthApiError::InvalidParams("Unknown entry point version".into()).into_rpc_err()
         })?;
@@ -418,7 +625,7 @@ impl<Q: MessageQueue + 'static, M: Mempool + 'static> IngressApiServer for Ingre
         let request = UserOperationRequest {
             user_operation: versioned_user_operation,
             entry_point,
-            chain_id: 1,
+            chain_id: self.chain_id,
         };
 
         if let Some(reputation_service) = &self.reputation_service {
@@ -451,9 +658,7 @@ impl<Q: MessageQueue + 'static, M: Mempool + 'static> IngressApiServer for Ingre
                 user_operation_hash = %user_op_hash,
                 error = %e
             );
-            return Err(
-                EthApiError::InvalidParams("Failed to queue user operation".into()).into_rpc_err(),
-            );
+            return Err(IngressError::QueueUnavailable("user operation".into()).into());
         }
 
         Ok(user_op_hash)
@@ -476,7 +681,7 @@ impl<Q: MessageQueue, M: Mempool> IngressService<Q, M> {
         Ok(transaction)
     }
 
-    async fn validate_bundle(&self, bundle: &Bundle) -> RpcResult<()> {
+    async fn validate_bundle(&self, bundle: &Bundle, kind: BundleKind) -> RpcResult<()> {
         let start = Instant::now();
         if bundle.txs.is_empty() {
             return Err(
@@ -485,14 +690,23 @@ impl<Q: MessageQueue, M: Mempool> IngressService<Q, M> {
             );
         }
 
-        let mut total_gas = 0u64;
+        let mut tx_gas_limits = Vec::new();
         let mut tx_hashes = Vec::new();
+        let mut txs = Vec::new();
         for tx_data in &bundle.txs {
             let transaction = self.get_tx(tx_data).await?;
-            total_gas = total_gas.saturating_add(transaction.gas_limit());
+            self.validate_tx_type(&transaction, kind)?;
+            self.validate_chain_id(&transaction)?;
+            self.validation_policy
+                .validate_intrinsic(transaction.inner(), tx_data)
+                .map_err(TxValidationError::into_rpc_err)?;
+            tx_gas_limits.push(transaction.gas_limit());
             tx_hashes.push(transaction.tx_hash());
+            txs.push((transaction, tx_data.clone()));
         }
-        validate_bundle(bundle, total_gas, tx_hashes)?;
+        self.validation_policy
+            .validate_bundle(kind, bundle, &tx_gas_limits, tx_hashes)?;
+        self.validate_txs(&txs).await?;
 
         self.metrics
             .validate_bundle_duration
@@ -500,6 +714,63 @@ impl<Q: MessageQueue, M: Mempool> IngressService<Q, M> {
         Ok(())
     }
 
+    fn validate_tx_type(
+        &self,
+        transaction: &Recovered<OpTxEnvelope>,
+        kind: BundleKind,
+    ) -> RpcResult<()> {
+        let result = self
+            .validation_policy
+            .validate_tx_type(kind, transaction.inner());
+        record_tx_type(
+            TxType::from(transaction.inner()).to_string(),
+            result.is_ok(),
+        );
+        result.map_err(TxValidationError::into_rpc_err)
+    }
+
+    fn validate_chain_id(&self, transaction: &Recovered<OpTxEnvelope>) -> RpcResult<()> {
+        validate_chain_id(
+            transaction.inner(),
+            self.chain_id,
+            self.allow_unprotected_txs,
+        )
+        .map_err(TxValidationError::into_rpc_err)
+    }
+
+    /// Checks transactions against their senders' account state and the L1 data fee, so txs that
+    /// can never land are rejected before they are metered
+    async fn validate_txs(&self, txs: &[(Recovered<OpTxEnvelope>, Bytes)]) -> RpcResult<()> {
+        if !self.validate_account_state {
+            return Ok(());
+        }
+
+        let start = Instant::now();
+        // Each distinct sender is looked up once, concurrently with the L1 block info
+        let senders: HashSet<Address> = txs.iter().map(|(tx, _)| tx.signer()).collect();
+        let (head, accounts) = tokio::try_join!(
+            self.simulation_provider.fetch_l1_block_info(),
+            try_join_all(senders.into_iter().map(|sender| async move {
+                self.simulation_provider
+                    .fetch_account_info(sender)
+                    .await
+                    .map(|account| (sender, account))
+            })),
+        )?;
+        let accounts: HashMap<Address, AccountInfo> = accounts.into_iter().collect();
+        let HeadL1BlockInfo {
+            mut l1_block_info,
+            timestamp,
+        } = head;
+        let spec_id = l1_fee_spec(self.hardforks.as_ref(), timestamp);
+        validate_bundle_txs(&accounts, txs, &mut l1_block_info, spec_id)
+            .map_err(TxValidationError::into_rpc_err)?;
+        self.metrics
+            .validate_tx_duration
+            .record(start.elapsed().as_secs_f64());
+        Ok(())
+    }
+
     /// `meter_bundle` is used to determine how long a bundle will take to execute. A bundle that
     /// is within `block_time_milliseconds` will return the `MeterBundleResponse` that can be passed along
     /// to the builder.
@@ -508,55 +779,93 @@ impl<Q: MessageQueue, M: Mempool> IngressService<Q, M> {
         bundle: &Bundle,
         bundle_hash: &B256,
     ) -> RpcResult<MeterBundleResponse> {
-        let start = Instant::now();
-        let timeout_duration = Duration::from_millis(self.meter_bundle_timeout_ms);
+        let res = self.request_metering(bundle, bundle_hash).await?;
+
+        // we can save some builder payload building computation by not including bundles
+        // that we know will take longer than the block time to execute
+        self.check_execution_time(&res)
+            .inspect_err(|_| self.metrics.bundles_exceeded_metering_time.increment(1))?;
+        Ok(res)
+    }
+
+    /// Requests `base_meterBundle` from the simulation node, within `meter_bundle_timeout_ms`.
+    /// Bundles already metered against the latest known state are served from the cache.
+    async fn request_metering(
+        &self,
+        bundle: &Bundle,
+        bundle_hash: &B256,
+    ) -> Result<MeterBundleResponse, IngressError> {
+        if let Some(res) = self.metering_cache.get(bundle).await {
+            self.metrics.metering_cache_hits.increment(1);
+            return Ok(res);
+        }
+        self.metrics.metering_cache_misses.increment(1);
+        let generation = self.metering_cache.generation();
+
+        let timeout_ms = self.controls.meter_bundle_timeout_ms();
+        let timeout_duration = Duration::from_millis(timeout_ms);
 
         // The future we await has the nested type:
         // Result<
-        //   RpcResult<MeterBundleResponse>, // 1. The inner operation's result
-        //   tokio::time::error::Elapsed     // 2. The outer timeout's result
+        //   anyhow::Result<MeterBundleResponse>, // 1. The inner operation's result
+        //   tokio::time::error::Elapsed          // 2. The outer timeout's result
         // >
         let res: MeterBundleResponse = timeout(
             timeout_duration,
-            self.simulation_provider
-                .client()
-                .request("base_meterBundle", (bundle,)),
+            self.simulation_pool.request("base_meterBundle", (bundle,)),
         )
         .await
         .map_err(|_| {
             warn!(message = "Timed out on requesting metering", bundle_hash = %bundle_hash);
-            EthApiError::InvalidParams("Timeout on requesting metering".into()).into_rpc_err()
+            IngressError::MeteringTimeout { timeout_ms }
         })?
-        .map_err(|e| EthApiError::InvalidParams(e.to_string()).into_rpc_err())?;
+        .map_err(|e| {
+            // The node answered, but rejected the bundle: retrying it unchanged fails again
+            match e
+                .downcast_ref::<TransportError>()
+                .and_then(TransportError::as_error_resp)
+            {
+                Some(payload) => IngressError::InvalidBundle(payload.message.to_string()),
+                None => IngressError::MeteringUnavailable(e.to_string()),
+            }
+        })?;
 
-        record_histogram(start.elapsed(), "base_meterBundle".to_string());
+        self.metering_cache
+            .insert(bundle, generation, res.clone())
+            .await;
+        Ok(res)
+    }
 
-        // we can save some builder payload building computation by not including bundles
-        // that we know will take longer than the block time to execute
+    /// Rejects a metered bundle that takes longer than the block time to execute
+    fn check_execution_time(&self, res: &MeterBundleResponse) -> Result<(), IngressError> {
         let total_execution_time = (res.total_execution_time_us / 1_000) as u64;
         if total_execution_time > self.block_time_milliseconds {
-            self.metrics.bundles_exceeded_metering_time.increment(1);
-            return Err(
-                EthApiError::InvalidParams("Bundle simulation took too long".into()).into_rpc_err(),
-            );
+            return Err(IngressError::ExecutionTimeExceeded {
+                execution_time_us: res.total_execution_time_us as u64,
+                limit_us: self.block_time_milliseconds * 1_000,
+            });
         }
-        Ok(res)
+        Ok(())
     }
 
     /// Helper method to validate, parse, and meter a bundle
     async fn validate_parse_and_meter_bundle(
         &self,
         bundle: &Bundle,
+        kind: BundleKind,
         to_meter: bool,
     ) -> RpcResult<(AcceptedBundle, B256)> {
-        self.validate_bundle(bundle).await?;
+        self.validate_bundle(bundle, kind).await?;
         let parsed_bundle: ParsedBundle = bundle
             .clone()
             .try_into()
             .map_err(|e: String| EthApiError::InvalidParams(e).into_rpc_err())?;
         let bundle_hash = parsed_bundle.bundle_hash();
         let meter_bundle_response = if to_meter {
-            self.meter_bundle(bundle, &bundle_hash).await?
+            let response = self.meter_bundle(bundle, &bundle_hash).await?;
+            validate_revert_protection(&parsed_bundle.protected_tx_hashes(), &response)
+                .inspect_err(|_| self.metrics.bundles_rejected_reverted.increment(1))?;
+            response
         } else {
             MeterBundleResponse::default()
         };
@@ -564,13 +873,76 @@ impl<Q: MessageQueue, M: Mempool> IngressService<Q, M> {
         Ok((accepted_bundle, bundle_hash))
     }
 
+    /// Best-effort check whether any of the bundle's transactions already has a receipt. Lookup
+    /// failures are treated as not included, so the cancellation still goes through.
+    async fn is_bundle_included(&self, live_bundle: &LiveBundle) -> bool {
+        for tx_hash in &live_bundle.tx_hashes {
+            match self
+                .simulation_provider
+                .get_transaction_receipt(*tx_hash)
+                .await
+            {
+                Ok(Some(_)) => return true,
+                Ok(None) => {}
+                Err(e) => {
+                    warn!(message = "Failed to fetch transaction receipt", tx_hash = %tx_hash, error = %e);
+                }
+            }
+        }
+        false
+    }
+
+    /// How a request arrived at this instance, recorded on the received and updated bundle events
+    fn ingress_metadata(&self, method: &str, ext: &Extensions) -> IngressMetadata {
+        let received_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as i64;
+        let client = ClientInfo::from_extensions(ext);
+        let client_ip_hash = client
+            .ip
+            .zip(self.client_ip_hash_key.as_ref())
+            .map(|(ip, key)| {
+                let mut mac = Hmac::<Sha256>::new_from_slice(key.as_bytes())
+                    .expect("HMAC accepts keys of any size");
+                mac.update(ip.to_string().as_bytes());
+                hex::encode(mac.finalize().into_bytes())
+            });
+        IngressMetadata {
+            method: method.to_string(),
+            received_at,
+            instance_id: self.instance_id.clone(),
+            client_ip_hash,
+            request_id: client
+                .request_id
+                .unwrap_or_else(|| Uuid::new_v4().to_string()),
+        }
+    }
+
     /// Helper method to send audit event for a bundle
-    fn send_audit_event(&self, accepted_bundle: &AcceptedBundle, bundle_hash: B256) {
-        let audit_event = BundleEvent::Received {
-            bundle_id: *accepted_bundle.uuid(),
-            bundle: Box::new(accepted_bundle.clone()),
+    async fn send_audit_event(
+        &self,
+        accepted_bundle: &AcceptedBundle,
+        bundle_hash: B256,
+        metadata: IngressMetadata,
+    ) {
+        let bundle_id = *accepted_bundle.uuid();
+        let bundle = Box::new(accepted_bundle.clone());
+        let audit_event = if accepted_bundle.is_replacement() {
+            BundleEvent::Updated {
+                bundle_id,
+                bundle,
+                metadata: Some(metadata),
+            }
+        } else {
+            BundleEvent::Received {
+                bundle_id,
+                bundle,
+                identity: accepted_bundle.identity.clone(),
+                metadata: Some(metadata),
+            }
         };
-        if let Err(e) = self.audit_channel.send(audit_event) {
+        if let Err(e) = self.audit_channel.send(audit_event).await {
             warn!(
                 message = "failed to send audit event",
                 bundle_hash = %bundle_hash,
@@ -583,12 +955,21 @@ impl<Q: MessageQueue, M: Mempool> IngressService<Q, M> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::auth::Tier;
+    use crate::errors::{
+        BACKRUN_NOT_ALLOWED_CODE, DRAINING_CODE, EXECUTION_TIME_EXCEEDED_CODE,
+        GAS_LIMIT_EXCEEDS_BLOCK_GAS_LIMIT_CODE, INVALID_BUNDLE_CODE, METERING_UNAVAILABLE_CODE,
+        NOT_BUNDLE_OWNER_CODE,
+    };
     use crate::{Config, TxSubmissionMethod, queue::MessageQueue};
     use account_abstraction_core::MempoolEvent;
     use account_abstraction_core::domain::PoolConfig;
     use account_abstraction_core::infrastructure::in_memory::mempool::InMemoryMempool;
     use account_abstraction_core::services::interfaces::event_source::EventSource;
+    use alloy_primitives::U256;
     use alloy_provider::RootProvider;
+    use alloy_provider::network::eip2718::Encodable2718;
+    use alloy_signer_local::PrivateKeySigner;
     use anyhow::Result;
     use async_trait::async_trait;
     use jsonrpsee::core::client::ClientT;
@@ -598,8 +979,13 @@ mod tests {
     use serde_json::json;
     use std::net::{IpAddr, SocketAddr};
     use std::str::FromStr;
-    use tips_core::test_utils::create_test_meter_bundle_response;
-    use tokio::sync::{RwLock, broadcast, mpsc};
+    use tips_audit_lib::{AuditChannelConfig, AuditReceiver, OverflowPolicy, audit_channel};
+    use tips_core::test_utils::{
+        create_test_bundle, create_test_meter_bundle_response, create_transaction,
+    };
+    use tips_core::types::TransactionResult;
+    use tips_core::{AuthMethod, SearcherIdentity};
+    use tokio::sync::{RwLock, broadcast};
     use url::Url;
     use wiremock::{Mock, MockServer, ResponseTemplate, matchers::method};
     struct MockQueue;
@@ -630,27 +1016,74 @@ mod tests {
             ingress_topic: String::new(),
             audit_kafka_properties: String::new(),
             audit_topic: String::new(),
+            audit_channel_capacity: 100,
+            audit_overflow_policy: OverflowPolicy::Block,
+            audit_spill_file: None,
+            audit_send_timeout_ms: 1000,
             user_operation_consumer_properties: Some(String::new()),
             user_operation_consumer_group_id: "tips-user-operation".to_string(),
             log_level: String::from("info"),
             log_format: tips_core::logger::LogFormat::Pretty,
             send_transaction_default_lifetime_seconds: 300,
             simulation_rpc: mock_server.uri().parse().unwrap(),
+            simulation_fallback_rpcs: vec![],
+            simulation_hedge_delay_ms: None,
+            simulation_failure_threshold: 3,
+            simulation_cooldown_ms: 30000,
             metrics_addr: SocketAddr::from(([127, 0, 0, 1], 9002)),
             block_time_milliseconds: 1000,
             meter_bundle_timeout_ms: 5000,
             validate_user_operation_timeout_ms: 2000,
             builder_rpcs: vec![],
+            builders_file: None,
+            builders_file_poll_interval_secs: 5,
+            admin_addr: None,
+            admin_auth_tokens: vec![],
             max_buffered_meter_bundle_responses: 100,
+            builder_queue_size: 1000,
+            builder_max_concurrent_requests: 16,
+            builder_max_retries: 3,
+            builder_request_timeout_ms: 2000,
+            builder_connect_timeout_ms: 500,
             max_buffered_backrun_bundles: 100,
             health_check_addr: SocketAddr::from(([127, 0, 0, 1], 8081)),
+            ready_check_timeout_ms: 2000,
+            ready_max_head_age_secs: 30,
+            shutdown_timeout_secs: 30,
+            rate_limits: vec![],
+            trust_forwarded_for: false,
+            auth_keys_file: None,
+            auth_required: false,
+            instance_id: Some("ingress-test".to_string()),
+            client_ip_hash_key: None,
             backrun_enabled: false,
             raw_tx_forward_rpc: None,
-            chain_id: 11,
+            chain_id: 1,
+            allow_unprotected_txs: false,
             user_operation_topic: String::new(),
             max_backrun_txs: 5,
             max_backrun_gas_limit: 5000000,
+            max_bundle_txs: 3,
+            max_bundle_gas: 25000000,
+            max_raw_tx_gas: 25000000,
+            max_tx_gas: 25000000,
+            bundle_min_time_window_secs: 0,
+            bundle_max_time_window_secs: 3600,
+            raw_tx_allowed_types: TxType::ALL.to_vec(),
+            bundle_allowed_tx_types: TxType::ALL.to_vec(),
+            max_tx_size_bytes: 131072,
+            block_gas_limit: 30000000,
+            validate_account_state: false,
             bundle_cache_ttl: 20,
+            metering_cache_size: 10000,
+            metering_cache_poll_interval_ms: 200,
+            live_bundle_ttl: 10800,
+            replacement_fee_bump_percent: 10,
+            audit_s3_bucket: None,
+            audit_s3_endpoint: None,
+            audit_subscription_kafka_properties: None,
+            firehose_auth_tokens: vec![],
+            max_buffered_bundle_events: 1000,
             send_to_builder: false,
         }
     }
@@ -696,9 +1129,7 @@ mod tests {
 
         let result = timeout(timeout_duration, slow_future)
             .await
-            .map_err(|_| {
-                EthApiError::InvalidParams("Timeout on requesting metering".into()).into_rpc_err()
-            })
+            .map_err(|_| IngressError::MeteringTimeout { timeout_ms: 100 })
             .map_err(|e| e.to_string());
 
         assert!(result.is_err());
@@ -718,9 +1149,7 @@ mod tests {
 
         let result = timeout(timeout_duration, fast_future)
             .await
-            .map_err(|_| {
-                EthApiError::InvalidParams("Timeout on requesting metering".into()).into_rpc_err()
-            })
+            .map_err(|_| IngressError::MeteringTimeout { timeout_ms: 100 })
             .map_err(|e| e.to_string());
 
         assert!(result.is_ok());
@@ -755,10 +1184,11 @@ mod tests {
         let providers = Providers {
             mempool: provider.clone(),
             simulation: provider.clone(),
+            simulation_fallbacks: vec![],
             raw_tx_forward: None,
         };
 
-        let (audit_tx, _audit_rx) = mpsc::unbounded_channel();
+        let (audit_tx, _audit_rx) = test_audit_channel();
         let (builder_tx, _builder_rx) = broadcast::channel(1);
         let (backrun_tx, _backrun_rx) = broadcast::channel(1);
 
@@ -788,6 +1218,42 @@ mod tests {
         assert_eq!(response, MeterBundleResponse::default());
     }
 
+    #[tokio::test]
+    async fn test_meter_bundle_error_codes() {
+        let rejecting_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "error": { "code": -32000, "message": "nonce too low" }
+            })))
+            .mount(&rejecting_server)
+            .await;
+        let (service, _audit_rx, _builder_rx) = create_test_service(&rejecting_server);
+
+        // A JSON-RPC error from the node is a client error
+        let err = service
+            .meter_bundle(&Bundle::default(), &B256::random())
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), INVALID_BUNDLE_CODE);
+        assert_eq!(err.message(), "Invalid bundle: nonce too low");
+
+        let failing_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(503))
+            .mount(&failing_server)
+            .await;
+        let (service, _audit_rx, _builder_rx) = create_test_service(&failing_server);
+
+        // The node not answering is a server error
+        let err = service
+            .meter_bundle(&Bundle::default(), &B256::random())
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), METERING_UNAVAILABLE_CODE);
+    }
+
     #[tokio::test]
     async fn test_raw_tx_forward() {
         let simulation_server = MockServer::start().await;
@@ -819,16 +1285,18 @@ mod tests {
 
         let mut config = create_test_config(&simulation_server);
         config.tx_submission_method = TxSubmissionMethod::Kafka; // Skip mempool send
+        config.chain_id = 13; // chain id the raw transaction below is signed for
 
         let providers = Providers {
             mempool: RootProvider::new_http(simulation_server.uri().parse().unwrap()),
             simulation: RootProvider::new_http(simulation_server.uri().parse().unwrap()),
+            simulation_fallbacks: vec![],
             raw_tx_forward: Some(RootProvider::new_http(
                 forward_server.uri().parse().unwrap(),
             )),
         };
 
-        let (audit_tx, _audit_rx) = mpsc::unbounded_channel();
+        let (audit_tx, _audit_rx) = test_audit_channel();
         let (builder_tx, _builder_rx) = broadcast::channel(1);
         let (backrun_tx, _backrun_rx) = broadcast::channel(1);
 
@@ -850,7 +1318,9 @@ mod tests {
         // Valid signed transaction bytes
         let tx_bytes = Bytes::from_str("0x02f86c0d010183072335825208940000000000000000000000000000000000000000872386f26fc1000080c001a0cdb9e4f2f1ba53f9429077e7055e078cf599786e29059cd80c5e0e923bb2c114a01c90e29201e031baf1da66296c3a5c15c200bcb5e6c34da2f05f7d1778f8be07").unwrap();
 
-        let result = service.send_raw_transaction(tx_bytes).await;
+        let result = service
+            .send_raw_transaction(&Extensions::new(), tx_bytes)
+            .await;
         assert!(result.is_ok());
 
         // Wait for spawned forward task to complete
@@ -858,15 +1328,206 @@ mod tests {
 
         // wiremock automatically verifies expect(1) when forward_server is dropped
     }
+
+    fn test_audit_channel() -> (AuditSender, AuditReceiver) {
+        audit_channel(AuditChannelConfig {
+            capacity: 100,
+            overflow_policy: OverflowPolicy::Block,
+            spill_file: None,
+            send_timeout: Duration::from_secs(1),
+        })
+        .unwrap()
+    }
+
+    fn create_test_service(
+        mock_server: &MockServer,
+    ) -> (
+        IngressService<MockQueue, InMemoryMempool>,
+        AuditReceiver,
+        broadcast::Receiver<MeterBundleResponse>,
+    ) {
+        let config = create_test_config(mock_server);
+        let provider: RootProvider<Optimism> =
+            RootProvider::new_http(mock_server.uri().parse().unwrap());
+
+        let providers = Providers {
+            mempool: provider.clone(),
+            simulation: provider,
+            simulation_fallbacks: vec![],
+            raw_tx_forward: None,
+        };
+
+        let (audit_tx, audit_rx) = test_audit_channel();
+        let (builder_tx, builder_rx) = broadcast::channel(10);
+        let (backrun_tx, _backrun_rx) = broadcast::channel(1);
+
+        let mempool_engine = Arc::new(MempoolEngine::<InMemoryMempool>::new(
+            Arc::new(RwLock::new(InMemoryMempool::new(PoolConfig::default()))),
+            Arc::new(NoopEventSource),
+        ));
+
+        let service = IngressService::new(
+            providers,
+            MockQueue,
+            audit_tx,
+            builder_tx,
+            backrun_tx,
+            mempool_engine,
+            config,
+        );
+
+        (service, audit_rx, builder_rx)
+    }
+
+    #[tokio::test]
+    async fn test_resubmitted_bundle_is_metered_once() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "result": create_test_meter_bundle_response()
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let (service, _audit_rx, _builder_rx) = create_test_service(&mock_server);
+
+        let bundle = Bundle::default();
+        let bundle_hash = B256::random();
+
+        let first = service.meter_bundle(&bundle, &bundle_hash).await.unwrap();
+        let second = service.meter_bundle(&bundle, &bundle_hash).await.unwrap();
+        assert_eq!(first, second);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_unknown_bundle() {
+        let mock_server = MockServer::start().await;
+        let (service, mut audit_rx, _builder_rx) = create_test_service(&mock_server);
+
+        let result = service
+            .cancel_bundle(
+                &Extensions::new(),
+                CancelBundle {
+                    replacement_uuid: Uuid::new_v5(&Uuid::NAMESPACE_OID, b"unknown").to_string(),
+                },
+            )
+            .await;
+        assert!(result.unwrap_err().message().contains("Unknown bundle"));
+
+        let result = service
+            .cancel_bundle(
+                &Extensions::new(),
+                CancelBundle {
+                    replacement_uuid: "not-a-uuid".to_string(),
+                },
+            )
+            .await;
+        assert!(
+            result
+                .unwrap_err()
+                .message()
+                .contains("Invalid replacement UUID")
+        );
+
+        assert!(audit_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_live_bundle() {
+        let mock_server = MockServer::start().await;
+
+        // No receipt for any of the bundle's transactions
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "result": null
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let (service, mut audit_rx, _builder_rx) = create_test_service(&mock_server);
+
+        let alice = PrivateKeySigner::random();
+        let tx = create_transaction(alice.clone(), 1, alice.address());
+        let ext = caller_extensions(Tier::default());
+        let bundle = create_test_bundle(vec![tx], None, None, None)
+            .with_identity(Caller::from_extensions(&ext).identity);
+        let uuid = *bundle.uuid();
+        service.bundle_tracker.track(&bundle).await;
+
+        let request = CancelBundle {
+            replacement_uuid: uuid.to_string(),
+        };
+        assert!(service.cancel_bundle(&ext, request.clone()).await.is_ok());
+        assert!(matches!(
+            audit_rx.try_recv().unwrap(),
+            BundleEvent::Cancelled { bundle_id } if bundle_id == uuid
+        ));
+
+        // A second cancellation no longer finds the bundle
+        let result = service.cancel_bundle(&ext, request).await;
+        assert!(result.unwrap_err().message().contains("Unknown bundle"));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_bundle_requires_owner() {
+        let mock_server = MockServer::start().await;
+        let (service, mut audit_rx, _builder_rx) = create_test_service(&mock_server);
+
+        let alice = PrivateKeySigner::random();
+        let tx = create_transaction(alice.clone(), 1, alice.address());
+        let bundle = create_test_bundle(vec![tx], None, None, None);
+        let request = CancelBundle {
+            replacement_uuid: bundle.uuid().to_string(),
+        };
+
+        // anyone could derive the UUID of an anonymous bundle from its hash
+        service.bundle_tracker.track(&bundle).await;
+        let err = service
+            .cancel_bundle(&Extensions::new(), request.clone())
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), NOT_BUNDLE_OWNER_CODE);
+
+        let mut other = Extensions::new();
+        other.insert(Caller {
+            identity: Some(SearcherIdentity {
+                id: "other".to_string(),
+                auth: AuthMethod::ApiKey,
+                signer: None,
+                tier: "default".to_string(),
+                priority: 0,
+            }),
+            tier: Tier::default(),
+        });
+        let owned = bundle
+            .with_identity(Caller::from_extensions(&caller_extensions(Tier::default())).identity);
+        service.bundle_tracker.track(&owned).await;
+        let err = service.cancel_bundle(&other, request).await.unwrap_err();
+        assert_eq!(err.code(), NOT_BUNDLE_OWNER_CODE);
+
+        assert!(service.bundle_tracker.get(owned.uuid()).await.is_some());
+        assert!(audit_rx.try_recv().is_err());
+    }
     mock! {
         pub IngressApi {}
 
         #[async_trait]
         impl IngressApiServer for IngressApi {
-            async fn send_bundle(&self, bundle: Bundle) -> RpcResult<BundleHash>;
-            async fn send_backrun_bundle(&self, bundle: Bundle) -> RpcResult<BundleHash>;
-            async fn cancel_bundle(&self, request: CancelBundle) -> RpcResult<()>;
-            async fn send_raw_transaction(&self, tx: Bytes) -> RpcResult<B256>;
+            async fn send_bundle(&self, ext: &Extensions, bundle: Bundle) -> RpcResult<BundleHash>;
+            async fn send_backrun_bundle(
+                &self,
+                ext: &Extensions,
+                bundle: Bundle,
+            ) -> RpcResult<BundleHash>;
+            async fn call_bundle(&self, bundle: Bundle) -> RpcResult<CallBundleResponse>;
+            async fn cancel_bundle(&self, ext: &Extensions, request: CancelBundle) -> RpcResult<()>;
+            async fn send_raw_transaction(&self, ext: &Extensions, tx: Bytes) -> RpcResult<B256>;
             async fn send_user_operation(
                 &self,
                 user_operation: VersionedUserOperation,
@@ -929,25 +1590,574 @@ mod tests {
         assert!(wrong_user_op_result.is_err());
     }
 
-    #[test]
-    fn test_validate_backrun_bundle_rejects_invalid() {
-        // Too few transactions (need at least 2: target + backrun)
-        let result = validate_backrun_bundle_limits(1, 21000, 5, 5000000);
-        assert!(result.is_err());
-        assert!(result.unwrap_err().contains("at least 2 transactions"));
+    #[tokio::test]
+    async fn test_send_bundle_replacement() {
+        let mock_server = MockServer::start().await;
 
-        // Exceeds max tx count
-        let result = validate_backrun_bundle_limits(6, 21000, 5, 5000000);
-        assert!(result.is_err());
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "result": create_test_meter_bundle_response()
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let (service, mut audit_rx, _builder_rx) = create_test_service(&mock_server);
+
+        let alice = PrivateKeySigner::random();
+        let replacement_uuid = Uuid::new_v5(&Uuid::NAMESPACE_OID, b"replacement");
+        let make_bundle = |nonce| {
+            let tx = create_transaction(alice.clone(), nonce, alice.address());
+            Bundle {
+                txs: vec![tx.encoded_2718().into()],
+                reverting_tx_hashes: vec![tx.tx_hash()],
+                replacement_uuid: Some(replacement_uuid.to_string()),
+                ..Default::default()
+            }
+        };
+
+        service
+            .send_bundle(&Extensions::new(), make_bundle(1))
+            .await
+            .unwrap();
+        match audit_rx.try_recv().unwrap() {
+            BundleEvent::Received {
+                bundle_id, bundle, ..
+            } => {
+                assert_eq!(bundle_id, replacement_uuid);
+                assert_eq!(bundle.version, 0);
+            }
+            event => panic!("Expected Received event, got {event:?}"),
+        }
+
+        service
+            .send_bundle(&Extensions::new(), make_bundle(2))
+            .await
+            .unwrap();
+        match audit_rx.try_recv().unwrap() {
+            BundleEvent::Updated {
+                bundle_id,
+                bundle,
+                metadata,
+            } => {
+                assert_eq!(bundle_id, replacement_uuid);
+                assert_eq!(bundle.version, 1);
+                assert_eq!(metadata.unwrap().method, "eth_sendBundle");
+            }
+            event => panic!("Expected Updated event, got {event:?}"),
+        }
+
+        let live_bundle = service.bundle_tracker.get(&replacement_uuid).await.unwrap();
+        assert_eq!(live_bundle.version, 1);
+    }
+
+    #[tokio::test]
+    async fn test_send_bundle_without_registered_builders() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "result": create_test_meter_bundle_response()
+            })))
+            .mount(&mock_server)
+            .await;
+
+        // No builder delivery pipeline is subscribed to the broadcast
+        let (service, mut audit_rx, builder_rx) = create_test_service(&mock_server);
+        drop(builder_rx);
+
+        let alice = PrivateKeySigner::random();
+        let tx = create_transaction(alice.clone(), 1, alice.address());
+        let bundle = Bundle {
+            txs: vec![tx.encoded_2718().into()],
+            reverting_tx_hashes: vec![tx.tx_hash()],
+            ..Default::default()
+        };
+
+        service
+            .send_bundle(&Extensions::new(), bundle)
+            .await
+            .unwrap();
+        assert!(matches!(
+            audit_rx.try_recv().unwrap(),
+            BundleEvent::Received { .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_raw_tx_replacement_by_sender_and_nonce() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "result": create_test_meter_bundle_response()
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let (mut service, mut audit_rx, _builder_rx) = create_test_service(&mock_server);
+        service
+            .controls
+            .set_tx_submission_method(TxSubmissionMethod::Kafka);
+
+        let alice = PrivateKeySigner::random();
+        let bob = PrivateKeySigner::random();
+        let original = create_transaction(alice.clone(), 1, alice.address());
+        let replacement = create_transaction(alice.clone(), 1, bob.address());
+
+        service
+            .send_raw_transaction(&Extensions::new(), original.encoded_2718().into())
+            .await
+            .unwrap();
+        let bundle_id = match audit_rx.try_recv().unwrap() {
+            BundleEvent::Received {
+                bundle_id, bundle, ..
+            } => {
+                assert_eq!(bundle.version, 0);
+                bundle_id
+            }
+            event => panic!("Expected Received event, got {event:?}"),
+        };
+
+        // same fees as the original, below the required bump
+        let result = service
+            .send_raw_transaction(&Extensions::new(), replacement.encoded_2718().into())
+            .await;
+        assert!(result.unwrap_err().message().contains("underpriced"));
+        assert!(audit_rx.try_recv().is_err());
+
+        service.replacement_fee_bump_percent = 0;
+        service
+            .send_raw_transaction(&Extensions::new(), replacement.encoded_2718().into())
+            .await
+            .unwrap();
+        match audit_rx.try_recv().unwrap() {
+            BundleEvent::Updated {
+                bundle_id: updated_id,
+                bundle,
+                metadata,
+            } => {
+                assert_eq!(updated_id, bundle_id);
+                assert_eq!(metadata.unwrap().method, "eth_sendRawTransaction");
+                assert_eq!(bundle.version, 1);
+                assert_eq!(bundle.txn_hashes(), vec![replacement.tx_hash()]);
+            }
+            event => panic!("Expected Updated event, got {event:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_raw_tx_not_published_to_kafka_is_not_tracked() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "result": create_test_meter_bundle_response()
+            })))
+            .mount(&mock_server)
+            .await;
+
+        // the test config only sends raw transactions to the mempool
+        let (service, _audit_rx, _builder_rx) = create_test_service(&mock_server);
+        let alice = PrivateKeySigner::random();
+        let tx = create_transaction(alice.clone(), 1, alice.address());
+
+        service
+            .send_raw_transaction(&Extensions::new(), tx.encoded_2718().into())
+            .await
+            .unwrap();
+        assert!(
+            service
+                .bundle_tracker
+                .get_transaction(alice.address(), 1)
+                .await
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_send_bundle_rejects_reverted_protected_tx() {
+        let mock_server = MockServer::start().await;
+
+        let alice = PrivateKeySigner::random();
+        let tx = create_transaction(alice.clone(), 1, alice.address());
+
+        let meter_bundle_response = MeterBundleResponse {
+            results: vec![TransactionResult {
+                coinbase_diff: U256::ZERO,
+                eth_sent_to_coinbase: U256::ZERO,
+                from_address: alice.address(),
+                gas_fees: U256::ZERO,
+                gas_price: U256::ZERO,
+                gas_used: 21_000,
+                to_address: Some(alice.address()),
+                tx_hash: tx.tx_hash(),
+                value: U256::ZERO,
+                execution_time_us: 0,
+                reverted: Some(true),
+            }],
+            ..create_test_meter_bundle_response()
+        };
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "result": meter_bundle_response
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let (service, mut audit_rx, _builder_rx) = create_test_service(&mock_server);
+
+        let protected_bundle = Bundle {
+            txs: vec![tx.encoded_2718().into()],
+            ..Default::default()
+        };
+        let result = service
+            .send_bundle(&Extensions::new(), protected_bundle.clone())
+            .await;
         assert!(
             result
                 .unwrap_err()
-                .contains("exceeds max transaction count")
+                .message()
+                .contains("Protected transaction reverted in simulation")
         );
+        assert!(audit_rx.try_recv().is_err());
 
-        // Exceeds max gas limit
-        let result = validate_backrun_bundle_limits(2, 6000000, 5, 5000000);
-        assert!(result.is_err());
-        assert!(result.unwrap_err().contains("exceeds max gas limit"));
+        // the same bundle is accepted once the transaction is allowed to revert
+        let reverting_bundle = Bundle {
+            reverting_tx_hashes: vec![tx.tx_hash()],
+            ..protected_bundle
+        };
+        assert!(
+            service
+                .send_bundle(&Extensions::new(), reverting_bundle)
+                .await
+                .is_ok()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_send_raw_transaction_rejects_wrong_chain_id() {
+        let mock_server = MockServer::start().await;
+        let (mut service, mut audit_rx, _builder_rx) = create_test_service(&mock_server);
+
+        let alice = PrivateKeySigner::random();
+        let tx = create_transaction(alice.clone(), 1, alice.address());
+        let tx_chain_id = tx.chain_id().unwrap();
+        service.chain_id = tx_chain_id + 1;
+
+        let result = service
+            .send_raw_transaction(&Extensions::new(), tx.encoded_2718().into())
+            .await;
+        let expected = TxValidationError::WrongChainId {
+            expected: tx_chain_id + 1,
+            actual: tx_chain_id,
+        };
+        assert!(
+            result
+                .unwrap_err()
+                .message()
+                .contains(&expected.to_string())
+        );
+        assert!(audit_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_send_raw_transaction_rejects_disallowed_tx_type() {
+        let mock_server = MockServer::start().await;
+        let (mut service, mut audit_rx, _builder_rx) = create_test_service(&mock_server);
+        service.validation_policy.raw_transaction_tx_types = vec![TxType::Legacy];
+
+        let alice = PrivateKeySigner::random();
+        let tx = create_transaction(alice.clone(), 1, alice.address());
+
+        let result = service
+            .send_raw_transaction(&Extensions::new(), tx.encoded_2718().into())
+            .await;
+        let expected = TxValidationError::TxTypeNotAllowed {
+            tx_type: TxType::Eip1559,
+            kind: BundleKind::RawTransaction,
+        };
+        assert!(
+            result
+                .unwrap_err()
+                .message()
+                .contains(&expected.to_string())
+        );
+        assert!(audit_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_draining_rejects_submissions() {
+        let mock_server = MockServer::start().await;
+        let (service, mut audit_rx, _builder_rx) = create_test_service(&mock_server);
+        service.runtime_controls().set_draining(true);
+
+        let alice = PrivateKeySigner::random();
+        let tx = create_transaction(alice.clone(), 1, alice.address());
+
+        let err = service
+            .send_raw_transaction(&Extensions::new(), tx.encoded_2718().into())
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), DRAINING_CODE);
+
+        let err = service
+            .send_bundle(
+                &Extensions::new(),
+                Bundle {
+                    txs: vec![tx.encoded_2718().into()],
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), DRAINING_CODE);
+        assert_eq!(service.runtime_controls().in_flight(), 0);
+        assert!(audit_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_send_bundle_rejects_tx_above_block_gas_limit() {
+        let mock_server = MockServer::start().await;
+        let (mut service, mut audit_rx, _builder_rx) = create_test_service(&mock_server);
+        service.validation_policy.block_gas_limit = 20_000;
+
+        let alice = PrivateKeySigner::random();
+        let tx = create_transaction(alice.clone(), 1, alice.address());
+        let bundle = Bundle {
+            txs: vec![tx.encoded_2718().into()],
+            ..Default::default()
+        };
+
+        let err = service
+            .send_bundle(&Extensions::new(), bundle)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), GAS_LIMIT_EXCEEDS_BLOCK_GAS_LIMIT_CODE);
+        assert!(audit_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_send_bundle_rejects_slow_bundle_with_execution_time() {
+        let mock_server = MockServer::start().await;
+        let meter_bundle_response = MeterBundleResponse {
+            total_execution_time_us: 1_500_000,
+            ..create_test_meter_bundle_response()
+        };
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "result": meter_bundle_response
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let (service, mut audit_rx, _builder_rx) = create_test_service(&mock_server);
+        let alice = PrivateKeySigner::random();
+        let tx = create_transaction(alice.clone(), 1, alice.address());
+        let bundle = Bundle {
+            txs: vec![tx.encoded_2718().into()],
+            ..Default::default()
+        };
+
+        let err = service
+            .send_bundle(&Extensions::new(), bundle)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), EXECUTION_TIME_EXCEEDED_CODE);
+        let data: serde_json::Value = serde_json::from_str(err.data().unwrap().get()).unwrap();
+        assert_eq!(
+            data,
+            serde_json::json!({ "executionTimeUs": 1_500_000, "limitUs": 1_000_000 })
+        );
+        assert!(audit_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_call_bundle_returns_verdicts_without_queueing() {
+        let mock_server = MockServer::start().await;
+        let meter_bundle_response = MeterBundleResponse {
+            total_execution_time_us: 1_500_000,
+            ..create_test_meter_bundle_response()
+        };
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "result": meter_bundle_response
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let (service, mut audit_rx, mut builder_rx) = create_test_service(&mock_server);
+        let alice = PrivateKeySigner::random();
+        let tx = create_transaction(alice.clone(), 1, alice.address());
+        let bundle = Bundle {
+            txs: vec![tx.encoded_2718().into()],
+            reverting_tx_hashes: vec![tx.tx_hash()],
+            ..Default::default()
+        };
+
+        let response = service.call_bundle(bundle).await.unwrap();
+        assert_eq!(response.meter_bundle_response, meter_bundle_response);
+        assert_eq!(response.verdicts.len(), 1);
+        assert_eq!(response.verdicts[0].code, EXECUTION_TIME_EXCEEDED_CODE);
+
+        assert!(audit_rx.try_recv().is_err());
+        assert!(builder_rx.try_recv().is_err());
+    }
+
+    fn caller_extensions(tier: Tier) -> Extensions {
+        let mut ext = Extensions::new();
+        ext.insert(Caller {
+            identity: Some(SearcherIdentity {
+                id: "searcher".to_string(),
+                auth: AuthMethod::ApiKey,
+                signer: None,
+                tier: tier.name.clone(),
+                priority: tier.priority,
+            }),
+            tier,
+        });
+        ext
+    }
+
+    #[tokio::test]
+    async fn test_send_bundle_attaches_identity() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "result": create_test_meter_bundle_response()
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let (service, mut audit_rx, _builder_rx) = create_test_service(&mock_server);
+        let alice = PrivateKeySigner::random();
+        let tx = create_transaction(alice.clone(), 1, alice.address());
+        let bundle = Bundle {
+            txs: vec![tx.encoded_2718().into()],
+            reverting_tx_hashes: vec![tx.tx_hash()],
+            ..Default::default()
+        };
+
+        service
+            .send_bundle(&caller_extensions(Tier::default()), bundle)
+            .await
+            .unwrap();
+        match audit_rx.try_recv().unwrap() {
+            BundleEvent::Received {
+                bundle, identity, ..
+            } => {
+                assert_eq!(identity.unwrap().id, "searcher");
+                assert_eq!(bundle.identity.unwrap().id, "searcher");
+            }
+            event => panic!("Expected Received event, got {event:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_send_bundle_records_ingress_metadata() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "result": create_test_meter_bundle_response()
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let (mut service, mut audit_rx, _builder_rx) = create_test_service(&mock_server);
+        service.client_ip_hash_key = Some("secret".to_string());
+        let alice = PrivateKeySigner::random();
+        let tx = create_transaction(alice.clone(), 1, alice.address());
+        let bundle = Bundle {
+            txs: vec![tx.encoded_2718().into()],
+            reverting_tx_hashes: vec![tx.tx_hash()],
+            ..Default::default()
+        };
+
+        let mut ext = Extensions::new();
+        ext.insert(ClientInfo {
+            ip: Some(IpAddr::from([10, 0, 0, 1])),
+            api_key: None,
+            request_id: Some("req-1".to_string()),
+        });
+        service.send_bundle(&ext, bundle).await.unwrap();
+
+        let metadata = match audit_rx.try_recv().unwrap() {
+            BundleEvent::Received { metadata, .. } => metadata.unwrap(),
+            event => panic!("Expected Received event, got {event:?}"),
+        };
+        assert_eq!(metadata.method, "eth_sendBundle");
+        assert_eq!(metadata.instance_id, "ingress-test");
+        assert_eq!(metadata.request_id, "req-1");
+        assert!(metadata.received_at > 0);
+
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"secret").unwrap();
+        mac.update(b"10.0.0.1");
+        let expected = hex::encode(mac.finalize().into_bytes());
+        assert_eq!(metadata.client_ip_hash, Some(expected));
+    }
+
+    #[tokio::test]
+    async fn test_send_raw_transaction_metadata_without_client_info() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "result": create_test_meter_bundle_response()
+            })))
+            .mount(&mock_server)
+            .await;
+
+        let (service, mut audit_rx, _builder_rx) = create_test_service(&mock_server);
+        let signer = PrivateKeySigner::random();
+        let tx = create_transaction(signer.clone(), 1, signer.address());
+
+        service
+            .send_raw_transaction(&Extensions::new(), tx.encoded_2718().into())
+            .await
+            .unwrap();
+
+        let metadata = match audit_rx.try_recv().unwrap() {
+            BundleEvent::Received { metadata, .. } => metadata.unwrap(),
+            event => panic!("Expected Received event, got {event:?}"),
+        };
+        assert_eq!(metadata.method, "eth_sendRawTransaction");
+        assert_eq!(metadata.client_ip_hash, None);
+        assert!(Uuid::parse_str(&metadata.request_id).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_send_backrun_bundle_rejected_by_tier() {
+        let mock_server = MockServer::start().await;
+        let (service, mut audit_rx, _builder_rx) = create_test_service(&mock_server);
+        service.controls.set_backrun_enabled(true);
+
+        let tier = Tier {
+            name: "basic".to_string(),
+            backrun: false,
+            ..Default::default()
+        };
+        let err = service
+            .send_backrun_bundle(&caller_extensions(tier), Bundle::default())
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), BACKRUN_NOT_ALLOWED_CODE);
+        assert!(audit_rx.try_recv().is_err());
     }
 }
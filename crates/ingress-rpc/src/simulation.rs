@@ -0,0 +1,379 @@
+use account_abstraction_core::infrastructure::base_node::client::BaseNodeClient;
+use alloy_provider::{Provider, RootProvider};
+use alloy_transport::TransportResult;
+use async_trait::async_trait;
+use op_alloy_network::Optimism;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::future;
+use std::sync::{Arc, Mutex};
+use tokio::task::JoinSet;
+use tokio::time::{Duration, Instant, sleep};
+use tracing::warn;
+
+use crate::metrics::record_histogram;
+
+/// Label of the first configured simulation endpoint, which also serves state lookups
+pub const PRIMARY_ENDPOINT: &str = "primary";
+
+/// Stops sending requests to an endpoint after `failure_threshold` consecutive failures, until
+/// `cooldown` has passed. The next request after the cooldown is a probe: a success closes the
+/// breaker again, a failure reopens it.
+#[derive(Debug)]
+struct CircuitBreaker {
+    failure_threshold: u32,
+    cooldown: Duration,
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    fn is_closed(&self, now: Instant) -> bool {
+        self.open_until.is_none_or(|open_until| now >= open_until)
+    }
+
+    fn record_success(&mut self) {
+        self.consecutive_failures = 0;
+        self.open_until = None;
+    }
+
+    fn record_failure(&mut self, now: Instant) {
+        self.consecutive_failures += 1;
+        if self.consecutive_failures >= self.failure_threshold {
+            self.open_until = Some(now + self.cooldown);
+        }
+    }
+}
+
+/// A request sent to an endpoint and its outcome
+type Attempt = (Arc<SimulationEndpoint>, TransportResult<Value>);
+
+#[derive(Debug)]
+struct SimulationEndpoint {
+    label: String,
+    provider: RootProvider<Optimism>,
+    breaker: Mutex<CircuitBreaker>,
+}
+
+impl SimulationEndpoint {
+    fn is_healthy(&self) -> bool {
+        self.breaker.lock().unwrap().is_closed(Instant::now())
+    }
+
+    fn record_success(&self) {
+        self.breaker.lock().unwrap().record_success();
+    }
+
+    fn record_failure(&self) {
+        let mut breaker = self.breaker.lock().unwrap();
+        breaker.record_failure(Instant::now());
+        if !breaker.is_closed(Instant::now()) {
+            warn!(
+                message = "Simulation endpoint circuit breaker open",
+                endpoint = %self.label,
+                consecutive_failures = breaker.consecutive_failures
+            );
+        }
+    }
+
+    async fn request(&self, method: &'static str, params: Value) -> TransportResult<Value> {
+        let start = Instant::now();
+        let result = self.provider.client().request(method, params).await;
+        record_histogram(start.elapsed(), method.to_string(), &self.label);
+        result
+    }
+}
+
+/// A pool of simulation nodes. Requests go to the first healthy endpoint, and fail over to the
+/// next one when it can't be reached. With a hedge delay, a request that hasn't completed after
+/// the delay is also sent to the next endpoint, and the first successful response wins. A
+/// JSON-RPC error response is returned as is: the node is up, and another one would reject the
+/// request the same way.
+#[derive(Debug)]
+pub struct SimulationPool {
+    endpoints: Vec<Arc<SimulationEndpoint>>,
+    hedge_delay: Option<Duration>,
+}
+
+impl SimulationPool {
+    /// The first provider is the primary endpoint, the others are fallbacks in order of preference
+    pub fn new(
+        providers: impl IntoIterator<Item = RootProvider<Optimism>>,
+        hedge_delay: Option<Duration>,
+        failure_threshold: u32,
+        cooldown: Duration,
+    ) -> Self {
+        let endpoints = providers
+            .into_iter()
+            .enumerate()
+            .map(|(i, provider)| {
+                let label = if i == 0 {
+                    PRIMARY_ENDPOINT.to_string()
+                } else {
+                    format!("fallback-{i}")
+                };
+                Arc::new(SimulationEndpoint {
+                    label,
+                    provider,
+                    breaker: Mutex::new(CircuitBreaker {
+                        failure_threshold,
+                        cooldown,
+                        consecutive_failures: 0,
+                        open_until: None,
+                    }),
+                })
+            })
+            .collect();
+        Self {
+            endpoints,
+            hedge_delay,
+        }
+    }
+
+    /// Sends a request through the pool and deserializes the first successful response
+    pub async fn request<P, R>(&self, method: &'static str, params: P) -> anyhow::Result<R>
+    where
+        P: Serialize,
+        R: DeserializeOwned,
+    {
+        let params = serde_json::to_value(params)?;
+        let response = self.request_value(method, params).await?;
+        Ok(serde_json::from_value(response)?)
+    }
+
+    /// Healthy endpoints first, endpoints with an open circuit breaker are only tried as a last
+    /// resort
+    fn candidates(&self) -> Vec<Arc<SimulationEndpoint>> {
+        let (mut healthy, unhealthy): (Vec<_>, Vec<_>) = self
+            .endpoints
+            .iter()
+            .cloned()
+            .partition(|endpoint| endpoint.is_healthy());
+        healthy.extend(unhealthy);
+        healthy
+    }
+
+    async fn request_value(&self, method: &'static str, params: Value) -> anyhow::Result<Value> {
+        let mut candidates = self.candidates().into_iter();
+        let mut requests = JoinSet::new();
+        let mut last_error = anyhow::anyhow!("No simulation endpoints configured");
+
+        let mut send_next = |requests: &mut JoinSet<Attempt>| -> bool {
+            let Some(endpoint) = candidates.next() else {
+                return false;
+            };
+            let params = params.clone();
+            requests.spawn(async move {
+                let result = endpoint.request(method, params).await;
+                (endpoint, result)
+            });
+            true
+        };
+
+        // requests still in flight once one completes are aborted when `requests` is dropped,
+        // without counting against their endpoints
+        let mut has_next = send_next(&mut requests);
+        while !requests.is_empty() {
+            let hedge_delay = self.hedge_delay.filter(|_| has_next);
+            let hedge = async move {
+                match hedge_delay {
+                    Some(delay) => sleep(delay).await,
+                    None => future::pending().await,
+                }
+            };
+
+            tokio::select! {
+                Some(joined) = requests.join_next() => {
+                    let Ok((endpoint, result)) = joined else {
+                        continue;
+                    };
+                    match result {
+                        Ok(response) => {
+                            endpoint.record_success();
+                            return Ok(response);
+                        }
+                        Err(e) if e.as_error_resp().is_some() => return Err(e.into()),
+                        Err(e) => {
+                            warn!(
+                                message = "Simulation request failed",
+                                endpoint = %endpoint.label,
+                                method,
+                                error = %e
+                            );
+                            endpoint.record_failure();
+                            last_error = e.into();
+                            has_next = send_next(&mut requests);
+                        }
+                    }
+                }
+                _ = hedge => {
+                    has_next = send_next(&mut requests);
+                }
+            }
+        }
+        Err(last_error)
+    }
+}
+
+#[async_trait]
+impl BaseNodeClient for SimulationPool {
+    async fn request(&self, method: &'static str, params: Value) -> anyhow::Result<Value> {
+        self.request_value(method, params).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::{Mock, MockServer, ResponseTemplate, matchers::method};
+
+    async fn mock_endpoint(response: ResponseTemplate) -> MockServer {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(response)
+            .mount(&mock_server)
+            .await;
+        mock_server
+    }
+
+    fn ok(result: Value) -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_json(json!({
+            "jsonrpc": "2.0",
+            "id": 0,
+            "result": result
+        }))
+    }
+
+    fn error() -> ResponseTemplate {
+        ResponseTemplate::new(500).set_body_json(json!({
+            "jsonrpc": "2.0",
+            "id": 0,
+            "error": { "code": -32000, "message": "Internal error" }
+        }))
+    }
+
+    fn rpc_error() -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_json(json!({
+            "jsonrpc": "2.0",
+            "id": 0,
+            "error": { "code": -32000, "message": "nonce too low" }
+        }))
+    }
+
+    fn new_pool(servers: &[&MockServer], hedge_delay: Option<Duration>) -> SimulationPool {
+        SimulationPool::new(
+            servers
+                .iter()
+                .map(|server| RootProvider::new_http(server.uri().parse().unwrap())),
+            hedge_delay,
+            2,
+            Duration::from_secs(60),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_fails_over_to_next_endpoint() {
+        let primary = mock_endpoint(error()).await;
+        let fallback = mock_endpoint(ok(json!("fallback"))).await;
+        let pool = new_pool(&[&primary, &fallback], None);
+
+        let response: String = pool.request("base_meterBundle", ()).await.unwrap();
+        assert_eq!(response, "fallback");
+        assert!(pool.endpoints[0].is_healthy());
+
+        // the primary's circuit breaker opens on the second consecutive failure
+        let _: String = pool.request("base_meterBundle", ()).await.unwrap();
+        assert!(!pool.endpoints[0].is_healthy());
+        assert_eq!(pool.candidates()[0].label, "fallback-1");
+    }
+
+    #[tokio::test]
+    async fn test_all_endpoints_failing() {
+        let primary = mock_endpoint(error()).await;
+        let fallback = mock_endpoint(error()).await;
+        let pool = new_pool(&[&primary, &fallback], None);
+
+        let result: anyhow::Result<String> = pool.request("base_meterBundle", ()).await;
+        assert!(result.unwrap_err().to_string().contains("Internal error"));
+    }
+
+    #[tokio::test]
+    async fn test_rpc_error_is_returned_without_failover() {
+        let primary = mock_endpoint(rpc_error()).await;
+        let fallback = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ok(json!("fallback")))
+            .expect(0)
+            .mount(&fallback)
+            .await;
+        let pool = new_pool(&[&primary, &fallback], None);
+
+        for _ in 0..3 {
+            let result: anyhow::Result<String> = pool.request("base_meterBundle", ()).await;
+            assert!(result.unwrap_err().to_string().contains("nonce too low"));
+        }
+        // the node answered, its circuit breaker stays closed
+        assert!(pool.endpoints[0].is_healthy());
+        assert_eq!(
+            pool.endpoints[0]
+                .breaker
+                .lock()
+                .unwrap()
+                .consecutive_failures,
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn test_hedged_request() {
+        let primary =
+            mock_endpoint(ok(json!("primary")).set_delay(Duration::from_millis(500))).await;
+        let fallback = mock_endpoint(ok(json!("fallback"))).await;
+
+        let pool = new_pool(&[&primary, &fallback], Some(Duration::from_millis(50)));
+        for _ in 0..2 {
+            let response: String = pool.request("base_meterBundle", ()).await.unwrap();
+            assert_eq!(response, "fallback");
+        }
+        // losing a hedge is not a failure
+        assert!(pool.endpoints[0].is_healthy());
+        assert_eq!(
+            pool.endpoints[0]
+                .breaker
+                .lock()
+                .unwrap()
+                .consecutive_failures,
+            0
+        );
+
+        let pool = new_pool(&[&primary, &fallback], None);
+        let response: String = pool.request("base_meterBundle", ()).await.unwrap();
+        assert_eq!(response, "primary");
+    }
+
+    #[test]
+    fn test_circuit_breaker() {
+        let now = Instant::now();
+        let mut breaker = CircuitBreaker {
+            failure_threshold: 2,
+            cooldown: Duration::from_secs(10),
+            consecutive_failures: 0,
+            open_until: None,
+        };
+
+        breaker.record_failure(now);
+        assert!(breaker.is_closed(now));
+        breaker.record_failure(now);
+        assert!(!breaker.is_closed(now));
+        assert!(breaker.is_closed(now + Duration::from_secs(10)));
+
+        // a failed probe reopens the breaker
+        breaker.record_failure(now + Duration::from_secs(10));
+        assert!(!breaker.is_closed(now + Duration::from_secs(11)));
+
+        breaker.record_success();
+        assert!(breaker.is_closed(now));
+    }
+}
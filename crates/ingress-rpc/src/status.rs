@@ -0,0 +1,397 @@
+use alloy_primitives::{B256, TxHash};
+use jsonrpsee::{
+    core::{RpcResult, async_trait},
+    proc_macros::rpc,
+};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::sync::Arc;
+use tips_audit_lib::{BundleEventS3Reader, BundleHistory, BundleHistoryEvent, DropReason};
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::errors::IngressError;
+
+/// Current state of a bundle, derived from its audit history
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BundleState {
+    Received,
+    IncludedByBuilder,
+    IncludedInBlock,
+    Dropped,
+    Cancelled,
+}
+
+impl BundleState {
+    /// How far a bundle has progressed, a bundle's state is the most advanced one in its history
+    const fn rank(self) -> u8 {
+        match self {
+            Self::Received => 0,
+            Self::IncludedByBuilder => 1,
+            Self::Dropped | Self::Cancelled => 2,
+            Self::IncludedInBlock => 3,
+        }
+    }
+}
+
+/// Status of a bundle returned by `tips_getBundleStatus`. Timestamps are unix milliseconds.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleStatus {
+    pub bundle_id: Uuid,
+    pub state: BundleState,
+    /// Timestamp of the event the state was derived from
+    pub updated_at: i64,
+    /// Timestamp of the first version of the bundle being received
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub received_at: Option<i64>,
+    /// Latest version of the bundle, bumped on every replacement
+    pub version: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_number: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub drop_reason: Option<DropReason>,
+}
+
+impl BundleStatus {
+    /// Derives the current state of a bundle from its history, `None` if it has no events
+    pub fn from_history(bundle_id: Uuid, history: &BundleHistory) -> Option<Self> {
+        let mut status: Option<Self> = None;
+        let mut received_at = None;
+        let mut version = 0;
+
+        for event in &history.history {
+            let (state, timestamp, block_number, drop_reason) = match event {
+                BundleHistoryEvent::Received {
+                    timestamp, bundle, ..
+                }
+                | BundleHistoryEvent::Updated {
+                    timestamp, bundle, ..
+                } => {
+                    received_at =
+                        Some(received_at.map_or(*timestamp, |at: i64| at.min(*timestamp)));
+                    version = version.max(bundle.version);
+                    (BundleState::Received, *timestamp, None, None)
+                }
+                BundleHistoryEvent::BuilderIncluded {
+                    timestamp,
+                    block_number,
+                    ..
+                } => (
+                    BundleState::IncludedByBuilder,
+                    *timestamp,
+                    Some(*block_number),
+                    None,
+                ),
+                BundleHistoryEvent::BlockIncluded {
+                    timestamp,
+                    block_number,
+                    ..
+                } => (
+                    BundleState::IncludedInBlock,
+                    *timestamp,
+                    Some(*block_number),
+                    None,
+                ),
+                BundleHistoryEvent::Dropped {
+                    timestamp, reason, ..
+                } => (BundleState::Dropped, *timestamp, None, Some(reason.clone())),
+                BundleHistoryEvent::Cancelled { timestamp, .. } => {
+                    (BundleState::Cancelled, *timestamp, None, None)
+                }
+            };
+
+            let advances = status.as_ref().is_none_or(|current| {
+                (state.rank(), timestamp) >= (current.state.rank(), current.updated_at)
+            });
+            if advances {
+                status = Some(Self {
+                    bundle_id,
+                    state,
+                    updated_at: timestamp,
+                    received_at: None,
+                    version: 0,
+                    block_number,
+                    drop_reason,
+                });
+            }
+        }
+
+        status.map(|status| Self {
+            received_at,
+            version,
+            ..status
+        })
+    }
+}
+
+/// Status of a transaction returned by `tips_getTransactionStatus`, one entry per bundle that
+/// contains it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionStatus {
+    pub tx_hash: TxHash,
+    pub bundles: Vec<BundleStatus>,
+}
+
+#[rpc(server, namespace = "tips")]
+pub trait BundleStatusApi {
+    /// Handler for: `tips_getBundleStatus`, by bundle UUID or bundle hash
+    #[method(name = "getBundleStatus")]
+    async fn get_bundle_status(&self, bundle_id: String) -> RpcResult<Option<BundleStatus>>;
+
+    /// Handler for: `tips_getTransactionStatus`
+    #[method(name = "getTransactionStatus")]
+    async fn get_transaction_status(&self, tx_hash: TxHash)
+    -> RpcResult<Option<TransactionStatus>>;
+}
+
+/// Serves bundle and transaction statuses from the audit store
+pub struct BundleStatusService {
+    reader: Arc<dyn BundleEventS3Reader + Send + Sync>,
+}
+
+impl BundleStatusService {
+    pub fn new(reader: Arc<dyn BundleEventS3Reader + Send + Sync>) -> Self {
+        Self { reader }
+    }
+
+    async fn bundle_status(&self, bundle_id: Uuid) -> Result<Option<BundleStatus>, IngressError> {
+        let history = self
+            .reader
+            .get_bundle_history(bundle_id)
+            .await
+            .map_err(|e| {
+                warn!(message = "Failed to read bundle history", bundle_id = %bundle_id, error = %e);
+                IngressError::AuditStoreUnavailable(e.to_string())
+            })?;
+        Ok(history.and_then(|history| BundleStatus::from_history(bundle_id, &history)))
+    }
+}
+
+/// Parses a bundle UUID, or a bundle hash which identifies bundles sent without a replacement UUID
+fn parse_bundle_id(bundle_id: &str) -> Result<Uuid, IngressError> {
+    if let Ok(uuid) = Uuid::parse_str(bundle_id) {
+        return Ok(uuid);
+    }
+    let bundle_hash = B256::from_str(bundle_id).map_err(|_| {
+        IngressError::InvalidParams(format!(
+            "Invalid bundle id: {bundle_id}, expected a UUID or a bundle hash"
+        ))
+    })?;
+    Ok(Uuid::new_v5(&Uuid::NAMESPACE_OID, bundle_hash.as_slice()))
+}
+
+#[async_trait]
+impl BundleStatusApiServer for BundleStatusService {
+    async fn get_bundle_status(&self, bundle_id: String) -> RpcResult<Option<BundleStatus>> {
+        let bundle_id = parse_bundle_id(&bundle_id)?;
+        Ok(self.bundle_status(bundle_id).await?)
+    }
+
+    async fn get_transaction_status(
+        &self,
+        tx_hash: TxHash,
+    ) -> RpcResult<Option<TransactionStatus>> {
+        let Some(metadata) = self
+            .reader
+            .get_transaction_metadata(tx_hash)
+            .await
+            .map_err(|e| {
+                warn!(message = "Failed to read transaction metadata", tx_hash = %tx_hash, error = %e);
+                IngressError::AuditStoreUnavailable(e.to_string())
+            })?
+        else {
+            return Ok(None);
+        };
+
+        let mut bundles = Vec::with_capacity(metadata.bundle_ids.len());
+        for bundle_id in metadata.bundle_ids {
+            if let Some(status) = self.bundle_status(bundle_id).await? {
+                bundles.push(status);
+            }
+        }
+        Ok(Some(TransactionStatus { tx_hash, bundles }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_signer_local::PrivateKeySigner;
+    use std::collections::HashMap;
+    use tips_audit_lib::TransactionMetadata;
+    use tips_core::test_utils::{create_test_bundle, create_transaction};
+
+    /// In-memory audit store
+    #[derive(Default)]
+    struct InMemoryAuditStore {
+        bundles: HashMap<Uuid, BundleHistory>,
+        transactions: HashMap<TxHash, TransactionMetadata>,
+    }
+
+    #[async_trait]
+    impl BundleEventS3Reader for InMemoryAuditStore {
+        async fn get_bundle_history(
+            &self,
+            bundle_id: Uuid,
+        ) -> anyhow::Result<Option<BundleHistory>> {
+            Ok(self.bundles.get(&bundle_id).cloned())
+        }
+
+        async fn get_transaction_metadata(
+            &self,
+            tx_hash: TxHash,
+        ) -> anyhow::Result<Option<TransactionMetadata>> {
+            Ok(self.transactions.get(&tx_hash).cloned())
+        }
+    }
+
+    fn received(timestamp: i64, version: u64) -> BundleHistoryEvent {
+        let alice = PrivateKeySigner::random();
+        let bundle = create_test_bundle(
+            vec![create_transaction(alice.clone(), 1, alice.address())],
+            None,
+            None,
+            None,
+        )
+        .with_version(version);
+        BundleHistoryEvent::Received {
+            key: format!("received-{timestamp}"),
+            timestamp,
+            bundle: Box::new(bundle),
+            metadata: None,
+        }
+    }
+
+    fn block_included(timestamp: i64, block_number: u64) -> BundleHistoryEvent {
+        BundleHistoryEvent::BlockIncluded {
+            key: format!("block-{timestamp}"),
+            timestamp,
+            block_number,
+            block_hash: B256::ZERO,
+        }
+    }
+
+    #[test]
+    fn test_status_from_history() {
+        let bundle_id = Uuid::new_v5(&Uuid::NAMESPACE_OID, b"bundle");
+        assert!(BundleStatus::from_history(bundle_id, &BundleHistory::default()).is_none());
+
+        let mut history = BundleHistory {
+            history: vec![received(100, 0), received(150, 1)],
+        };
+        let status = BundleStatus::from_history(bundle_id, &history).unwrap();
+        assert_eq!(status.state, BundleState::Received);
+        assert_eq!(status.updated_at, 150);
+        assert_eq!(status.received_at, Some(100));
+        assert_eq!(status.version, 1);
+
+        history.history.push(BundleHistoryEvent::BuilderIncluded {
+            key: "builder".to_string(),
+            timestamp: 200,
+            builder: "builder".to_string(),
+            block_number: 10,
+            flashblock_index: 2,
+        });
+        let status = BundleStatus::from_history(bundle_id, &history).unwrap();
+        assert_eq!(status.state, BundleState::IncludedByBuilder);
+        assert_eq!(status.block_number, Some(10));
+
+        // events are ranked by progress, an inclusion written before the drop still wins
+        history.history.insert(0, block_included(300, 10));
+        history.history.push(BundleHistoryEvent::Dropped {
+            key: "dropped".to_string(),
+            timestamp: 400,
+            reason: DropReason::TimedOut,
+        });
+        let status = BundleStatus::from_history(bundle_id, &history).unwrap();
+        assert_eq!(status.state, BundleState::IncludedInBlock);
+        assert_eq!(status.updated_at, 300);
+        assert_eq!(status.drop_reason, None);
+    }
+
+    #[test]
+    fn test_cancelled_status() {
+        let bundle_id = Uuid::new_v5(&Uuid::NAMESPACE_OID, b"bundle");
+        let history = BundleHistory {
+            history: vec![
+                received(100, 0),
+                BundleHistoryEvent::Cancelled {
+                    key: "cancelled".to_string(),
+                    timestamp: 200,
+                },
+            ],
+        };
+        let status = BundleStatus::from_history(bundle_id, &history).unwrap();
+        assert_eq!(status.state, BundleState::Cancelled);
+        assert_eq!(status.updated_at, 200);
+        assert_eq!(status.received_at, Some(100));
+    }
+
+    #[test]
+    fn test_parse_bundle_id() {
+        let uuid = Uuid::new_v5(&Uuid::NAMESPACE_OID, b"bundle");
+        assert_eq!(parse_bundle_id(&uuid.to_string()), Ok(uuid));
+
+        let bundle_hash = B256::random();
+        assert_eq!(
+            parse_bundle_id(&bundle_hash.to_string()),
+            Ok(Uuid::new_v5(&Uuid::NAMESPACE_OID, bundle_hash.as_slice()))
+        );
+
+        assert!(parse_bundle_id("not-a-bundle-id").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_get_transaction_status() {
+        let bundle_id = Uuid::new_v5(&Uuid::NAMESPACE_OID, b"bundle");
+        let tx_hash = TxHash::random();
+        let mut store = InMemoryAuditStore::default();
+        store.bundles.insert(
+            bundle_id,
+            BundleHistory {
+                history: vec![received(100, 0), block_included(200, 42)],
+            },
+        );
+        store.transactions.insert(
+            tx_hash,
+            TransactionMetadata {
+                bundle_ids: vec![bundle_id],
+            },
+        );
+        let service = BundleStatusService::new(Arc::new(store));
+
+        let status = service
+            .get_bundle_status(bundle_id.to_string())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(status.state, BundleState::IncludedInBlock);
+        assert_eq!(status.block_number, Some(42));
+
+        let status = service
+            .get_transaction_status(tx_hash)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(status.tx_hash, tx_hash);
+        assert_eq!(status.bundles.len(), 1);
+        assert_eq!(status.bundles[0].bundle_id, bundle_id);
+
+        assert!(
+            service
+                .get_transaction_status(TxHash::random())
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            service
+                .get_bundle_status(Uuid::nil().to_string())
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+}
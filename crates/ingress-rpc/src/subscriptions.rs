@@ -0,0 +1,277 @@
+use jsonrpsee::{
+    PendingSubscriptionSink, SubscriptionSink,
+    core::{SubscriptionResult, async_trait},
+    proc_macros::rpc,
+};
+use rdkafka::ClientConfig;
+use rdkafka::consumer::StreamConsumer;
+use std::collections::HashSet;
+use tips_audit_lib::{BundleEvent, EventReader};
+use tips_core::kafka::load_kafka_config_from_file;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+use crate::errors::IngressError;
+
+#[rpc(server, namespace = "tips")]
+pub trait BundleSubscriptionApi {
+    /// Handler for: `tips_subscribeBundle`, streams the lifecycle events of a single bundle
+    #[subscription(
+        name = "subscribeBundle" => "bundleEvent",
+        unsubscribe = "unsubscribeBundle",
+        item = BundleEvent
+    )]
+    async fn subscribe_bundle(&self, bundle_id: Uuid) -> SubscriptionResult;
+
+    /// Handler for: `tips_subscribeAllBundles`, streams the lifecycle events of every bundle to
+    /// clients holding a firehose token
+    #[subscription(
+        name = "subscribeAllBundles" => "bundleEvent",
+        unsubscribe = "unsubscribeAllBundles",
+        item = BundleEvent
+    )]
+    async fn subscribe_all_bundles(&self, token: String) -> SubscriptionResult;
+}
+
+/// Streams bundle events to WebSocket subscribers as they are produced
+pub struct BundleSubscriptionService {
+    events: broadcast::Sender<BundleEvent>,
+    firehose_tokens: HashSet<String>,
+}
+
+impl BundleSubscriptionService {
+    /// The firehose subscription is disabled when `firehose_tokens` is empty
+    pub fn new(
+        events: broadcast::Sender<BundleEvent>,
+        firehose_tokens: impl IntoIterator<Item = String>,
+    ) -> Self {
+        Self {
+            events,
+            firehose_tokens: firehose_tokens.into_iter().collect(),
+        }
+    }
+}
+
+#[async_trait]
+impl BundleSubscriptionApiServer for BundleSubscriptionService {
+    async fn subscribe_bundle(
+        &self,
+        pending: PendingSubscriptionSink,
+        bundle_id: Uuid,
+    ) -> SubscriptionResult {
+        let events = self.events.subscribe();
+        let sink = pending.accept().await?;
+        forward_events(sink, events, |event| event.bundle_id() == bundle_id).await
+    }
+
+    async fn subscribe_all_bundles(
+        &self,
+        pending: PendingSubscriptionSink,
+        token: String,
+    ) -> SubscriptionResult {
+        if !self.firehose_tokens.contains(&token) {
+            pending.reject(IngressError::FirehoseUnauthorized).await;
+            return Ok(());
+        }
+        let events = self.events.subscribe();
+        let sink = pending.accept().await?;
+        forward_events(sink, events, |_| true).await
+    }
+}
+
+/// Sends the events matching `filter` to the subscriber until it unsubscribes
+async fn forward_events(
+    sink: SubscriptionSink,
+    mut events: broadcast::Receiver<BundleEvent>,
+    filter: impl Fn(&BundleEvent) -> bool,
+) -> SubscriptionResult {
+    loop {
+        let event = tokio::select! {
+            _ = sink.closed() => return Ok(()),
+            event = events.recv() => event,
+        };
+        match event {
+            Ok(event) if filter(&event) => {
+                let message = serde_json::value::to_raw_value(&event)?;
+                if sink.send(message).await.is_err() {
+                    return Ok(());
+                }
+            }
+            Ok(_) => {}
+            Err(RecvError::Lagged(skipped)) => {
+                warn!(
+                    message = "Bundle event subscriber lagged behind",
+                    subscription_id = ?sink.subscription_id(),
+                    skipped
+                );
+            }
+            Err(RecvError::Closed) => return Ok(()),
+        }
+    }
+}
+
+/// Publishes the inclusion and drop events read back from the audit log to the subscribers. The
+/// events ingress produces itself are already published from the audit channel.
+pub fn connect_audit_log_to_subscriptions<R>(mut reader: R, events: broadcast::Sender<BundleEvent>)
+where
+    R: EventReader + Send + 'static,
+{
+    tokio::spawn(async move {
+        loop {
+            match reader.read_event().await {
+                Ok(event) => {
+                    if is_produced_downstream(&event.event) {
+                        let _ = events.send(event.event);
+                    }
+                }
+                Err(e) => debug!(message = "Failed to read audit event", error = %e),
+            }
+        }
+    });
+}
+
+/// Creates the consumer the audit log is read back with. Every instance streams the events of all
+/// partitions to its own subscribers, so each one reads the topic in a consumer group of its own.
+pub fn create_audit_subscription_consumer(properties_file: &str) -> anyhow::Result<StreamConsumer> {
+    let mut client_config = ClientConfig::from_iter(load_kafka_config_from_file(properties_file)?);
+    let group_id = audit_subscription_group_id(client_config.get("group.id"));
+    client_config.set("group.id", group_id);
+    Ok(client_config.create()?)
+}
+
+/// The `group.id` of the properties, suffixed with an id unique to this process
+fn audit_subscription_group_id(group_id_prefix: Option<&str>) -> String {
+    format!(
+        "{}-{}",
+        group_id_prefix.unwrap_or("tips-ingress-audit-subscription"),
+        Uuid::new_v4()
+    )
+}
+
+/// Events produced after the bundle left ingress, by the builder or the chain
+const fn is_produced_downstream(event: &BundleEvent) -> bool {
+    matches!(
+        event,
+        BundleEvent::BuilderIncluded { .. }
+            | BundleEvent::BlockIncluded { .. }
+            | BundleEvent::Dropped { .. }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::FIREHOSE_UNAUTHORIZED_CODE;
+    use alloy_primitives::B256;
+    use jsonrpsee::core::server::MethodsError;
+    use tips_audit_lib::DropReason;
+
+    fn uuid(name: &str) -> Uuid {
+        Uuid::new_v5(&Uuid::NAMESPACE_OID, name.as_bytes())
+    }
+
+    fn block_included(bundle_id: Uuid, block_number: u64) -> BundleEvent {
+        BundleEvent::BlockIncluded {
+            bundle_id,
+            block_number,
+            block_hash: B256::ZERO,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_bundle() {
+        let (events, _) = broadcast::channel(16);
+        let module = BundleSubscriptionService::new(events.clone(), []).into_rpc();
+        let bundle_id = uuid("bundle");
+
+        let mut subscription = module
+            .subscribe_unbounded("tips_subscribeBundle", [bundle_id])
+            .await
+            .unwrap();
+
+        events.send(block_included(uuid("other"), 1)).unwrap();
+        events.send(block_included(bundle_id, 2)).unwrap();
+
+        let (event, _) = subscription.next::<BundleEvent>().await.unwrap().unwrap();
+        assert_eq!(event.bundle_id(), bundle_id);
+        assert!(matches!(
+            event,
+            BundleEvent::BlockIncluded {
+                block_number: 2,
+                ..
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_all_bundles() {
+        let (events, _) = broadcast::channel(16);
+        let module =
+            BundleSubscriptionService::new(events.clone(), ["secret".to_string()]).into_rpc();
+
+        let err = module
+            .subscribe_unbounded("tips_subscribeAllBundles", ["wrong"])
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, MethodsError::JsonRpc(err) if err.code() == FIREHOSE_UNAUTHORIZED_CODE)
+        );
+
+        let mut subscription = module
+            .subscribe_unbounded("tips_subscribeAllBundles", ["secret"])
+            .await
+            .unwrap();
+
+        let first = uuid("first");
+        let second = uuid("second");
+        events.send(block_included(first, 1)).unwrap();
+        events
+            .send(BundleEvent::Dropped {
+                bundle_id: second,
+                reason: DropReason::TimedOut,
+            })
+            .unwrap();
+
+        let (event, _) = subscription.next::<BundleEvent>().await.unwrap().unwrap();
+        assert_eq!(event.bundle_id(), first);
+        let (event, _) = subscription.next::<BundleEvent>().await.unwrap().unwrap();
+        assert_eq!(event.bundle_id(), second);
+    }
+
+    #[tokio::test]
+    async fn test_firehose_disabled_without_tokens() {
+        let (events, _) = broadcast::channel(16);
+        let module = BundleSubscriptionService::new(events, []).into_rpc();
+
+        assert!(
+            module
+                .subscribe_unbounded("tips_subscribeAllBundles", [""])
+                .await
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_is_produced_downstream() {
+        let bundle_id = uuid("bundle");
+        assert!(is_produced_downstream(&block_included(bundle_id, 1)));
+        assert!(is_produced_downstream(&BundleEvent::Dropped {
+            bundle_id,
+            reason: DropReason::TimedOut,
+        }));
+        assert!(!is_produced_downstream(&BundleEvent::Cancelled {
+            bundle_id
+        }));
+    }
+
+    #[test]
+    fn test_audit_subscription_group_id_is_unique() {
+        let first = audit_subscription_group_id(Some("ingress-audit-subscription"));
+        let second = audit_subscription_group_id(Some("ingress-audit-subscription"));
+        assert!(first.starts_with("ingress-audit-subscription-"));
+        assert_ne!(first, second);
+        assert!(audit_subscription_group_id(None).starts_with("tips-ingress-audit-subscription-"));
+    }
+}
@@ -0,0 +1,597 @@
+use alloy_consensus::Transaction;
+use alloy_consensus::transaction::Recovered;
+use alloy_primitives::{Address, B256, TxHash};
+use jsonrpsee::types::ErrorObjectOwned;
+use moka::future::Cache;
+use moka::ops::compute::Op;
+use op_alloy_consensus::OpTxEnvelope;
+use serde_json::{Value, json};
+use std::fmt;
+use tips_core::{AcceptedBundle, BundleExtensions, SearcherIdentity};
+use tokio::time::Duration;
+use uuid::Uuid;
+
+use crate::errors::{
+    BUNDLE_ALREADY_INCLUDED_CODE, INVALID_UUID_CODE, IngressError, NOT_BUNDLE_OWNER_CODE,
+    UNKNOWN_BUNDLE_CODE,
+};
+
+/// Who may cancel a live bundle
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BundleOwner {
+    /// The authenticated searcher that submitted the bundle, by identity id
+    Searcher(String),
+    /// Anyone holding the replacement UUID an anonymous client submitted the bundle with
+    ReplacementUuid,
+    /// Nobody, the bundle was submitted anonymously under the UUID derived from its hash, which
+    /// anyone who sees the bundle can compute
+    Nobody,
+}
+
+impl BundleOwner {
+    fn of(bundle: &AcceptedBundle) -> Self {
+        match (&bundle.identity, bundle.replacement_uuid) {
+            (Some(identity), _) => Self::Searcher(identity.id.clone()),
+            (None, Some(_)) => Self::ReplacementUuid,
+            (None, None) => Self::Nobody,
+        }
+    }
+
+    /// Whether the caller, authenticated as `identity` if any, may cancel the bundle
+    pub fn may_cancel(&self, identity: Option<&SearcherIdentity>) -> bool {
+        match self {
+            Self::Searcher(id) => identity.is_some_and(|identity| identity.id == *id),
+            Self::ReplacementUuid => true,
+            Self::Nobody => false,
+        }
+    }
+
+    /// Whether the caller may send a bundle under the same UUID, taking over the live bundle.
+    /// Anonymous bundles without a replacement UUID can only be resubmitted anonymously, so no
+    /// searcher can take them over and cancel them.
+    pub fn may_replace(&self, identity: Option<&SearcherIdentity>) -> bool {
+        match self {
+            Self::Nobody => identity.is_none(),
+            owner => owner.may_cancel(identity),
+        }
+    }
+}
+
+/// A bundle accepted by ingress that can still be replaced or cancelled
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LiveBundle {
+    pub bundle_hash: B256,
+    pub tx_hashes: Vec<TxHash>,
+    pub version: u64,
+    pub owner: BundleOwner,
+}
+
+impl LiveBundle {
+    fn new(bundle: &AcceptedBundle, version: u64) -> Self {
+        Self {
+            bundle_hash: bundle.bundle_hash(),
+            tx_hashes: bundle.txn_hashes(),
+            version,
+            owner: BundleOwner::of(bundle),
+        }
+    }
+}
+
+/// A raw transaction accepted by ingress that can be replaced by a transaction with the same
+/// sender and nonce
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LiveTransaction {
+    pub bundle_id: Uuid,
+    pub tx_hash: TxHash,
+    pub max_fee_per_gas: u128,
+    pub max_priority_fee_per_gas: u128,
+    pub version: u64,
+}
+
+impl LiveTransaction {
+    fn new(tx: &Recovered<OpTxEnvelope>, bundle_id: Uuid, version: u64) -> Self {
+        let (max_fee_per_gas, max_priority_fee_per_gas) = fees(tx);
+        Self {
+            bundle_id,
+            tx_hash: tx.tx_hash(),
+            max_fee_per_gas,
+            max_priority_fee_per_gas,
+            version,
+        }
+    }
+
+    /// Checks that `tx` pays at least `min_bump_percent` more than this transaction, on both the
+    /// max fee and the priority fee.
+    pub fn check_fee_bump(
+        &self,
+        tx: &Recovered<OpTxEnvelope>,
+        min_bump_percent: u64,
+    ) -> Result<(), IngressError> {
+        let bump = |fee: u128| fee.saturating_mul(100 + min_bump_percent as u128) / 100;
+        let (max_fee_per_gas, max_priority_fee_per_gas) = fees(tx);
+
+        if max_fee_per_gas < bump(self.max_fee_per_gas)
+            || max_priority_fee_per_gas < bump(self.max_priority_fee_per_gas)
+        {
+            return Err(IngressError::ReplacementUnderpriced {
+                tx_hash: self.tx_hash,
+                min_bump_percent,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// A raw transaction tracked before it is published
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransactionReservation {
+    /// The live transaction with the same sender and nonce it replaces, if any
+    pub replaced: Option<LiveTransaction>,
+    /// The transaction as tracked now, with the bundle and version to publish it under
+    pub reserved: LiveTransaction,
+}
+
+/// Max fee and priority fee of a transaction, legacy transactions pay their gas price for both
+fn fees(tx: &Recovered<OpTxEnvelope>) -> (u128, u128) {
+    let max_fee_per_gas = tx.max_fee_per_gas();
+    let max_priority_fee_per_gas = tx.max_priority_fee_per_gas().unwrap_or(max_fee_per_gas);
+    (max_fee_per_gas, max_priority_fee_per_gas)
+}
+
+/// Reasons an `eth_cancelBundle` request can be rejected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CancelBundleError {
+    /// The provided replacement UUID could not be parsed
+    InvalidUuid(String),
+    /// No live bundle is tracked under the UUID, it was never submitted, already cancelled or expired
+    UnknownBundle(Uuid),
+    /// At least one of the bundle's transactions has already landed on chain
+    AlreadyIncluded(Uuid),
+    /// The caller is not the searcher that submitted the bundle
+    NotOwner(Uuid),
+}
+
+impl fmt::Display for CancelBundleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidUuid(uuid) => write!(f, "Invalid replacement UUID: {uuid}"),
+            Self::UnknownBundle(uuid) => write!(f, "Unknown bundle: {uuid}"),
+            Self::AlreadyIncluded(uuid) => write!(f, "Bundle already included: {uuid}"),
+            Self::NotOwner(uuid) => write!(f, "Not the owner of bundle: {uuid}"),
+        }
+    }
+}
+
+impl std::error::Error for CancelBundleError {}
+
+impl CancelBundleError {
+    pub const fn code(&self) -> i32 {
+        match self {
+            Self::InvalidUuid(_) => INVALID_UUID_CODE,
+            Self::UnknownBundle(_) => UNKNOWN_BUNDLE_CODE,
+            Self::AlreadyIncluded(_) => BUNDLE_ALREADY_INCLUDED_CODE,
+            Self::NotOwner(_) => NOT_BUNDLE_OWNER_CODE,
+        }
+    }
+
+    pub fn data(&self) -> Option<Value> {
+        match self {
+            Self::InvalidUuid(_) => None,
+            Self::UnknownBundle(uuid) | Self::AlreadyIncluded(uuid) | Self::NotOwner(uuid) => {
+                Some(json!({ "replacementUuid": uuid.to_string() }))
+            }
+        }
+    }
+
+    pub fn into_rpc_err(self) -> ErrorObjectOwned {
+        IngressError::from(self).into()
+    }
+}
+
+/// Tracks the bundles accepted by ingress by their UUID, so they can be replaced or cancelled
+/// while they are still live. Raw transactions are additionally indexed by sender and nonce.
+/// Entries expire after the configured TTL.
+pub struct BundleTracker {
+    bundles: Cache<Uuid, LiveBundle>,
+    transactions: Cache<(Address, u64), LiveTransaction>,
+}
+
+impl BundleTracker {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            bundles: Cache::builder().time_to_live(ttl).build(),
+            transactions: Cache::builder().time_to_live(ttl).build(),
+        }
+    }
+
+    pub async fn track(&self, bundle: &AcceptedBundle) {
+        self.bundles
+            .insert(*bundle.uuid(), LiveBundle::new(bundle, bundle.version))
+            .await;
+    }
+
+    pub async fn get(&self, uuid: &Uuid) -> Option<LiveBundle> {
+        self.bundles.get(uuid).await
+    }
+
+    /// Tracks a bundle before it is published and returns the version to publish it with: one
+    /// past the live version for a bundle sent with a replacement UUID, or zero when nothing is
+    /// live under the UUID. The version is reserved atomically, so concurrent replacements of the
+    /// same UUID get distinct versions. Fails without touching the live bundle when the caller
+    /// doesn't own it. A bundle that then fails to publish stays tracked, and the next
+    /// replacement skips its version.
+    pub async fn reserve(&self, bundle: &AcceptedBundle) -> Result<u64, CancelBundleError> {
+        let uuid = *bundle.uuid();
+        let mut reserved = Err(CancelBundleError::NotOwner(uuid));
+        self.bundles
+            .entry(uuid)
+            .and_upsert_with(|entry| {
+                let live_bundle = match entry.map(|entry| entry.into_value()) {
+                    Some(live_bundle)
+                        if !live_bundle.owner.may_replace(bundle.identity.as_ref()) =>
+                    {
+                        live_bundle
+                    }
+                    live_bundle => {
+                        let version = match (live_bundle, bundle.replacement_uuid) {
+                            (Some(live_bundle), Some(_)) => live_bundle.version + 1,
+                            _ => bundle.version,
+                        };
+                        reserved = Ok(version);
+                        LiveBundle::new(bundle, version)
+                    }
+                };
+                std::future::ready(live_bundle)
+            })
+            .await;
+        reserved
+    }
+
+    pub async fn remove(&self, uuid: &Uuid) -> Option<LiveBundle> {
+        self.bundles.remove(uuid).await
+    }
+
+    /// Tracks a raw transaction by sender and nonce before it is published. A transaction with
+    /// the same sender and nonce as a live one replaces it, keeping its bundle and taking the
+    /// version after it, once it pays `min_bump_percent` more. Otherwise it's tracked under
+    /// `bundle_id` at version zero. The version is reserved atomically, so concurrent
+    /// replacements of the same transaction get distinct versions. Fails without touching the
+    /// live transaction when the replacement is underpriced.
+    pub async fn reserve_transaction(
+        &self,
+        tx: &Recovered<OpTxEnvelope>,
+        bundle_id: Uuid,
+        min_bump_percent: u64,
+    ) -> Result<TransactionReservation, IngressError> {
+        let mut reservation = None;
+        self.transactions
+            .entry((tx.signer(), tx.nonce()))
+            .and_upsert_with(|entry| {
+                let live_transaction = match entry.map(|entry| entry.into_value()) {
+                    Some(live_transaction) if live_transaction.tx_hash != tx.tx_hash() => {
+                        match live_transaction.check_fee_bump(tx, min_bump_percent) {
+                            Ok(()) => {
+                                let reserved = LiveTransaction::new(
+                                    tx,
+                                    live_transaction.bundle_id,
+                                    live_transaction.version + 1,
+                                );
+                                reservation = Some(Ok(TransactionReservation {
+                                    replaced: Some(live_transaction),
+                                    reserved: reserved.clone(),
+                                }));
+                                reserved
+                            }
+                            Err(e) => {
+                                reservation = Some(Err(e));
+                                live_transaction
+                            }
+                        }
+                    }
+                    _ => {
+                        let reserved = LiveTransaction::new(tx, bundle_id, 0);
+                        reservation = Some(Ok(TransactionReservation {
+                            replaced: None,
+                            reserved: reserved.clone(),
+                        }));
+                        reserved
+                    }
+                };
+                std::future::ready(live_transaction)
+            })
+            .await;
+        reservation.expect("the upsert closure always runs")
+    }
+
+    /// Releases the reservation of a transaction that failed to publish, so only published
+    /// transactions can be replaced. The transaction it replaced is live again. Does nothing
+    /// once another transaction replaced it in the meantime.
+    pub async fn release_transaction(
+        &self,
+        tx: &Recovered<OpTxEnvelope>,
+        reservation: TransactionReservation,
+    ) {
+        self.transactions
+            .entry((tx.signer(), tx.nonce()))
+            .and_compute_with(|entry| {
+                let op = match entry.map(|entry| entry.into_value()) {
+                    Some(live_transaction) if live_transaction == reservation.reserved => {
+                        match reservation.replaced {
+                            Some(replaced) => Op::Put(replaced),
+                            None => Op::Remove,
+                        }
+                    }
+                    _ => Op::Nop,
+                };
+                std::future::ready(op)
+            })
+            .await;
+    }
+
+    pub async fn get_transaction(&self, sender: Address, nonce: u64) -> Option<LiveTransaction> {
+        self.transactions.get(&(sender, nonce)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_consensus::transaction::SignerRecoverable;
+    use alloy_signer_local::PrivateKeySigner;
+    use std::sync::Arc;
+    use tips_core::AuthMethod;
+    use tips_core::test_utils::{create_test_bundle, create_transaction};
+    use tokio::task::JoinSet;
+
+    #[tokio::test]
+    async fn test_track_and_remove_bundle() {
+        let tracker = BundleTracker::new(Duration::from_secs(60));
+        let alice = PrivateKeySigner::random();
+        let tx = create_transaction(alice.clone(), 1, alice.address());
+        let bundle = create_test_bundle(vec![tx.clone()], None, None, None);
+
+        assert_eq!(tracker.get(bundle.uuid()).await, None);
+
+        tracker.track(&bundle).await;
+        let live_bundle = tracker.get(bundle.uuid()).await.unwrap();
+        assert_eq!(live_bundle.bundle_hash, bundle.bundle_hash());
+        assert_eq!(live_bundle.tx_hashes, vec![tx.tx_hash()]);
+
+        assert_eq!(tracker.remove(bundle.uuid()).await, Some(live_bundle));
+        assert_eq!(tracker.get(bundle.uuid()).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_reserve_version() {
+        let tracker = BundleTracker::new(Duration::from_secs(60));
+        let alice = PrivateKeySigner::random();
+        let mut bundle = create_test_bundle(
+            vec![create_transaction(alice.clone(), 1, alice.address())],
+            None,
+            None,
+            None,
+        );
+        bundle.replacement_uuid = Some(Uuid::new_v4());
+
+        assert_eq!(tracker.reserve(&bundle).await, Ok(0));
+        assert_eq!(tracker.reserve(&bundle).await, Ok(1));
+        assert_eq!(tracker.reserve(&bundle).await, Ok(2));
+        assert_eq!(tracker.get(bundle.uuid()).await.unwrap().version, 2);
+
+        tracker.remove(bundle.uuid()).await;
+        assert_eq!(tracker.reserve(&bundle).await, Ok(0));
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_reservations_get_distinct_versions() {
+        let tracker = Arc::new(BundleTracker::new(Duration::from_secs(60)));
+        let alice = PrivateKeySigner::random();
+        let mut bundle = create_test_bundle(
+            vec![create_transaction(alice.clone(), 1, alice.address())],
+            None,
+            None,
+            None,
+        );
+        bundle.replacement_uuid = Some(Uuid::new_v4());
+
+        let mut reservations = JoinSet::new();
+        for _ in 0..16 {
+            let tracker = tracker.clone();
+            let bundle = bundle.clone();
+            reservations.spawn(async move { tracker.reserve(&bundle).await.unwrap() });
+        }
+        let mut versions = reservations.join_all().await;
+        versions.sort_unstable();
+        assert_eq!(versions, (0..16).collect::<Vec<_>>());
+    }
+
+    fn recovered_transaction(
+        signer: &PrivateKeySigner,
+        nonce: u64,
+        to: Address,
+    ) -> Recovered<OpTxEnvelope> {
+        create_transaction(signer.clone(), nonce, to)
+            .try_into_recovered()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_reserve_transaction() {
+        let tracker = BundleTracker::new(Duration::from_secs(60));
+        let alice = PrivateKeySigner::random();
+        let bundle_id = Uuid::new_v4();
+        let tx = recovered_transaction(&alice, 7, alice.address());
+
+        assert_eq!(tracker.get_transaction(alice.address(), 7).await, None);
+
+        let reservation = tracker
+            .reserve_transaction(&tx, bundle_id, 10)
+            .await
+            .unwrap();
+        assert_eq!(reservation.replaced, None);
+        let live_transaction = tracker.get_transaction(alice.address(), 7).await.unwrap();
+        assert_eq!(live_transaction, reservation.reserved);
+        assert_eq!(live_transaction.bundle_id, bundle_id);
+        assert_eq!(live_transaction.tx_hash, tx.tx_hash());
+        assert_eq!(live_transaction.max_fee_per_gas, 200);
+        assert_eq!(live_transaction.max_priority_fee_per_gas, 100);
+        assert_eq!(live_transaction.version, 0);
+        assert_eq!(tracker.get_transaction(alice.address(), 8).await, None);
+
+        // same fees, below the required bump
+        let replacement = recovered_transaction(&alice, 7, Address::ZERO);
+        assert!(matches!(
+            tracker
+                .reserve_transaction(&replacement, Uuid::new_v4(), 10)
+                .await,
+            Err(IngressError::ReplacementUnderpriced { .. })
+        ));
+        assert_eq!(
+            tracker.get_transaction(alice.address(), 7).await,
+            Some(live_transaction.clone())
+        );
+
+        let reservation = tracker
+            .reserve_transaction(&replacement, Uuid::new_v4(), 0)
+            .await
+            .unwrap();
+        assert_eq!(reservation.replaced, Some(live_transaction.clone()));
+        assert_eq!(reservation.reserved.bundle_id, bundle_id);
+        assert_eq!(reservation.reserved.tx_hash, replacement.tx_hash());
+        assert_eq!(reservation.reserved.version, 1);
+
+        // a replacement that failed to publish makes the replaced transaction live again
+        tracker.release_transaction(&replacement, reservation).await;
+        assert_eq!(
+            tracker.get_transaction(alice.address(), 7).await,
+            Some(live_transaction)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_release_transaction_keeps_later_replacement() {
+        let tracker = BundleTracker::new(Duration::from_secs(60));
+        let alice = PrivateKeySigner::random();
+        let tx = recovered_transaction(&alice, 1, alice.address());
+        let first = recovered_transaction(&alice, 1, Address::ZERO);
+        let second = recovered_transaction(&alice, 1, Address::repeat_byte(1));
+
+        tracker
+            .reserve_transaction(&tx, Uuid::new_v4(), 0)
+            .await
+            .unwrap();
+        let reservation = tracker
+            .reserve_transaction(&first, Uuid::new_v4(), 0)
+            .await
+            .unwrap();
+        let later = tracker
+            .reserve_transaction(&second, Uuid::new_v4(), 0)
+            .await
+            .unwrap();
+
+        tracker.release_transaction(&first, reservation).await;
+        assert_eq!(
+            tracker.get_transaction(alice.address(), 1).await,
+            Some(later.reserved)
+        );
+
+        // releasing a new transaction stops tracking it
+        let other = recovered_transaction(&alice, 2, alice.address());
+        let reservation = tracker
+            .reserve_transaction(&other, Uuid::new_v4(), 0)
+            .await
+            .unwrap();
+        tracker.release_transaction(&other, reservation).await;
+        assert_eq!(tracker.get_transaction(alice.address(), 2).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_transaction_reservations_get_distinct_versions() {
+        let tracker = Arc::new(BundleTracker::new(Duration::from_secs(60)));
+        let alice = PrivateKeySigner::random();
+        let bundle_id = Uuid::new_v4();
+
+        let mut reservations = JoinSet::new();
+        for _ in 0..16 {
+            let tracker = tracker.clone();
+            // same sender and nonce, each one a distinct transaction
+            let tx = recovered_transaction(&alice, 1, PrivateKeySigner::random().address());
+            reservations.spawn(async move {
+                tracker
+                    .reserve_transaction(&tx, bundle_id, 0)
+                    .await
+                    .unwrap()
+                    .reserved
+            });
+        }
+        let reserved = reservations.join_all().await;
+        assert!(
+            reserved
+                .iter()
+                .all(|reserved| reserved.bundle_id == bundle_id)
+        );
+        let mut versions: Vec<_> = reserved.iter().map(|reserved| reserved.version).collect();
+        versions.sort_unstable();
+        assert_eq!(versions, (0..16).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_check_fee_bump() {
+        let alice = PrivateKeySigner::random();
+        // create_transaction pays a max fee of 200 and a priority fee of 100
+        let tx = create_transaction(alice.clone(), 1, alice.address())
+            .try_into_recovered()
+            .unwrap();
+
+        let live_transaction = |max_fee_per_gas, max_priority_fee_per_gas| LiveTransaction {
+            bundle_id: Uuid::nil(),
+            tx_hash: TxHash::ZERO,
+            max_fee_per_gas,
+            max_priority_fee_per_gas,
+            version: 0,
+        };
+
+        assert!(live_transaction(180, 90).check_fee_bump(&tx, 10).is_ok());
+        assert!(live_transaction(183, 90).check_fee_bump(&tx, 10).is_err());
+        assert!(live_transaction(180, 92).check_fee_bump(&tx, 10).is_err());
+        assert!(live_transaction(200, 100).check_fee_bump(&tx, 0).is_ok());
+    }
+
+    #[test]
+    fn test_bundle_owner() {
+        let identity = |id: &str| SearcherIdentity {
+            id: id.to_string(),
+            auth: AuthMethod::ApiKey,
+            signer: None,
+            tier: "default".to_string(),
+            priority: 0,
+        };
+        let alice = PrivateKeySigner::random();
+        let bundle = create_test_bundle(
+            vec![create_transaction(alice.clone(), 1, alice.address())],
+            None,
+            None,
+            None,
+        );
+
+        // anonymous bundles under the UUID derived from their hash can't be cancelled
+        let owner = BundleOwner::of(&bundle);
+        assert_eq!(owner, BundleOwner::Nobody);
+        assert!(!owner.may_cancel(None));
+        assert!(!owner.may_cancel(Some(&identity("searcher"))));
+        assert!(owner.may_replace(None));
+        assert!(!owner.may_replace(Some(&identity("searcher"))));
+
+        let mut with_uuid = bundle.clone();
+        with_uuid.replacement_uuid = Some(Uuid::new_v4());
+        assert!(BundleOwner::of(&with_uuid).may_cancel(None));
+
+        let owned = bundle.with_identity(Some(identity("searcher")));
+        let owner = BundleOwner::of(&owned);
+        assert_eq!(owner, BundleOwner::Searcher("searcher".to_string()));
+        assert!(owner.may_cancel(Some(&identity("searcher"))));
+        assert!(!owner.may_cancel(Some(&identity("other"))));
+        assert!(!owner.may_cancel(None));
+        assert!(!owner.may_replace(Some(&identity("other"))));
+    }
+}
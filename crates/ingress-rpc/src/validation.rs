@@ -1,20 +1,37 @@
+use alloy_consensus::Transaction;
+use alloy_consensus::private::alloy_eips::eip7623::{tokens_in_calldata, transaction_floor_cost};
 use alloy_consensus::private::alloy_eips::{BlockId, BlockNumberOrTag};
-use alloy_primitives::{Address, B256, U256};
+use alloy_consensus::transaction::Recovered;
+use alloy_op_hardforks::{OpChainHardforks, OpHardforks};
+use alloy_primitives::{Address, B256, Bytes, TxHash, U256};
 use alloy_provider::{Provider, RootProvider};
 use async_trait::async_trait;
 use base_reth_rpc_types::{EthApiError, SignError, extract_l1_info_from_tx};
 use jsonrpsee::core::RpcResult;
+use jsonrpsee::types::ErrorObjectOwned;
+use op_alloy_consensus::OpTxEnvelope;
 use op_alloy_network::Optimism;
+use op_revm::OpSpecId;
 use op_revm::l1block::L1BlockInfo;
-use std::collections::HashSet;
+use serde::{Serialize, Serializer};
+use serde_json::{Value, json};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
-use tips_core::Bundle;
+use tips_core::{Bundle, MeterBundleResponse};
 use tokio::time::Instant;
 use tracing::warn;
 
+use crate::Config;
+use crate::errors::{
+    DEPOSIT_TRANSACTION_CODE, GAS_LIMIT_EXCEEDS_BLOCK_GAS_LIMIT_CODE, INITCODE_TOO_LARGE_CODE,
+    INSUFFICIENT_FUNDS_CODE, INTRINSIC_GAS_TOO_LOW_CODE, IngressError, NONCE_TOO_LOW_CODE,
+    OVERSIZED_DATA_CODE, TIP_ABOVE_FEE_CAP_CODE, TX_TYPE_NOT_ALLOWED_CODE,
+    UNPROTECTED_TRANSACTION_CODE, WRONG_CHAIN_ID_CODE,
+};
 use crate::metrics::record_histogram;
-
-const MAX_BUNDLE_GAS: u64 = 25_000_000;
+use crate::simulation::PRIMARY_ENDPOINT;
 
 /// Account info for a given address
 pub struct AccountInfo {
@@ -38,7 +55,11 @@ impl AccountInfoLookup for RootProvider<Optimism> {
             .get_account(address)
             .await
             .map_err(|_| EthApiError::Signing(SignError::NoAccount))?;
-        record_histogram(start.elapsed(), "eth_getAccount".to_string());
+        record_histogram(
+            start.elapsed(),
+            "eth_getAccount".to_string(),
+            PRIMARY_ENDPOINT,
+        );
 
         Ok(AccountInfo {
             balance: account.balance,
@@ -48,16 +69,22 @@ impl AccountInfoLookup for RootProvider<Optimism> {
     }
 }
 
+/// L1 block info of the latest L2 block, with the timestamp of that block
+pub struct HeadL1BlockInfo {
+    pub l1_block_info: L1BlockInfo,
+    pub timestamp: u64,
+}
+
 /// Interface for fetching L1 block info for a given block number
 #[async_trait]
 pub trait L1BlockInfoLookup: Send + Sync {
-    async fn fetch_l1_block_info(&self) -> RpcResult<L1BlockInfo>;
+    async fn fetch_l1_block_info(&self) -> RpcResult<HeadL1BlockInfo>;
 }
 
 /// Implementation of the `L1BlockInfoLookup` trait for the `RootProvider`
 #[async_trait]
 impl L1BlockInfoLookup for RootProvider<Optimism> {
-    async fn fetch_l1_block_info(&self) -> RpcResult<L1BlockInfo> {
+    async fn fetch_l1_block_info(&self) -> RpcResult<HeadL1BlockInfo> {
         let start = Instant::now();
         let block = self
             .get_block(BlockId::Number(BlockNumberOrTag::Latest))
@@ -71,7 +98,11 @@ impl L1BlockInfoLookup for RootProvider<Optimism> {
                 warn!(message = "empty latest block returned");
                 EthApiError::InternalEthError.into_rpc_err()
             })?;
-        record_histogram(start.elapsed(), "eth_getBlockByNumber".to_string());
+        record_histogram(
+            start.elapsed(),
+            "eth_getBlockByNumber".to_string(),
+            PRIMARY_ENDPOINT,
+        );
 
         let txs = block.transactions.clone();
         let first_tx = txs.first_transaction().ok_or_else(|| {
@@ -79,87 +110,761 @@ impl L1BlockInfoLookup for RootProvider<Optimism> {
             EthApiError::InternalEthError.into_rpc_err()
         })?;
 
-        Ok(extract_l1_info_from_tx(&first_tx.clone()).map_err(|e| {
+        let l1_block_info = extract_l1_info_from_tx(&first_tx.clone()).map_err(|e| {
             warn!(message = "failed to extract l1_info from tx", err = %e);
             EthApiError::InternalEthError.into_rpc_err()
-        })?)
+        })?;
+        Ok(HeadL1BlockInfo {
+            l1_block_info,
+            timestamp: block.header.timestamp,
+        })
     }
 }
 
-/// Helper function to validate propeties of a bundle. A bundle is valid if it satisfies the following criteria:
-/// - The bundle's max_timestamp is not more than 1 hour in the future
-/// - The bundle's gas limit is not greater than the maximum allowed gas limit
-/// - The bundle can only contain 3 transactions at once
-/// - Partial transaction dropping is not supported, `dropping_tx_hashes` must be empty
-/// - revert protection is not supported, all transaction hashes must be in `reverting_tx_hashes`
-pub fn validate_bundle(bundle: &Bundle, bundle_gas: u64, tx_hashes: Vec<B256>) -> RpcResult<()> {
-    // Don't allow bundles to be submitted over 1 hour into the future
-    // TODO: make the window configurable
-    let valid_timestamp_window = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_secs()
-        + Duration::from_secs(3600).as_secs();
-    if let Some(max_timestamp) = bundle.max_timestamp
-        && max_timestamp > valid_timestamp_window
-    {
-        return Err(EthApiError::InvalidParams(
-            "Bundle cannot be more than 1 hour in the future".into(),
-        )
-        .into_rpc_err());
+/// Hardfork schedule of the OP Stack chains known to ingress, by chain id
+pub fn chain_hardforks(chain_id: u64) -> Option<OpChainHardforks> {
+    match chain_id {
+        10 => Some(OpChainHardforks::op_mainnet()),
+        11_155_420 => Some(OpChainHardforks::op_sepolia()),
+        8453 => Some(OpChainHardforks::base_mainnet()),
+        84_532 => Some(OpChainHardforks::base_sepolia()),
+        _ => None,
     }
+}
 
-    // Check max gas limit for the entire bundle
-    if bundle_gas > MAX_BUNDLE_GAS {
-        return Err(
-            EthApiError::InvalidParams("Bundle gas limit exceeds maximum allowed".into())
-                .into_rpc_err(),
-        );
+/// The spec the L1 data fee is computed with, from the hardforks active at `timestamp`.
+/// Chains without a known schedule, like local devnets, are assumed to run the latest one.
+/// Hardforks after Isthmus keep its L1 data fee formula.
+pub fn l1_fee_spec(hardforks: Option<&OpChainHardforks>, timestamp: u64) -> OpSpecId {
+    let Some(hardforks) = hardforks else {
+        return OpSpecId::ISTHMUS;
+    };
+    if hardforks.is_isthmus_active_at_timestamp(timestamp) {
+        OpSpecId::ISTHMUS
+    } else if hardforks.is_holocene_active_at_timestamp(timestamp) {
+        OpSpecId::HOLOCENE
+    } else if hardforks.is_granite_active_at_timestamp(timestamp) {
+        OpSpecId::GRANITE
+    } else if hardforks.is_fjord_active_at_timestamp(timestamp) {
+        OpSpecId::FJORD
+    } else if hardforks.is_ecotone_active_at_timestamp(timestamp) {
+        OpSpecId::ECOTONE
+    } else if hardforks.is_regolith_active_at_timestamp(timestamp) {
+        OpSpecId::REGOLITH
+    } else {
+        OpSpecId::BEDROCK
     }
+}
 
-    // Can only provide 3 transactions at once
-    if bundle.txs.len() > 3 {
-        return Err(
-            EthApiError::InvalidParams("Bundle can only contain 3 transactions".into())
-                .into_rpc_err(),
-        );
+/// Maximum initcode size of a contract creation transaction (EIP-3860)
+pub const MAX_INITCODE_SIZE: usize = 2 * 24_576;
+
+const TX_GAS: u64 = 21_000;
+const TX_CREATE_GAS: u64 = 53_000;
+const TX_DATA_ZERO_GAS: u64 = 4;
+const TX_DATA_NON_ZERO_GAS: u64 = 16;
+const INITCODE_WORD_GAS: u64 = 2;
+const ACCESS_LIST_ADDRESS_GAS: u64 = 2_400;
+const ACCESS_LIST_STORAGE_KEY_GAS: u64 = 1_900;
+const PER_EMPTY_ACCOUNT_GAS: u64 = 25_000;
+
+/// Reasons a transaction is rejected before it is metered
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TxValidationError {
+    /// The transaction is signed for a different chain than the one ingress serves
+    WrongChainId { expected: u64, actual: u64 },
+    /// A legacy transaction without EIP-155 replay protection
+    UnprotectedTransaction,
+    /// The transaction's nonce is below the sender's account nonce
+    NonceTooLow { tx: u64, state: u64 },
+    /// The sender can't cover the transaction's execution and L1 data fee
+    InsufficientFunds { cost: U256, balance: U256 },
+    /// Deposit transactions are only created by the sequencer
+    DepositTransaction,
+    /// The transaction type is not allowed for this kind of bundle
+    TxTypeNotAllowed { tx_type: TxType, kind: BundleKind },
+    /// The encoded transaction is larger than the max transaction size
+    OversizedData { size: usize, max: usize },
+    /// The gas limit doesn't cover the transaction's intrinsic gas
+    IntrinsicGasTooLow { gas_limit: u64, intrinsic_gas: u64 },
+    /// The initcode of a contract creation exceeds `MAX_INITCODE_SIZE`
+    InitcodeTooLarge { size: usize, max: usize },
+    /// The priority fee is higher than the max fee
+    TipAboveFeeCap {
+        max_priority_fee_per_gas: u128,
+        max_fee_per_gas: u128,
+    },
+    /// The gas limit is above the block gas limit, so the transaction can never be included
+    GasLimitExceedsBlockGasLimit {
+        gas_limit: u64,
+        block_gas_limit: u64,
+    },
+}
+
+impl fmt::Display for TxValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WrongChainId { expected, actual } => write!(
+                f,
+                "Invalid chain id: transaction is signed for chain {actual}, expected {expected}"
+            ),
+            Self::UnprotectedTransaction => write!(
+                f,
+                "Transactions without EIP-155 replay protection are not allowed"
+            ),
+            Self::NonceTooLow { tx, state } => write!(
+                f,
+                "Nonce too low: transaction nonce {tx} is below account nonce {state}"
+            ),
+            Self::InsufficientFunds { cost, balance } => write!(
+                f,
+                "Insufficient funds for gas * price + value + l1 fee: balance {balance}, cost {cost}"
+            ),
+            Self::DepositTransaction => write!(f, "Deposit transactions are not allowed"),
+            Self::TxTypeNotAllowed { tx_type, kind } => write!(
+                f,
+                "Transaction type {tx_type} is not allowed in a {}",
+                kind.to_string().to_lowercase()
+            ),
+            Self::OversizedData { size, max } => write!(
+                f,
+                "Oversized data: transaction size {size} exceeds max size {max}"
+            ),
+            Self::IntrinsicGasTooLow {
+                gas_limit,
+                intrinsic_gas,
+            } => write!(
+                f,
+                "Intrinsic gas too low: gas limit {gas_limit} is below intrinsic gas {intrinsic_gas}"
+            ),
+            Self::InitcodeTooLarge { size, max } => write!(
+                f,
+                "Max initcode size exceeded: initcode size {size} exceeds max size {max}"
+            ),
+            Self::TipAboveFeeCap {
+                max_priority_fee_per_gas,
+                max_fee_per_gas,
+            } => write!(
+                f,
+                "Max priority fee per gas higher than max fee per gas: {max_priority_fee_per_gas} > {max_fee_per_gas}"
+            ),
+            Self::GasLimitExceedsBlockGasLimit {
+                gas_limit,
+                block_gas_limit,
+            } => write!(
+                f,
+                "Exceeds block gas limit: gas limit {gas_limit} > block gas limit {block_gas_limit}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TxValidationError {}
+
+impl TxValidationError {
+    pub const fn code(&self) -> i32 {
+        match self {
+            Self::WrongChainId { .. } => WRONG_CHAIN_ID_CODE,
+            Self::UnprotectedTransaction => UNPROTECTED_TRANSACTION_CODE,
+            Self::NonceTooLow { .. } => NONCE_TOO_LOW_CODE,
+            Self::InsufficientFunds { .. } => INSUFFICIENT_FUNDS_CODE,
+            Self::DepositTransaction => DEPOSIT_TRANSACTION_CODE,
+            Self::TxTypeNotAllowed { .. } => TX_TYPE_NOT_ALLOWED_CODE,
+            Self::OversizedData { .. } => OVERSIZED_DATA_CODE,
+            Self::IntrinsicGasTooLow { .. } => INTRINSIC_GAS_TOO_LOW_CODE,
+            Self::InitcodeTooLarge { .. } => INITCODE_TOO_LARGE_CODE,
+            Self::TipAboveFeeCap { .. } => TIP_ABOVE_FEE_CAP_CODE,
+            Self::GasLimitExceedsBlockGasLimit { .. } => GAS_LIMIT_EXCEEDS_BLOCK_GAS_LIMIT_CODE,
+        }
+    }
+
+    /// The values the transaction was rejected on, returned as the JSON-RPC error `data`
+    pub fn data(&self) -> Option<Value> {
+        match self {
+            Self::WrongChainId { expected, actual } => Some(json!({
+                "expected": expected,
+                "actual": actual,
+            })),
+            Self::NonceTooLow { tx, state } => Some(json!({
+                "txNonce": tx,
+                "accountNonce": state,
+            })),
+            Self::InsufficientFunds { cost, balance } => Some(json!({
+                "cost": cost,
+                "balance": balance,
+            })),
+            Self::TxTypeNotAllowed { tx_type, .. } => Some(json!({
+                "txType": tx_type.to_string(),
+            })),
+            Self::OversizedData { size, max } | Self::InitcodeTooLarge { size, max } => {
+                Some(json!({
+                    "size": size,
+                    "maxSize": max,
+                }))
+            }
+            Self::IntrinsicGasTooLow {
+                gas_limit,
+                intrinsic_gas,
+            } => Some(json!({
+                "gasLimit": gas_limit,
+                "intrinsicGas": intrinsic_gas,
+            })),
+            Self::TipAboveFeeCap {
+                max_priority_fee_per_gas,
+                max_fee_per_gas,
+            } => Some(json!({
+                "maxPriorityFeePerGas": max_priority_fee_per_gas.to_string(),
+                "maxFeePerGas": max_fee_per_gas.to_string(),
+            })),
+            Self::GasLimitExceedsBlockGasLimit {
+                gas_limit,
+                block_gas_limit,
+            } => Some(json!({
+                "gasLimit": gas_limit,
+                "blockGasLimit": block_gas_limit,
+            })),
+            Self::UnprotectedTransaction | Self::DepositTransaction => None,
+        }
+    }
+
+    pub fn into_rpc_err(self) -> ErrorObjectOwned {
+        IngressError::from(self).into()
+    }
+}
+
+/// Validates that a transaction is signed for the configured chain. Legacy transactions without
+/// a chain id are only accepted when `allow_unprotected` is set.
+pub fn validate_chain_id<T: Transaction>(
+    txn: &T,
+    chain_id: u64,
+    allow_unprotected: bool,
+) -> Result<(), TxValidationError> {
+    match txn.chain_id() {
+        Some(actual) if actual != chain_id => Err(TxValidationError::WrongChainId {
+            expected: chain_id,
+            actual,
+        }),
+        Some(_) => Ok(()),
+        None if allow_unprotected => Ok(()),
+        None => Err(TxValidationError::UnprotectedTransaction),
+    }
+}
+
+/// Intrinsic gas of a transaction: the base cost, calldata, initcode words, access list and
+/// authorization list, and at least the EIP-7623 calldata floor
+pub fn intrinsic_gas<T: Transaction>(txn: &T) -> u64 {
+    let input = txn.input();
+    let zero_bytes = input.iter().filter(|byte| **byte == 0).count() as u64;
+    let non_zero_bytes = input.len() as u64 - zero_bytes;
+
+    let mut gas = if txn.is_create() {
+        TX_CREATE_GAS + INITCODE_WORD_GAS * (input.len() as u64).div_ceil(32)
+    } else {
+        TX_GAS
+    };
+    gas += zero_bytes * TX_DATA_ZERO_GAS + non_zero_bytes * TX_DATA_NON_ZERO_GAS;
+
+    if let Some(access_list) = txn.access_list() {
+        for item in access_list.iter() {
+            gas += ACCESS_LIST_ADDRESS_GAS
+                + ACCESS_LIST_STORAGE_KEY_GAS * item.storage_keys.len() as u64;
+        }
+    }
+    if let Some(authorizations) = txn.authorization_count() {
+        gas += PER_EMPTY_ACCOUNT_GAS * authorizations;
+    }
+
+    gas.max(transaction_floor_cost(tokens_in_calldata(input)))
+}
+
+/// Validates a transaction against its sender's account state, before it is metered:
+/// - The transaction's nonce is not below the account nonce
+/// - The balance covers `gas_limit * max_fee_per_gas + value` plus the L1 data fee for the
+///   transaction's encoded bytes, computed with `spec_id`
+pub fn validate_tx<T: Transaction>(
+    account: &AccountInfo,
+    txn: &T,
+    data: &[u8],
+    l1_block_info: &mut L1BlockInfo,
+    spec_id: OpSpecId,
+) -> Result<(), TxValidationError> {
+    validate_nonce(account, txn)?;
+    validate_balance(account, tx_cost(txn, data, l1_block_info, spec_id))
+}
+
+/// Validates the transactions of a bundle against their senders' account state, like
+/// [`validate_tx`]. The balance of a sender must cover all of its transactions in the bundle
+/// together. Senders receiving value from an earlier transaction of the bundle only get their
+/// nonces checked, as their balance depends on its execution. Senders missing from `accounts`
+/// are not checked.
+pub fn validate_bundle_txs<T: Transaction>(
+    accounts: &HashMap<Address, AccountInfo>,
+    txs: &[(Recovered<T>, Bytes)],
+    l1_block_info: &mut L1BlockInfo,
+    spec_id: OpSpecId,
+) -> Result<(), TxValidationError> {
+    let mut spent: HashMap<Address, U256> = HashMap::new();
+    let mut funded = HashSet::new();
+    for (txn, data) in txs {
+        let sender = txn.signer();
+        if let Some(account) = accounts.get(&sender) {
+            validate_nonce(account, txn.inner())?;
+            if !funded.contains(&sender) {
+                let total = spent.entry(sender).or_default();
+                *total = total.saturating_add(tx_cost(txn.inner(), data, l1_block_info, spec_id));
+                validate_balance(account, *total)?;
+            }
+        }
+
+        if !txn.inner().value().is_zero()
+            && let Some(to) = txn.inner().to()
+        {
+            funded.insert(to);
+        }
+    }
+    Ok(())
+}
+
+fn validate_nonce<T: Transaction>(account: &AccountInfo, txn: &T) -> Result<(), TxValidationError> {
+    if txn.nonce() < account.nonce {
+        return Err(TxValidationError::NonceTooLow {
+            tx: txn.nonce(),
+            state: account.nonce,
+        });
+    }
+    Ok(())
+}
+
+fn validate_balance(account: &AccountInfo, cost: U256) -> Result<(), TxValidationError> {
+    if cost > account.balance {
+        return Err(TxValidationError::InsufficientFunds {
+            cost,
+            balance: account.balance,
+        });
+    }
+    Ok(())
+}
+
+/// The most a transaction can cost its sender: `gas_limit * max_fee_per_gas + value` plus the L1
+/// data fee
+fn tx_cost<T: Transaction>(
+    txn: &T,
+    data: &[u8],
+    l1_block_info: &mut L1BlockInfo,
+    spec_id: OpSpecId,
+) -> U256 {
+    let execution_cost = U256::from(txn.gas_limit())
+        .saturating_mul(U256::from(txn.max_fee_per_gas()))
+        .saturating_add(txn.value());
+    let l1_cost = l1_block_info.calculate_tx_l1_cost(data, spec_id);
+    execution_cost.saturating_add(l1_cost)
+}
+
+/// The kinds of bundles accepted by ingress, each with its own limits
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BundleKind {
+    /// Bundles sent with `eth_sendBundle`
+    Bundle,
+    /// Bundles sent with `eth_sendBackrunBundle`
+    Backrun,
+    /// Singleton bundles wrapping a transaction sent with `eth_sendRawTransaction`
+    RawTransaction,
+}
+
+impl fmt::Display for BundleKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bundle => write!(f, "Bundle"),
+            Self::Backrun => write!(f, "Backrun bundle"),
+            Self::RawTransaction => write!(f, "Raw transaction bundle"),
+        }
+    }
+}
+
+/// Transaction types of an `OpTxEnvelope`, used to configure which types ingress accepts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TxType {
+    Legacy,
+    Eip2930,
+    Eip1559,
+    Eip7702,
+    Deposit,
+}
+
+impl TxType {
+    /// Every type that can be allowed, deposits are always rejected
+    pub const ALL: [TxType; 4] = [
+        TxType::Legacy,
+        TxType::Eip2930,
+        TxType::Eip1559,
+        TxType::Eip7702,
+    ];
+}
+
+impl From<&OpTxEnvelope> for TxType {
+    fn from(envelope: &OpTxEnvelope) -> Self {
+        match envelope {
+            OpTxEnvelope::Legacy(_) => TxType::Legacy,
+            OpTxEnvelope::Eip2930(_) => TxType::Eip2930,
+            OpTxEnvelope::Eip1559(_) => TxType::Eip1559,
+            OpTxEnvelope::Eip7702(_) => TxType::Eip7702,
+            OpTxEnvelope::Deposit(_) => TxType::Deposit,
+        }
+    }
+}
+
+impl fmt::Display for TxType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Legacy => write!(f, "legacy"),
+            Self::Eip2930 => write!(f, "eip2930"),
+            Self::Eip1559 => write!(f, "eip1559"),
+            Self::Eip7702 => write!(f, "eip7702"),
+            Self::Deposit => write!(f, "deposit"),
+        }
+    }
+}
+
+impl Serialize for TxType {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl FromStr for TxType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "legacy" => Ok(TxType::Legacy),
+            "eip2930" => Ok(TxType::Eip2930),
+            "eip1559" => Ok(TxType::Eip1559),
+            "eip7702" => Ok(TxType::Eip7702),
+            _ => Err(format!(
+                "Invalid transaction type: '{s}'. Valid options: legacy, eip2930, eip1559, eip7702"
+            )),
+        }
     }
+}
+
+/// Transaction count and gas limits for one kind of bundle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BundleLimits {
+    pub min_txs: usize,
+    pub max_txs: usize,
+    pub max_gas: u64,
+}
+
+/// Limits every bundle has to satisfy before it is metered, built from `Config`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BundleValidationPolicy {
+    pub bundle: BundleLimits,
+    pub backrun: BundleLimits,
+    pub raw_transaction: BundleLimits,
+    /// Maximum gas limit of any single transaction
+    pub max_tx_gas: u64,
+    /// A bundle's max_timestamp must be at least this far in the future
+    pub min_time_window: Duration,
+    /// A bundle's max_timestamp must be at most this far in the future
+    pub max_time_window: Duration,
+    /// Transaction types accepted with `eth_sendRawTransaction`
+    pub raw_transaction_tx_types: Vec<TxType>,
+    /// Transaction types accepted in bundles and backrun bundles
+    pub bundle_tx_types: Vec<TxType>,
+    /// Maximum size of an encoded transaction in bytes
+    pub max_tx_size: usize,
+    /// A transaction's gas limit can't exceed the block gas limit
+    pub block_gas_limit: u64,
+}
 
-    // Partial transaction dropping is not supported, `dropping_tx_hashes` must be empty
-    if !bundle.dropping_tx_hashes.is_empty() {
-        return Err(EthApiError::InvalidParams(
-            "Partial transaction dropping is not supported".into(),
-        )
-        .into_rpc_err());
+impl From<&Config> for BundleValidationPolicy {
+    fn from(config: &Config) -> Self {
+        Self {
+            bundle: BundleLimits {
+                min_txs: 1,
+                max_txs: config.max_bundle_txs,
+                max_gas: config.max_bundle_gas,
+            },
+            backrun: BundleLimits {
+                // target + backrun
+                min_txs: 2,
+                max_txs: config.max_backrun_txs,
+                max_gas: config.max_backrun_gas_limit,
+            },
+            raw_transaction: BundleLimits {
+                min_txs: 1,
+                max_txs: 1,
+                max_gas: config.max_raw_tx_gas,
+            },
+            max_tx_gas: config.max_tx_gas,
+            min_time_window: Duration::from_secs(config.bundle_min_time_window_secs),
+            max_time_window: Duration::from_secs(config.bundle_max_time_window_secs),
+            raw_transaction_tx_types: config.raw_tx_allowed_types.clone(),
+            bundle_tx_types: config.bundle_allowed_tx_types.clone(),
+            max_tx_size: config.max_tx_size_bytes,
+            block_gas_limit: config.block_gas_limit,
+        }
+    }
+}
+
+impl BundleValidationPolicy {
+    pub const fn limits(&self, kind: BundleKind) -> &BundleLimits {
+        match kind {
+            BundleKind::Bundle => &self.bundle,
+            BundleKind::Backrun => &self.backrun,
+            BundleKind::RawTransaction => &self.raw_transaction,
+        }
     }
 
-    // revert protection: all transaction hashes must be in `reverting_tx_hashes`
-    let reverting_tx_hashes_set: HashSet<_> = bundle.reverting_tx_hashes.iter().collect();
-    let tx_hashes_set: HashSet<_> = tx_hashes.iter().collect();
-    if reverting_tx_hashes_set != tx_hashes_set {
-        return Err(EthApiError::InvalidParams(
-            "Revert protection is not supported. reverting_tx_hashes must include all hashes"
-                .into(),
-        )
-        .into_rpc_err());
+    /// Rejects deposit transactions, and transaction types that are not allowed for the given
+    /// kind of bundle
+    pub fn validate_tx_type(
+        &self,
+        kind: BundleKind,
+        envelope: &OpTxEnvelope,
+    ) -> Result<(), TxValidationError> {
+        let tx_type = TxType::from(envelope);
+        if tx_type == TxType::Deposit {
+            return Err(TxValidationError::DepositTransaction);
+        }
+
+        let allowed = match kind {
+            BundleKind::RawTransaction => &self.raw_transaction_tx_types,
+            BundleKind::Bundle | BundleKind::Backrun => &self.bundle_tx_types,
+        };
+        if !allowed.contains(&tx_type) {
+            return Err(TxValidationError::TxTypeNotAllowed { tx_type, kind });
+        }
+        Ok(())
+    }
+
+    /// Runs the stateless checks a node's transaction pool runs on a transaction:
+    /// - The encoded transaction is not larger than the max transaction size
+    /// - The priority fee is not higher than the max fee
+    /// - A contract creation's initcode is not larger than `MAX_INITCODE_SIZE`
+    /// - The gas limit covers the intrinsic gas and is not above the block gas limit
+    pub fn validate_intrinsic<T: Transaction>(
+        &self,
+        txn: &T,
+        data: &[u8],
+    ) -> Result<(), TxValidationError> {
+        if data.len() > self.max_tx_size {
+            return Err(TxValidationError::OversizedData {
+                size: data.len(),
+                max: self.max_tx_size,
+            });
+        }
+
+        if let Some(max_priority_fee_per_gas) = txn.max_priority_fee_per_gas()
+            && max_priority_fee_per_gas > txn.max_fee_per_gas()
+        {
+            return Err(TxValidationError::TipAboveFeeCap {
+                max_priority_fee_per_gas,
+                max_fee_per_gas: txn.max_fee_per_gas(),
+            });
+        }
+
+        if txn.is_create() && txn.input().len() > MAX_INITCODE_SIZE {
+            return Err(TxValidationError::InitcodeTooLarge {
+                size: txn.input().len(),
+                max: MAX_INITCODE_SIZE,
+            });
+        }
+
+        let intrinsic_gas = intrinsic_gas(txn);
+        if txn.gas_limit() < intrinsic_gas {
+            return Err(TxValidationError::IntrinsicGasTooLow {
+                gas_limit: txn.gas_limit(),
+                intrinsic_gas,
+            });
+        }
+
+        if txn.gas_limit() > self.block_gas_limit {
+            return Err(TxValidationError::GasLimitExceedsBlockGasLimit {
+                gas_limit: txn.gas_limit(),
+                block_gas_limit: self.block_gas_limit,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Checks the transaction count, the per-transaction gas limits and the total gas limit of a
+    /// bundle of the given kind
+    pub fn validate_limits(&self, kind: BundleKind, tx_gas_limits: &[u64]) -> RpcResult<()> {
+        let limits = self.limits(kind);
+        let txs_count = tx_gas_limits.len();
+        if txs_count < limits.min_txs {
+            return Err(EthApiError::InvalidParams(format!(
+                "{kind} must have at least {} transactions",
+                limits.min_txs
+            ))
+            .into_rpc_err());
+        }
+
+        if let Some(tx_gas) = tx_gas_limits.iter().find(|gas| **gas > self.max_tx_gas) {
+            return Err(EthApiError::InvalidParams(format!(
+                "Transaction gas limit exceeds max gas limit: {tx_gas} > {}",
+                self.max_tx_gas
+            ))
+            .into_rpc_err());
+        }
+
+        let total_gas = tx_gas_limits
+            .iter()
+            .fold(0u64, |total, gas| total.saturating_add(*gas));
+        if total_gas > limits.max_gas {
+            return Err(EthApiError::InvalidParams(format!(
+                "{kind} exceeds max gas limit: {total_gas} > {}",
+                limits.max_gas
+            ))
+            .into_rpc_err());
+        }
+
+        if txs_count > limits.max_txs {
+            return Err(EthApiError::InvalidParams(format!(
+                "{kind} exceeds max transaction count: {txs_count} > {}",
+                limits.max_txs
+            ))
+            .into_rpc_err());
+        }
+
+        Ok(())
+    }
+
+    /// Validates the properties of a bundle. A bundle is valid if it satisfies the following criteria:
+    /// - The bundle's max_timestamp is within the policy's time window
+    /// - The bundle's transaction count and gas limits are within the limits of its kind
+    /// - Every hash in `dropping_tx_hashes` must belong to a transaction in the bundle
+    /// - Every hash in `reverting_tx_hashes` must belong to a transaction in the bundle
+    pub fn validate_bundle(
+        &self,
+        kind: BundleKind,
+        bundle: &Bundle,
+        tx_gas_limits: &[u64],
+        tx_hashes: Vec<B256>,
+    ) -> RpcResult<()> {
+        if let Some(max_timestamp) = bundle.max_timestamp {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs();
+            if max_timestamp > now + self.max_time_window.as_secs() {
+                return Err(EthApiError::InvalidParams(format!(
+                    "Bundle max_timestamp cannot be more than {} seconds in the future",
+                    self.max_time_window.as_secs()
+                ))
+                .into_rpc_err());
+            }
+            if max_timestamp < now + self.min_time_window.as_secs() {
+                return Err(EthApiError::InvalidParams(format!(
+                    "Bundle max_timestamp must be at least {} seconds in the future",
+                    self.min_time_window.as_secs()
+                ))
+                .into_rpc_err());
+            }
+        }
+
+        self.validate_limits(kind, tx_gas_limits)?;
+
+        // droppable transactions must be part of the bundle
+        let tx_hashes_set: HashSet<_> = tx_hashes.iter().collect();
+        if let Some(unknown) = bundle
+            .dropping_tx_hashes
+            .iter()
+            .find(|hash| !tx_hashes_set.contains(hash))
+        {
+            return Err(EthApiError::InvalidParams(format!(
+                "dropping_tx_hashes contains a transaction not in the bundle: {unknown}"
+            ))
+            .into_rpc_err());
+        }
+
+        // transactions allowed to revert must be part of the bundle
+        if let Some(unknown) = bundle
+            .reverting_tx_hashes
+            .iter()
+            .find(|hash| !tx_hashes_set.contains(hash))
+        {
+            return Err(EthApiError::InvalidParams(format!(
+                "reverting_tx_hashes contains a transaction not in the bundle: {unknown}"
+            ))
+            .into_rpc_err());
+        }
+
+        Ok(())
     }
+}
 
+/// Rejects a bundle when one of its protected transactions reverted while metering it, or when
+/// the node didn't report whether it did
+pub fn validate_revert_protection(
+    protected_tx_hashes: &[TxHash],
+    meter_bundle_response: &MeterBundleResponse,
+) -> Result<(), IngressError> {
+    for tx_hash in protected_tx_hashes {
+        let reverted = meter_bundle_response
+            .results
+            .iter()
+            .find(|result| result.tx_hash == *tx_hash)
+            .and_then(|result| result.reverted);
+        match reverted {
+            Some(false) => {}
+            Some(true) => return Err(IngressError::ProtectedTransactionReverted(*tx_hash)),
+            None => {
+                return Err(IngressError::MeteringUnavailable(format!(
+                    "node did not report whether transaction {tx_hash} reverted"
+                )));
+            }
+        }
+    }
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use alloy_consensus::Sealed;
     use alloy_consensus::SignableTransaction;
     use alloy_consensus::TxEip1559;
+    use alloy_consensus::TxLegacy;
+    use alloy_consensus::private::alloy_eips::eip2930::{AccessList, AccessListItem};
     use alloy_consensus::transaction::SignerRecoverable;
-    use alloy_primitives::Bytes;
     use alloy_primitives::bytes;
+    use alloy_primitives::{Bytes, TxKind};
     use alloy_signer_local::PrivateKeySigner;
-    use op_alloy_consensus::OpTxEnvelope;
+    use op_alloy_consensus::TxDeposit;
     use op_alloy_network::TxSignerSync;
     use op_alloy_network::eip2718::Encodable2718;
     use std::time::{SystemTime, UNIX_EPOCH};
+    use tips_core::types::TransactionResult;
+
+    fn test_policy() -> BundleValidationPolicy {
+        BundleValidationPolicy {
+            bundle: BundleLimits {
+                min_txs: 1,
+                max_txs: 3,
+                max_gas: 25_000_000,
+            },
+            backrun: BundleLimits {
+                min_txs: 2,
+                max_txs: 5,
+                max_gas: 5_000_000,
+            },
+            raw_transaction: BundleLimits {
+                min_txs: 1,
+                max_txs: 1,
+                max_gas: 25_000_000,
+            },
+            max_tx_gas: 16_000_000,
+            min_time_window: Duration::from_secs(0),
+            max_time_window: Duration::from_secs(3600),
+            raw_transaction_tx_types: TxType::ALL.to_vec(),
+            bundle_tx_types: vec![TxType::Legacy, TxType::Eip2930, TxType::Eip1559],
+            max_tx_size: 128 * 1024,
+            block_gas_limit: 30_000_000,
+        }
+    }
 
     #[tokio::test]
     async fn test_err_bundle_max_timestamp_too_far_in_the_future() {
@@ -174,9 +879,9 @@ mod tests {
             ..Default::default()
         };
         assert_eq!(
-            validate_bundle(&bundle, 0, vec![]),
+            test_policy().validate_bundle(BundleKind::Bundle, &bundle, &[], vec![]),
             Err(EthApiError::InvalidParams(
-                "Bundle cannot be more than 1 hour in the future".into()
+                "Bundle max_timestamp cannot be more than 3600 seconds in the future".into()
             )
             .into_rpc_err())
         );
@@ -191,7 +896,7 @@ mod tests {
         // Create transactions that collectively exceed MAX_BUNDLE_GAS (25M)
         // Each transaction uses 4M gas, so 8 transactions = 32M gas > 25M limit
         let gas = 4_000_000;
-        let mut total_gas = 0u64;
+        let mut tx_gas_limits = vec![];
         for _ in 0..8 {
             let mut tx = TxEip1559 {
                 chain_id: 1,
@@ -204,7 +909,7 @@ mod tests {
                 access_list: Default::default(),
                 input: bytes!("").clone(),
             };
-            total_gas = total_gas.saturating_add(gas);
+            tx_gas_limits.push(gas);
 
             let signature = signer.sign_transaction_sync(&mut tx).unwrap();
             let envelope = OpTxEnvelope::Eip1559(tx.into_signed(signature));
@@ -227,11 +932,12 @@ mod tests {
         };
 
         // Test should fail due to exceeding gas limit
-        let result = validate_bundle(&bundle, total_gas, tx_hashes);
+        let result =
+            test_policy().validate_bundle(BundleKind::Bundle, &bundle, &tx_gas_limits, tx_hashes);
         assert!(result.is_err());
         if let Err(e) = result {
             let error_message = format!("{e:?}");
-            assert!(error_message.contains("Bundle gas limit exceeds maximum allowed"));
+            assert!(error_message.contains("Bundle exceeds max gas limit"));
         }
     }
 
@@ -242,7 +948,7 @@ mod tests {
         let mut tx_hashes = vec![];
 
         let gas = 4_000_000;
-        let mut total_gas = 0u64;
+        let mut tx_gas_limits = vec![];
         for _ in 0..4 {
             let mut tx = TxEip1559 {
                 chain_id: 1,
@@ -255,7 +961,7 @@ mod tests {
                 access_list: Default::default(),
                 input: bytes!("").clone(),
             };
-            total_gas = total_gas.saturating_add(gas);
+            tx_gas_limits.push(gas);
 
             let signature = signer.sign_transaction_sync(&mut tx).unwrap();
             let envelope = OpTxEnvelope::Eip1559(tx.into_signed(signature));
@@ -278,27 +984,51 @@ mod tests {
         };
 
         // Test should fail due to exceeding gas limit
-        let result = validate_bundle(&bundle, total_gas, tx_hashes);
+        let result =
+            test_policy().validate_bundle(BundleKind::Bundle, &bundle, &tx_gas_limits, tx_hashes);
         assert!(result.is_err());
         if let Err(e) = result {
             let error_message = format!("{e:?}");
-            assert!(error_message.contains("Bundle can only contain 3 transactions"));
+            assert!(error_message.contains("Bundle exceeds max transaction count"));
         }
     }
 
     #[tokio::test]
-    async fn test_err_bundle_partial_transaction_dropping_not_supported() {
+    async fn test_err_bundle_dropping_tx_hash_not_in_bundle() {
+        let tx_hash = B256::random();
+        let unknown = B256::random();
         let bundle = Bundle {
             txs: vec![],
-            dropping_tx_hashes: vec![B256::random()],
+            reverting_tx_hashes: vec![tx_hash],
+            dropping_tx_hashes: vec![tx_hash, unknown],
             ..Default::default()
         };
         assert_eq!(
-            validate_bundle(&bundle, 0, vec![]),
-            Err(
-                EthApiError::InvalidParams("Partial transaction dropping is not supported".into())
-                    .into_rpc_err()
-            )
+            test_policy().validate_bundle(BundleKind::Bundle, &bundle, &[21_000], vec![tx_hash]),
+            Err(EthApiError::InvalidParams(format!(
+                "dropping_tx_hashes contains a transaction not in the bundle: {unknown}"
+            ))
+            .into_rpc_err())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_bundle_with_dropping_tx_hashes() {
+        let tx_hashes = vec![B256::random(), B256::random()];
+        let bundle = Bundle {
+            txs: vec![],
+            reverting_tx_hashes: tx_hashes.clone(),
+            dropping_tx_hashes: vec![tx_hashes[1]],
+            ..Default::default()
+        };
+        assert_eq!(
+            test_policy().validate_bundle(
+                BundleKind::Bundle,
+                &bundle,
+                &[21_000, 21_000],
+                tx_hashes
+            ),
+            Ok(())
         );
     }
 
@@ -309,7 +1039,7 @@ mod tests {
         let mut tx_hashes = vec![];
 
         let gas = 4_000_000;
-        let mut total_gas = 0u64;
+        let mut tx_gas_limits = vec![];
         for _ in 0..4 {
             let mut tx = TxEip1559 {
                 chain_id: 1,
@@ -322,7 +1052,7 @@ mod tests {
                 access_list: Default::default(),
                 input: bytes!("").clone(),
             };
-            total_gas = total_gas.saturating_add(gas);
+            tx_gas_limits.push(gas);
 
             let signature = signer.sign_transaction_sync(&mut tx).unwrap();
             let envelope = OpTxEnvelope::Eip1559(tx.into_signed(signature));
@@ -345,12 +1075,653 @@ mod tests {
         };
 
         // Test should fail due to exceeding gas limit
-        let result = validate_bundle(&bundle, total_gas, tx_hashes);
+        let result =
+            test_policy().validate_bundle(BundleKind::Bundle, &bundle, &tx_gas_limits, tx_hashes);
         assert!(result.is_err());
         if let Err(e) = result {
             let error_message = format!("{e:?}");
-            assert!(error_message.contains("Bundle can only contain 3 transactions"));
+            assert!(error_message.contains("Bundle exceeds max transaction count"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_err_bundle_reverting_tx_hash_not_in_bundle() {
+        let tx_hash = B256::random();
+        let unknown = B256::random();
+        let bundle = Bundle {
+            txs: vec![],
+            reverting_tx_hashes: vec![unknown],
+            ..Default::default()
+        };
+        assert_eq!(
+            test_policy().validate_bundle(BundleKind::Bundle, &bundle, &[21_000], vec![tx_hash]),
+            Err(EthApiError::InvalidParams(format!(
+                "reverting_tx_hashes contains a transaction not in the bundle: {unknown}"
+            ))
+            .into_rpc_err())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_bundle_with_protected_transactions() {
+        let tx_hashes = vec![B256::random(), B256::random()];
+        let bundle = Bundle {
+            txs: vec![],
+            reverting_tx_hashes: vec![tx_hashes[0]],
+            ..Default::default()
+        };
+        assert_eq!(
+            test_policy().validate_bundle(
+                BundleKind::Bundle,
+                &bundle,
+                &[21_000, 21_000],
+                tx_hashes
+            ),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_validate_revert_protection() {
+        let protected = B256::random();
+        let reverting = B256::random();
+        let result = |tx_hash, reverted| TransactionResult {
+            coinbase_diff: U256::ZERO,
+            eth_sent_to_coinbase: U256::ZERO,
+            from_address: Address::ZERO,
+            gas_fees: U256::ZERO,
+            gas_price: U256::ZERO,
+            gas_used: 21_000,
+            to_address: None,
+            tx_hash,
+            value: U256::ZERO,
+            execution_time_us: 0,
+            reverted: Some(reverted),
+        };
+
+        let mut response = MeterBundleResponse {
+            results: vec![result(protected, false), result(reverting, true)],
+            ..Default::default()
+        };
+        assert_eq!(validate_revert_protection(&[protected], &response), Ok(()));
+
+        response.results[0].reverted = Some(true);
+        assert_eq!(
+            validate_revert_protection(&[protected], &response),
+            Err(IngressError::ProtectedTransactionReverted(protected))
+        );
+
+        // a node that doesn't report reverts can't vouch for protected transactions
+        response.results[0].reverted = None;
+        assert!(matches!(
+            validate_revert_protection(&[protected], &response),
+            Err(IngressError::MeteringUnavailable(_))
+        ));
+        response.results[1].reverted = None;
+        assert!(validate_revert_protection(&[], &response).is_ok());
+
+        // nor for protected transactions it has no result for
+        response.results = vec![result(reverting, true)];
+        assert!(matches!(
+            validate_revert_protection(&[protected], &response),
+            Err(IngressError::MeteringUnavailable(_))
+        ));
+    }
+
+    #[test]
+    fn test_validate_backrun_bundle_rejects_invalid() {
+        let policy = test_policy();
+
+        // Too few transactions (need at least 2: target + backrun)
+        let result = policy.validate_limits(BundleKind::Backrun, &[21000]);
+        assert!(result.is_err());
+        assert!(
+            result
+                .unwrap_err()
+                .message()
+                .contains("at least 2 transactions")
+        );
+
+        // Exceeds max tx count
+        let result = policy.validate_limits(BundleKind::Backrun, &[21000; 6]);
+        assert!(result.is_err());
+        assert!(
+            result
+                .unwrap_err()
+                .message()
+                .contains("exceeds max transaction count")
+        );
+
+        // Exceeds max gas limit
+        let result = policy.validate_limits(BundleKind::Backrun, &[3000000, 3000000]);
+        assert!(result.is_err());
+        assert!(
+            result
+                .unwrap_err()
+                .message()
+                .contains("exceeds max gas limit")
+        );
+
+        assert!(
+            policy
+                .validate_limits(BundleKind::Backrun, &[21000; 5])
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_validate_limits_per_kind() {
+        let policy = test_policy();
+
+        // a single transaction over the per-transaction cap
+        let result = policy.validate_limits(BundleKind::Bundle, &[16_000_001]);
+        assert!(
+            result
+                .unwrap_err()
+                .message()
+                .contains("Transaction gas limit exceeds max gas limit")
+        );
+
+        // raw transactions are singleton bundles
+        assert!(
+            policy
+                .validate_limits(BundleKind::RawTransaction, &[21000])
+                .is_ok()
+        );
+        assert!(
+            policy
+                .validate_limits(BundleKind::RawTransaction, &[21000, 21000])
+                .is_err()
+        );
+
+        // the same transactions can fit one kind and not another
+        assert!(
+            policy
+                .validate_limits(BundleKind::Bundle, &[21000; 4])
+                .is_err()
+        );
+        assert!(
+            policy
+                .validate_limits(BundleKind::Backrun, &[21000; 4])
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_err_bundle_max_timestamp_too_soon() {
+        let policy = BundleValidationPolicy {
+            min_time_window: Duration::from_secs(10),
+            ..test_policy()
+        };
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let tx_hash = B256::random();
+        let bundle = |max_timestamp| Bundle {
+            max_timestamp: Some(max_timestamp),
+            reverting_tx_hashes: vec![tx_hash],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            policy.validate_bundle(
+                BundleKind::Bundle,
+                &bundle(now + 5),
+                &[21000],
+                vec![tx_hash]
+            ),
+            Err(EthApiError::InvalidParams(
+                "Bundle max_timestamp must be at least 10 seconds in the future".into()
+            )
+            .into_rpc_err())
+        );
+        assert!(
+            policy
+                .validate_bundle(
+                    BundleKind::Bundle,
+                    &bundle(now + 60),
+                    &[21000],
+                    vec![tx_hash]
+                )
+                .is_ok()
+        );
+    }
+
+    fn create_signed_tx(nonce: u64, value: U256) -> (OpTxEnvelope, Bytes) {
+        sign_tx(&PrivateKeySigner::random(), nonce, value, Address::random())
+    }
+
+    fn create_recovered_tx(
+        signer: &PrivateKeySigner,
+        nonce: u64,
+        value: U256,
+        to: Address,
+    ) -> (Recovered<OpTxEnvelope>, Bytes) {
+        let (envelope, data) = sign_tx(signer, nonce, value, to);
+        (Recovered::new_unchecked(envelope, signer.address()), data)
+    }
+
+    fn sign_tx(
+        signer: &PrivateKeySigner,
+        nonce: u64,
+        value: U256,
+        to: Address,
+    ) -> (OpTxEnvelope, Bytes) {
+        let mut tx = TxEip1559 {
+            chain_id: 1,
+            nonce,
+            gas_limit: 21_000,
+            max_fee_per_gas: 100,
+            max_priority_fee_per_gas: 10,
+            to: to.into(),
+            value,
+            access_list: Default::default(),
+            input: bytes!("").clone(),
+        };
+        let signature = signer.sign_transaction_sync(&mut tx).unwrap();
+        let envelope = OpTxEnvelope::Eip1559(tx.into_signed(signature));
+        let data = Bytes::from(envelope.encoded_2718());
+        (envelope, data)
+    }
+
+    fn account(nonce: u64, balance: U256) -> AccountInfo {
+        AccountInfo {
+            balance,
+            nonce,
+            code_hash: B256::ZERO,
+        }
+    }
+
+    #[test]
+    fn test_validate_tx() {
+        let (tx, data) = create_signed_tx(5, U256::from(1_000));
+        // 21_000 gas * 100 wei + 1_000 wei of value
+        let cost = U256::from(2_101_000);
+        let mut l1_block_info = L1BlockInfo::default();
+
+        assert!(
+            validate_tx(
+                &account(5, cost),
+                &tx,
+                &data,
+                &mut l1_block_info,
+                OpSpecId::ISTHMUS
+            )
+            .is_ok()
+        );
+        assert!(
+            validate_tx(
+                &account(3, cost),
+                &tx,
+                &data,
+                &mut l1_block_info,
+                OpSpecId::ISTHMUS
+            )
+            .is_ok()
+        );
+
+        assert_eq!(
+            validate_tx(
+                &account(6, cost),
+                &tx,
+                &data,
+                &mut l1_block_info,
+                OpSpecId::ISTHMUS
+            ),
+            Err(TxValidationError::NonceTooLow { tx: 5, state: 6 })
+        );
+
+        assert_eq!(
+            validate_tx(
+                &account(5, cost - U256::from(1)),
+                &tx,
+                &data,
+                &mut l1_block_info,
+                OpSpecId::ISTHMUS,
+            ),
+            Err(TxValidationError::InsufficientFunds {
+                cost,
+                balance: cost - U256::from(1),
+            })
+        );
+    }
+
+    #[test]
+    fn test_validate_tx_includes_l1_fee() {
+        let (tx, data) = create_signed_tx(0, U256::ZERO);
+        let execution_cost = U256::from(2_100_000);
+        let mut l1_block_info = L1BlockInfo {
+            l1_base_fee: U256::from(1_000_000_000),
+            l1_base_fee_scalar: U256::from(1_000_000),
+            ..Default::default()
+        };
+
+        let l1_cost = l1_block_info
+            .clone()
+            .calculate_tx_l1_cost(&data, OpSpecId::ISTHMUS);
+        assert!(l1_cost > U256::ZERO);
+
+        assert_eq!(
+            validate_tx(
+                &account(0, execution_cost),
+                &tx,
+                &data,
+                &mut l1_block_info,
+                OpSpecId::ISTHMUS
+            ),
+            Err(TxValidationError::InsufficientFunds {
+                cost: execution_cost + l1_cost,
+                balance: execution_cost,
+            })
+        );
+
+        assert!(
+            validate_tx(
+                &account(0, execution_cost + l1_cost),
+                &tx,
+                &data,
+                &mut l1_block_info,
+                OpSpecId::ISTHMUS
+            )
+            .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_l1_fee_spec() {
+        assert!(chain_hardforks(1337).is_none());
+        assert_eq!(l1_fee_spec(None, 0), OpSpecId::ISTHMUS);
+
+        let base_mainnet = chain_hardforks(8453);
+        assert!(base_mainnet.is_some());
+        // Between Holocene (2025-01-09) and Isthmus (2025-05-09) on Base mainnet
+        assert_eq!(
+            l1_fee_spec(base_mainnet.as_ref(), 1_740_000_000),
+            OpSpecId::HOLOCENE
+        );
+        assert_eq!(
+            l1_fee_spec(base_mainnet.as_ref(), 1_750_000_000),
+            OpSpecId::ISTHMUS
+        );
+    }
+
+    #[test]
+    fn test_validate_bundle_txs_accumulates_spend_per_sender() {
+        let signer = PrivateKeySigner::random();
+        let txs = vec![
+            create_recovered_tx(&signer, 5, U256::from(1_000), Address::random()),
+            create_recovered_tx(&signer, 6, U256::from(1_000), Address::random()),
+        ];
+        // Each transaction costs 21_000 gas * 100 wei + 1_000 wei of value
+        let cost = U256::from(2 * 2_101_000);
+        let mut l1_block_info = L1BlockInfo::default();
+
+        let accounts = HashMap::from([(signer.address(), account(5, cost))]);
+        assert!(
+            validate_bundle_txs(&accounts, &txs, &mut l1_block_info, OpSpecId::ISTHMUS).is_ok()
+        );
+
+        let accounts = HashMap::from([(signer.address(), account(5, cost - U256::from(1)))]);
+        assert_eq!(
+            validate_bundle_txs(&accounts, &txs, &mut l1_block_info, OpSpecId::ISTHMUS),
+            Err(TxValidationError::InsufficientFunds {
+                cost,
+                balance: cost - U256::from(1),
+            })
+        );
+    }
+
+    #[test]
+    fn test_validate_bundle_txs_skips_balance_of_funded_senders() {
+        let funder = PrivateKeySigner::random();
+        let funded = PrivateKeySigner::random();
+        let funding = create_recovered_tx(&funder, 0, U256::from(1_000_000_000), funded.address());
+        let spend = create_recovered_tx(&funded, 0, U256::from(1_000), Address::random());
+        let mut l1_block_info = L1BlockInfo::default();
+        let accounts = HashMap::from([
+            (funder.address(), account(0, U256::MAX)),
+            (funded.address(), account(0, U256::ZERO)),
+        ]);
+
+        let txs = vec![funding.clone(), spend.clone()];
+        assert!(
+            validate_bundle_txs(&accounts, &txs, &mut l1_block_info, OpSpecId::ISTHMUS).is_ok()
+        );
+
+        // Only transfers earlier in the bundle fund a sender
+        let txs = vec![spend, funding];
+        assert!(matches!(
+            validate_bundle_txs(&accounts, &txs, &mut l1_block_info, OpSpecId::ISTHMUS),
+            Err(TxValidationError::InsufficientFunds { .. })
+        ));
+
+        // Nonces of funded senders are still checked
+        let accounts = HashMap::from([
+            (funder.address(), account(0, U256::MAX)),
+            (funded.address(), account(1, U256::ZERO)),
+        ]);
+        let stale = create_recovered_tx(&funded, 0, U256::ZERO, Address::random());
+        let funding = create_recovered_tx(&funder, 0, U256::from(1_000), funded.address());
+        assert_eq!(
+            validate_bundle_txs(
+                &accounts,
+                &[funding, stale],
+                &mut l1_block_info,
+                OpSpecId::ISTHMUS
+            ),
+            Err(TxValidationError::NonceTooLow { tx: 0, state: 1 })
+        );
+    }
+
+    #[test]
+    fn test_validate_chain_id() {
+        let (tx, _) = create_signed_tx(0, U256::ZERO);
+
+        assert_eq!(validate_chain_id(&tx, 1, false), Ok(()));
+        assert_eq!(
+            validate_chain_id(&tx, 8453, false),
+            Err(TxValidationError::WrongChainId {
+                expected: 8453,
+                actual: 1,
+            })
+        );
+        // allowing unprotected transactions doesn't allow other chains
+        assert!(validate_chain_id(&tx, 8453, true).is_err());
+    }
+
+    #[test]
+    fn test_validate_chain_id_unprotected_legacy_tx() {
+        let signer = PrivateKeySigner::random();
+        let mut tx = TxLegacy {
+            chain_id: None,
+            nonce: 0,
+            gas_price: 100,
+            gas_limit: 21_000,
+            to: Address::random().into(),
+            value: U256::ZERO,
+            input: bytes!("").clone(),
+        };
+        let signature = signer.sign_transaction_sync(&mut tx).unwrap();
+        let envelope = OpTxEnvelope::Legacy(tx.into_signed(signature));
+
+        assert_eq!(
+            validate_chain_id(&envelope, 1, false),
+            Err(TxValidationError::UnprotectedTransaction)
+        );
+        assert_eq!(validate_chain_id(&envelope, 1, true), Ok(()));
+    }
+
+    #[test]
+    fn test_validate_tx_type() {
+        let mut policy = test_policy();
+        let (tx, _) = create_signed_tx(0, U256::ZERO);
+        assert_eq!(
+            policy.validate_tx_type(BundleKind::RawTransaction, &tx),
+            Ok(())
+        );
+        assert_eq!(policy.validate_tx_type(BundleKind::Bundle, &tx), Ok(()));
+
+        policy.bundle_tx_types = vec![TxType::Legacy];
+        assert_eq!(
+            policy.validate_tx_type(BundleKind::Backrun, &tx),
+            Err(TxValidationError::TxTypeNotAllowed {
+                tx_type: TxType::Eip1559,
+                kind: BundleKind::Backrun,
+            })
+        );
+        assert_eq!(
+            policy.validate_tx_type(BundleKind::RawTransaction, &tx),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_validate_tx_type_rejects_deposits() {
+        let deposit =
+            OpTxEnvelope::Deposit(Sealed::new_unchecked(TxDeposit::default(), B256::ZERO));
+        for kind in [
+            BundleKind::Bundle,
+            BundleKind::Backrun,
+            BundleKind::RawTransaction,
+        ] {
+            assert_eq!(
+                test_policy().validate_tx_type(kind, &deposit),
+                Err(TxValidationError::DepositTransaction)
+            );
+        }
+    }
+
+    fn create_call(input: Bytes) -> TxEip1559 {
+        TxEip1559 {
+            chain_id: 1,
+            nonce: 0,
+            gas_limit: 21_000,
+            max_fee_per_gas: 100,
+            max_priority_fee_per_gas: 10,
+            to: Address::random().into(),
+            value: U256::ZERO,
+            access_list: Default::default(),
+            input,
+        }
+    }
+
+    #[test]
+    fn test_intrinsic_gas() {
+        assert_eq!(intrinsic_gas(&create_call(Bytes::new())), 21_000);
+
+        let mut access_list_call = create_call(Bytes::new());
+        access_list_call.access_list = AccessList(vec![AccessListItem {
+            address: Address::random(),
+            storage_keys: vec![B256::ZERO, B256::random()],
+        }]);
+        assert_eq!(intrinsic_gas(&access_list_call), 21_000 + 2_400 + 2 * 1_900);
+
+        let mut create = create_call(Bytes::from(vec![1u8; 33]));
+        create.to = TxKind::Create;
+        // two initcode words
+        assert_eq!(intrinsic_gas(&create), 53_000 + 33 * 16 + 2 * 2);
+
+        // calldata of a plain call pays the EIP-7623 floor: 2 zero bytes and 2 non-zero bytes
+        // are 10 tokens
+        assert_eq!(
+            intrinsic_gas(&create_call(bytes!("00000101"))),
+            21_000 + 10 * 10
+        );
+    }
+
+    #[test]
+    fn test_validate_intrinsic() {
+        let policy = test_policy();
+        let tx = create_call(Bytes::new());
+        assert_eq!(policy.validate_intrinsic(&tx, &[0u8; 100]), Ok(()));
+
+        let oversized = Bytes::from(vec![0u8; 128 * 1024 + 1]);
+        assert_eq!(
+            policy.validate_intrinsic(&tx, &oversized),
+            Err(TxValidationError::OversizedData {
+                size: 128 * 1024 + 1,
+                max: 128 * 1024,
+            })
+        );
+
+        let mut tip_above_fee_cap = tx.clone();
+        tip_above_fee_cap.max_priority_fee_per_gas = 101;
+        assert_eq!(
+            policy.validate_intrinsic(&tip_above_fee_cap, &[]),
+            Err(TxValidationError::TipAboveFeeCap {
+                max_priority_fee_per_gas: 101,
+                max_fee_per_gas: 100,
+            })
+        );
+
+        let mut large_initcode = create_call(Bytes::from(vec![0u8; MAX_INITCODE_SIZE + 1]));
+        large_initcode.to = TxKind::Create;
+        large_initcode.gas_limit = 10_000_000;
+        assert_eq!(
+            policy.validate_intrinsic(&large_initcode, &[]),
+            Err(TxValidationError::InitcodeTooLarge {
+                size: MAX_INITCODE_SIZE + 1,
+                max: MAX_INITCODE_SIZE,
+            })
+        );
+
+        let mut intrinsic_gas_too_low = create_call(bytes!("01"));
+        assert_eq!(
+            policy.validate_intrinsic(&intrinsic_gas_too_low, &[]),
+            Err(TxValidationError::IntrinsicGasTooLow {
+                gas_limit: 21_000,
+                intrinsic_gas: 21_040,
+            })
+        );
+        intrinsic_gas_too_low.gas_limit = 21_040;
+        assert_eq!(
+            policy.validate_intrinsic(&intrinsic_gas_too_low, &[]),
+            Ok(())
+        );
+
+        let mut above_block_gas_limit = tx.clone();
+        above_block_gas_limit.gas_limit = 30_000_001;
+        assert_eq!(
+            policy.validate_intrinsic(&above_block_gas_limit, &[]),
+            Err(TxValidationError::GasLimitExceedsBlockGasLimit {
+                gas_limit: 30_000_001,
+                block_gas_limit: 30_000_000,
+            })
+        );
+    }
+
+    #[test]
+    fn test_intrinsic_errors_have_distinct_codes() {
+        let errors = [
+            TxValidationError::OversizedData { size: 0, max: 0 },
+            TxValidationError::IntrinsicGasTooLow {
+                gas_limit: 0,
+                intrinsic_gas: 0,
+            },
+            TxValidationError::InitcodeTooLarge { size: 0, max: 0 },
+            TxValidationError::TipAboveFeeCap {
+                max_priority_fee_per_gas: 0,
+                max_fee_per_gas: 0,
+            },
+            TxValidationError::GasLimitExceedsBlockGasLimit {
+                gas_limit: 0,
+                block_gas_limit: 0,
+            },
+        ];
+        let codes: HashSet<_> = errors
+            .into_iter()
+            .map(|error| error.into_rpc_err().code())
+            .collect();
+        assert_eq!(codes.len(), 5);
+    }
+
+    #[test]
+    fn test_parse_tx_type() {
+        for tx_type in TxType::ALL {
+            assert_eq!(tx_type.to_string().parse::<TxType>(), Ok(tx_type));
         }
+        assert!("deposit".parse::<TxType>().is_err());
     }
 
     #[tokio::test]
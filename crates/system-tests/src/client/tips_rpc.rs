@@ -2,9 +2,9 @@ use alloy_network::Network;
 use alloy_primitives::{Bytes, TxHash};
 use alloy_provider::{Provider, RootProvider};
 use anyhow::Result;
-use tips_core::{Bundle, BundleHash, CancelBundle};
+use tips_core::{Bundle, BundleHash, CallBundleResponse, CancelBundle};
 
-/// Client for TIPS-specific RPC methods (eth_sendBundle, eth_cancelBundle)
+/// Client for TIPS-specific RPC methods (eth_sendBundle, eth_callBundle, eth_cancelBundle)
 ///
 /// Wraps a RootProvider to add TIPS functionality while preserving access
 /// to standard Ethereum JSON-RPC methods via provider().
@@ -40,7 +40,14 @@ impl<N: Network> TipsRpcClient<N> {
             .map_err(Into::into)
     }
 
-    pub async fn cancel_bundle(&self, request: CancelBundle) -> Result<bool> {
+    pub async fn call_bundle(&self, bundle: Bundle) -> Result<CallBundleResponse> {
+        self.provider
+            .raw_request("eth_callBundle".into(), [bundle])
+            .await
+            .map_err(Into::into)
+    }
+
+    pub async fn cancel_bundle(&self, request: CancelBundle) -> Result<()> {
         self.provider
             .raw_request("eth_cancelBundle".into(), [request])
             .await